@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How the delay between retries grows.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackoffStrategy {
+    /// Always wait the base delay.
+    Fixed,
+    /// Wait the base delay times the number of the retry.
+    Linear,
+    /// Double the delay after every retry.
+    Exponential,
+}
+
+/// Delays between the retries of a failing operation, up to a cap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    pub strategy: BackoffStrategy,
+    pub base: Duration,
+    pub cap: Duration,
+    /// Retries made before waiting for the next scheduled check instead, zero retrying forever.
+    pub max_attempts: u32,
+}
+
+impl Backoff {
+    /// Returns the delay before the provided retry, counting from one, or `None` if no retry
+    /// should be made.
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if self.base.is_zero() || attempt == 0 || (self.max_attempts > 0 && attempt > self.max_attempts) {
+            return None;
+        }
+
+        let delay = match self.strategy {
+            BackoffStrategy::Fixed => self.base,
+            BackoffStrategy::Linear => self.base.saturating_mul(attempt),
+            BackoffStrategy::Exponential => self.base.saturating_mul(1 << (attempt - 1).min(16)),
+        };
+
+        Some(delay.min(self.cap))
+    }
+}
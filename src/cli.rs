@@ -0,0 +1,198 @@
+use clap::{Parser, Subcommand};
+use clap_complete::Shell;
+use std::path::PathBuf;
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::config::ConfigOverrides;
+use crate::logging::{LogFormat, LogTimestamps};
+
+#[derive(Parser)]
+#[command(version, about = "Updates Cloudflare on local public IP changes.")]
+pub struct Cli {
+    /// Show a live dashboard while running the updater.
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Fork into the background after loading the configuration.
+    #[cfg(unix)]
+    #[arg(long, conflicts_with = "tui")]
+    pub daemon: bool,
+
+    /// Write the PID of the updater to the provided file.
+    #[arg(long)]
+    pub pidfile: Option<PathBuf>,
+
+    /// Stop the updater after running for the provided number of seconds.
+    #[arg(long, conflicts_with = "tui")]
+    pub max_runtime: Option<u64>,
+
+    /// Stop the updater at the provided RFC 3339 timestamp, such as `2025-01-31T18:00:00Z`.
+    #[arg(long, conflicts_with = "tui", value_parser = parse_timestamp)]
+    pub until: Option<OffsetDateTime>,
+
+    /// Write a JSON object on stdout for every check, detected IP, applied update and error.
+    #[arg(long, conflicts_with = "tui")]
+    pub json_events: bool,
+
+    /// Also report warnings and errors to the Windows Event Log.
+    #[cfg(windows)]
+    #[arg(long, global = true)]
+    pub event_log: bool,
+
+    /// Only log warnings and errors.
+    #[arg(long, short, global = true)]
+    pub quiet: bool,
+
+    /// Never color log output. Colors are also disabled when `NO_COLOR` is set or stderr is
+    /// not a terminal.
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Layout of log lines.
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Full)]
+    pub log_format: LogFormat,
+
+    /// Time zone of log timestamps.
+    #[arg(long, global = true, value_enum, default_value_t = LogTimestamps::Utc)]
+    pub log_timestamps: LogTimestamps,
+
+    /// Path of the configuration file.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Seconds to wait between checks, overriding the configuration.
+    #[arg(long, global = true)]
+    pub wait_duration: Option<u64>,
+
+    /// Cloudflare zone ID, overriding the configuration.
+    #[arg(long, global = true)]
+    pub zone_id: Option<String>,
+
+    /// Cloudflare DNS record ID, overriding the configuration.
+    #[arg(long, global = true)]
+    pub dns_record_id: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Lists all DNS records in the configured zone.
+    ListRecords {
+        /// Print the records as JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Creates a record with the current public IP in the configured zone and adds it to the
+    /// configuration file.
+    CreateRecord {
+        /// Name of the record, asked for if not provided.
+        name: Option<String>,
+        /// Type of the record.
+        #[arg(long = "type", value_parser = ["A", "AAAA"], default_value = "A")]
+        record_type: String,
+        /// Time to live of the record in seconds, 1 letting Cloudflare choose.
+        #[arg(long, default_value_t = 1)]
+        ttl: i64,
+        /// Proxy traffic to the record through Cloudflare.
+        #[arg(long)]
+        proxied: bool,
+    },
+    /// Lists the records created by the updater which are no longer configured.
+    Cleanup {
+        /// Delete the listed records.
+        #[arg(long)]
+        delete: bool,
+    },
+    /// Prints the changes the updater would make to the configured records, without making them.
+    DryRun {
+        /// Print the changes as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Lists all zones accessible to the configured API token.
+    ListZones {
+        /// Print the zones as JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Shows the last known public IP, record content and recent errors.
+    Status {
+        /// Print the status as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Asks the running updater to check the public IP immediately.
+    CheckNow,
+    /// Pauses DNS updates of the running updater, which keeps monitoring the public IP.
+    Pause,
+    /// Resumes the running updater.
+    Resume,
+    /// Asks the running updater to reload its configuration file.
+    Reload,
+    /// Encrypts an API token read from stdin, printing the value to store as `api_token`.
+    EncryptToken,
+    /// Stores an API token read from stdin in the platform keyring.
+    SetToken,
+    /// Checks the configuration, connectivity, IP providers and API token permissions step by
+    /// step, printing a report.
+    Doctor,
+    /// Rewrites the configuration file to select the records configured by ID by their name
+    /// and type instead, as found on Cloudflare.
+    MigrateConfig {
+        /// Print the rewritten configuration instead of writing it.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Exports or imports the configuration.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Prints shell completions for the provided shell.
+    Completions {
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Prints the effective configuration, merged from every source, with secrets redacted.
+    Export,
+    /// Validates the provided configuration file, or the one read from stdin, and makes it
+    /// the configuration file.
+    Import {
+        file: Option<PathBuf>,
+    },
+}
+
+impl Cli {
+    /// Returns the configuration values provided on the command line.
+    pub fn config_overrides(&self) -> ConfigOverrides {
+        ConfigOverrides {
+            path: self.config.clone(),
+            wait_duration: self.wait_duration,
+            zone_id: self.zone_id.clone(),
+            dns_record_id: self.dns_record_id.clone(),
+        }
+    }
+
+    /// Returns how long the updater may run from the provided time, the earliest of
+    /// `--max-runtime` and `--until`, or `None` to run until stopped.
+    pub fn runtime_limit(&self, now: OffsetDateTime) -> Option<Duration> {
+        let until = self.until.map(|t| Duration::try_from(t - now).unwrap_or(Duration::ZERO));
+        let max_runtime = self.max_runtime.map(Duration::from_secs);
+
+        match (until, max_runtime) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+fn parse_timestamp(value: &str) -> Result<OffsetDateTime, String> {
+    OffsetDateTime::parse(value, &Rfc3339).map_err(|e| e.to_string())
+}
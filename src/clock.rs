@@ -0,0 +1,95 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+/// Source of time for the updater loop, so its scheduling can be simulated.
+pub trait Clock: Send + Sync {
+    /// Returns the current monotonic time.
+    fn now(&self) -> Instant;
+
+    /// Returns the current wall-clock time.
+    fn now_utc(&self) -> OffsetDateTime;
+
+    /// Waits for the provided duration.
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+/// Clock backed by the system time and tokio timers.
+#[derive(Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now_utc(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+}
+
+/// Clock whose sleeps return immediately after advancing the simulated time.
+pub struct SimulatedClock {
+    start: Instant,
+    start_utc: OffsetDateTime,
+    elapsed: Mutex<Duration>,
+    sleeps: Mutex<Vec<Duration>>,
+}
+
+impl Default for SimulatedClock {
+    fn default() -> Self {
+        SimulatedClock {
+            start: Instant::now(),
+            start_utc: OffsetDateTime::UNIX_EPOCH,
+            elapsed: Mutex::new(Duration::ZERO),
+            sleeps: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl SimulatedClock {
+    /// Creates a simulated clock whose wall-clock time starts at the provided time.
+    pub fn starting_at(start_utc: OffsetDateTime) -> SimulatedClock {
+        SimulatedClock {
+            start_utc,
+            ..Default::default()
+        }
+    }
+
+    /// Advances the simulated time without recording a sleep.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    /// Returns the simulated time elapsed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+
+    /// Returns every sleep requested so far, in order.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn now_utc(&self) -> OffsetDateTime {
+        self.start_utc + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        self.sleeps.lock().unwrap().push(duration);
+        self.advance(duration);
+
+        std::future::ready(())
+    }
+}
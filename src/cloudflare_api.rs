@@ -1,19 +1,56 @@
 use serde::{Serialize, Deserialize};
 
+use crate::constants::*;
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CloudflareResponse<T> {
     pub result: T,
     pub success: bool,
+    #[serde(default)]
     pub errors: Vec<CloudflareError>,
-    pub messages: Vec<String>,
+    #[serde(default)]
+    pub messages: Vec<CloudflareError>,
+    /// Position of the page among the results of a list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_info: Option<CloudflareResultInfo>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudflareResultInfo {
+    #[serde(default)]
+    pub page: u64,
+    #[serde(default)]
+    pub per_page: u64,
+    #[serde(default)]
+    pub total_pages: u64,
+    #[serde(default)]
+    pub total_count: u64,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CloudflareError {
+    #[serde(default)]
     pub code: i64,
+    #[serde(default)]
     pub message: String
 }
 
+impl CloudflareError {
+    /// Returns a human-readable explanation and suggested fix for well-known error codes.
+    pub fn guidance(&self) -> Option<&'static str> {
+        match self.code {
+            6003 | 6111 | 9106 => Some("The API token was missing or malformed. Check that `api_token` is set correctly."),
+            7003 | 7000 => Some("The zone or record ID could not be routed. Check that `zone_id` and `dns_record_id` are correct."),
+            9109 => Some("The API token is invalid. It may have been revoked or expired, create a new one and update `api_token`."),
+            10000 => Some("Authentication failed. Make sure the API token has the \"Zone.DNS: Edit\" permission for this zone."),
+            81044 => Some("The DNS record was not found. Use the `list-records` command to find the correct `dns_record_id`."),
+            81057 => Some("A record with the same name and content already exists."),
+            1004 => Some("Cloudflare rejected the record content. The detected IP may not match the record type."),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CloudflareDnsRecord {
     #[serde(rename = "type")]
@@ -21,31 +58,160 @@ pub struct CloudflareDnsRecord {
     pub name: String,
     pub content: String,
     pub ttl: i64,
-    pub proxied: bool
+    pub proxied: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Sent back with every update, which would otherwise remove them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl CloudflareDnsRecord {
+    /// Checks the constraints Cloudflare enforces on records, so that a record it would reject
+    /// is reported before sending it. The name is only checked when the zone name is known.
+    pub fn validate(&self, zone_name: &str, min_ttl: i64) -> Result<(), String> {
+        if self.ttl != 1 && !(min_ttl..=MAX_RECORD_TTL).contains(&self.ttl) {
+            return Err(format!("the TTL of {} must be 1 (automatic) or between {} and {} seconds, not {}",
+                self.name, min_ttl, MAX_RECORD_TTL, self.ttl));
+        }
+
+        if self.proxied && !PROXIABLE_RECORD_TYPES.contains(&self.dns_type.as_str()) {
+            return Err(format!("{} records such as {} cannot be proxied", self.dns_type, self.name));
+        }
+
+        let name = self.name.trim_end_matches('.').to_lowercase();
+        let zone_name = zone_name.to_lowercase();
+        if !zone_name.is_empty() && name != zone_name && !name.ends_with(format!(".{}", zone_name).as_str()) {
+            return Err(format!("{} is not within the zone {}", self.name, zone_name));
+        }
+
+        Ok(())
+    }
 }
 
+/// Records overwritten through the batch endpoint, applied by Cloudflare in a single transaction.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudflareBatchRequest {
+    pub puts: Vec<CloudflareBatchPut>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudflareBatchPut {
+    pub id: String,
+    #[serde(flatten)]
+    pub record: CloudflareDnsRecord,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudflareBatchResult {
+    #[serde(default)]
+    pub puts: Vec<CloudflareDnsResult>,
+}
+
+/// A DNS record as returned by Cloudflare. Only the fields needed to update the
+/// record are required, the rest fall back to their defaults if Cloudflare omits them.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CloudflareDnsResult {
     pub id: String,
+    #[serde(default)]
     pub zone_id: String,
+    #[serde(default)]
     pub zone_name: String,
     pub name: String,
     #[serde(rename = "type")]
     pub dns_type: String,
     pub content: String,
+    #[serde(default)]
     pub proxiable: bool,
+    #[serde(default)]
     pub proxied: bool,
     pub ttl: i64,
+    #[serde(default)]
     pub locked: bool,
+    #[serde(default)]
     pub meta: Meta,
+    #[serde(default)]
     pub created_on: String,
+    #[serde(default)]
     pub modified_on: String,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl CloudflareDnsResult {
+    /// Returns whether the comment of the record contains the marker, or one of its tags is
+    /// the marker, either alone or as the name of a `name:value` tag.
+    pub fn has_marker(&self, marker: &str) -> bool {
+        self.comment.as_deref().is_some_and(|c| c.contains(marker))
+            || self.tags.iter().any(|t| t == marker || t.split_once(':').is_some_and(|(name, _)| name == marker))
+    }
+
+    /// Describes the fields changed since the provided version of the record, such as
+    /// `ttl 300 -> 60`.
+    pub fn changes_from(&self, previous: &CloudflareDnsResult) -> Vec<String> {
+        let mut changes = Vec::new();
+        let mut compare = |field: &str, before: String, after: String| {
+            if before != after {
+                changes.push(format!("{} {} -> {}", field, before, after));
+            }
+        };
+
+        compare("content", previous.content.trim().to_string(), self.content.trim().to_string());
+        compare("ttl", previous.ttl.to_string(), self.ttl.to_string());
+        compare("proxied", previous.proxied.to_string(), self.proxied.to_string());
+        compare("comment",
+            format!("{:?}", previous.comment.as_deref().unwrap_or_default()),
+            format!("{:?}", self.comment.as_deref().unwrap_or_default()));
+
+        changes
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudflareDeletedRecord {
+    pub id: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudflareTokenStatus {
+    pub id: String,
+    pub status: String,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Meta {
     pub auto_added: bool,
     pub managed_by_apps: bool,
     pub managed_by_argo_tunnel: bool,
     pub source: String,
-}
\ No newline at end of file
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudflareZone {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub plan: CloudflarePlan,
+}
+
+impl CloudflareZone {
+    /// Returns the lowest TTL other than automatic accepted for records of the zone.
+    pub fn min_ttl(&self) -> i64 {
+        match self.plan.name.to_lowercase().contains("enterprise") {
+            true => MIN_ENTERPRISE_RECORD_TTL,
+            false => MIN_RECORD_TTL,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CloudflarePlan {
+    pub id: String,
+    pub name: String,
+}
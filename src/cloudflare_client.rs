@@ -0,0 +1,294 @@
+use log::{debug, error, info, warn};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::cloudflare_api::{CloudflareBatchRequest, CloudflareBatchResult, CloudflareDeletedRecord, CloudflareDnsRecord, CloudflareDnsResult, CloudflareError, CloudflareResponse, CloudflareTokenStatus, CloudflareZone};
+use crate::constants::*;
+use crate::failure::FailureKind;
+use crate::metrics::RequestMetrics;
+use crate::rate_limit::RateLimiter;
+
+/// Client for the parts of the Cloudflare API used by the updater.
+#[derive(Clone)]
+pub struct CloudflareClient {
+    client: Client,
+    base_url: String,
+    api_token: String,
+    limiter: Arc<RateLimiter>,
+    metrics: Arc<RequestMetrics>,
+    /// Records fetched along with their validators by URL, when revalidating them.
+    validated: Option<Arc<Mutex<HashMap<String, ValidatedRecord>>>>,
+}
+
+/// Record kept to be reused when Cloudflare answers that it did not change.
+#[derive(Clone)]
+struct ValidatedRecord {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    response: CloudflareResponse<CloudflareDnsResult>,
+}
+
+impl CloudflareClient {
+    pub fn new(client: Client, api_token: &str) -> CloudflareClient {
+        CloudflareClient {
+            client,
+            base_url: CLOUDFLARE_API_BASE_URL.to_string(),
+            api_token: api_token.to_string(),
+            limiter: Arc::new(RateLimiter::new(DEFAULT_CLOUDFLARE_RATE_LIMIT_REQUESTS,
+                Duration::from_secs(DEFAULT_CLOUDFLARE_RATE_LIMIT_PERIOD))),
+            metrics: Arc::default(),
+            validated: None,
+        }
+    }
+
+    /// Revalidates the records fetched before with their ETag or Last-Modified date, reusing
+    /// them when Cloudflare answers that they did not change.
+    pub fn with_conditional_requests(mut self) -> CloudflareClient {
+        self.validated = Some(Arc::default());
+        self
+    }
+
+    /// Records the duration and failures of the requests in the provided metrics.
+    pub fn with_metrics(mut self, metrics: Arc<RequestMetrics>) -> CloudflareClient {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Limits requests to the provided quota, shared with every clone of this client.
+    pub fn with_rate_limit(mut self, requests: u64, period: Duration) -> CloudflareClient {
+        self.limiter = Arc::new(RateLimiter::new(requests, period));
+        self
+    }
+
+    /// Sends requests to the provided base URL instead of the Cloudflare API.
+    pub fn with_base_url(mut self, base_url: &str) -> CloudflareClient {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Gets the provided DNS record.
+    pub async fn get_dns_record(&self, zone_id: &str, dns_record_id: &str) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        let url = format!("{}/zones/{}/dns_records/{}", self.base_url, zone_id, dns_record_id);
+        let action = "get the current Cloudflare DNS entry";
+
+        let Some(validated) = self.validated.as_ref() else {
+            return self.send(self.client.get(url.as_str()), "get_dns_record", action).await;
+        };
+
+        let cached = validated.lock().unwrap().get(&url).cloned();
+        let mut request = self.client.get(url.as_str()).bearer_auth(&self.api_token);
+
+        if let Some(c) = cached.as_ref() {
+            if let Some(etag) = c.etag.as_deref() {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = c.last_modified.as_deref() {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        self.limiter.acquire().await;
+        let started = Instant::now();
+        let body = request.send().await;
+
+        if let (Ok(r), Some(c)) = (body.as_ref(), cached) {
+            if r.status() == StatusCode::NOT_MODIFIED {
+                debug!("The Cloudflare DNS entry {} did not change.", dns_record_id);
+                self.metrics.observe("cloudflare", "get_dns_record", started.elapsed(), None);
+                return Ok(c.response);
+            }
+        }
+
+        let header = |name| body.as_ref().ok()
+            .and_then(|r| r.headers().get(name))
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+
+        let response = read_cloudflare_response(body, action).await;
+        self.metrics.observe("cloudflare", "get_dns_record", started.elapsed(), response.as_ref().err().copied());
+
+        if let (Ok(r), true) = (response.as_ref(), etag.is_some() || last_modified.is_some()) {
+            validated.lock().unwrap().insert(url, ValidatedRecord { etag, last_modified, response: r.clone() });
+        }
+
+        response
+    }
+
+    /// Lists all DNS records in the provided zone.
+    pub async fn list_dns_records(&self, zone_id: &str) -> Result<CloudflareResponse<Vec<CloudflareDnsResult>>, FailureKind> {
+        let url = format!("{}/zones/{}/dns_records", self.base_url, zone_id);
+        self.list_all(url.as_str(), CLOUDFLARE_DNS_RECORDS_PAGE_SIZE, "list_dns_records", "list the Cloudflare DNS records").await
+    }
+
+    /// Checks that the API token is valid, returning its status.
+    pub async fn verify_token(&self) -> Result<CloudflareResponse<CloudflareTokenStatus>, FailureKind> {
+        let request = self.client.get(format!("{}/user/tokens/verify", self.base_url));
+        self.send(request, "verify_token", "verify the Cloudflare API token").await
+    }
+
+    /// Lists all zones accessible to the API token.
+    pub async fn list_zones(&self) -> Result<CloudflareResponse<Vec<CloudflareZone>>, FailureKind> {
+        let url = format!("{}/zones", self.base_url);
+        self.list_all(url.as_str(), CLOUDFLARE_ZONES_PAGE_SIZE, "list_zones", "list the Cloudflare zones").await
+    }
+
+    /// Sends the request with the API token once the quota allows it, recording its duration
+    /// and outcome under the provided operation.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder, operation: &'static str, action: &str) -> Result<CloudflareResponse<T>, FailureKind> {
+        self.limiter.acquire().await;
+        let started = Instant::now();
+        let body = request.bearer_auth(&self.api_token).send().await;
+        let response = read_cloudflare_response(body, action).await;
+
+        self.metrics.observe("cloudflare", operation, started.elapsed(), response.as_ref().err().copied());
+        response
+    }
+
+    /// Gets every page of the provided list, returning the results of all pages together.
+    /// Without page information, a page shorter than `per_page` is taken as the last one.
+    async fn list_all<T: DeserializeOwned>(&self, url: &str, per_page: u64, operation: &'static str, action: &str) -> Result<CloudflareResponse<Vec<T>>, FailureKind> {
+        let mut page = 1;
+        let mut all: Option<CloudflareResponse<Vec<T>>> = None;
+
+        loop {
+            let request = self.client.get(format!("{}?page={}&per_page={}", url, page, per_page));
+            let response: CloudflareResponse<Vec<T>> = self.send(request, operation, action).await?;
+            let fetched = response.result.len() as u64;
+            let last = match response.result_info.as_ref() {
+                Some(info) if info.total_pages > 0 => page >= info.total_pages,
+                _ => fetched < per_page,
+            };
+
+            let mut merged = match all.take() {
+                Some(mut all) => {
+                    all.result.extend(response.result);
+                    all.messages.extend(response.messages);
+                    all
+                },
+                None => response,
+            };
+
+            if last || fetched == 0 {
+                merged.result_info = None;
+                return Ok(merged);
+            }
+
+            all = Some(merged);
+
+            page += 1;
+        }
+    }
+
+    /// Updates the provided DNS record.
+    pub async fn update_dns_record(&self, zone_id: &str, dns_record_id: &str, dns_record: &CloudflareDnsRecord) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        let request = self.client.post(format!("{}/zones/{}/dns_records/{}", self.base_url, zone_id, dns_record_id))
+            .json(dns_record);
+        let response = self.send(request, "update_dns_record", "update the Cloudflare DNS record").await?;
+        info!("Cloudflare DNS record updated successfully.");
+
+        Ok(response)
+    }
+
+    /// Sends the provided comment of the DNS record back unchanged, checking that the record
+    /// may be edited without changing it.
+    pub async fn patch_dns_record_comment(&self, zone_id: &str, dns_record_id: &str, comment: Option<&str>) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        let request = self.client.patch(format!("{}/zones/{}/dns_records/{}", self.base_url, zone_id, dns_record_id))
+            .json(&serde_json::json!({ "comment": comment }));
+        self.send(request, "patch_dns_record", "edit the Cloudflare DNS record").await
+    }
+
+    /// Creates the provided DNS record in the zone.
+    pub async fn create_dns_record(&self, zone_id: &str, dns_record: &CloudflareDnsRecord) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        let request = self.client.post(format!("{}/zones/{}/dns_records", self.base_url, zone_id))
+            .json(dns_record);
+        let response: CloudflareResponse<CloudflareDnsResult> = self.send(request, "create_dns_record", "create the Cloudflare DNS record").await?;
+        info!("Cloudflare DNS record {} created successfully.", response.result.name);
+
+        Ok(response)
+    }
+
+    /// Deletes the provided DNS record.
+    pub async fn delete_dns_record(&self, zone_id: &str, dns_record_id: &str) -> Result<CloudflareResponse<CloudflareDeletedRecord>, FailureKind> {
+        let request = self.client.delete(format!("{}/zones/{}/dns_records/{}", self.base_url, zone_id, dns_record_id));
+        self.send(request, "delete_dns_record", "delete the Cloudflare DNS record").await
+    }
+
+    /// Updates the provided DNS records of a zone in a single request.
+    pub async fn batch_update_dns_records(&self, zone_id: &str, batch: &CloudflareBatchRequest) -> Result<CloudflareResponse<CloudflareBatchResult>, FailureKind> {
+        let request = self.client.post(format!("{}/zones/{}/dns_records/batch", self.base_url, zone_id))
+            .json(batch);
+        let response = self.send(request, "batch_update_dns_records", "update the Cloudflare DNS records").await?;
+        info!("{} Cloudflare DNS records updated successfully.", batch.puts.len());
+
+        Ok(response)
+    }
+}
+
+/// Reads a Cloudflare API response, classifying the failure if it was not successful.
+async fn read_cloudflare_response<T: DeserializeOwned>(body: Result<reqwest::Response, reqwest::Error>, action: &str) -> Result<CloudflareResponse<T>, FailureKind> {
+    let response = match body {
+        Ok(r) => r,
+        Err(e) => {
+            warn!("Issue trying to {}: {:?}", action, e);
+            return Err(FailureKind::Network);
+        }
+    };
+
+    let status = response.status();
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        warn!("Rate limited by Cloudflare while trying to {}.", action);
+        return Err(FailureKind::RateLimit);
+    }
+
+    let text = match response.text().await {
+        Ok(t) => t,
+        Err(e) => {
+            warn!("Issue reading the response while trying to {}: {:?}", action, e);
+            return Err(FailureKind::Network);
+        }
+    };
+
+    if !status.is_success() {
+        let (errors, messages) = serde_json::from_str::<CloudflareResponse<serde_json::Value>>(text.as_str())
+            .map(|r| (r.errors, r.messages))
+            .unwrap_or_default();
+
+        error!("Cloudflare returned {} while trying to {}.", status, action);
+        log_cloudflare_errors(&errors, &messages);
+
+        return Err(FailureKind::from_cloudflare(status, &errors));
+    }
+
+    match serde_json::from_str::<CloudflareResponse<T>>(text.as_str()) {
+        Ok(v) if !v.success => {
+            error!("Cloudflare was unable to {}.", action);
+            log_cloudflare_errors(&v.errors, &v.messages);
+
+            Err(FailureKind::from_cloudflare(status, &v.errors))
+        },
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!("Error deserializing the response while trying to {}: {:?}", action, e);
+            Err(FailureKind::Api)
+        }
+    }
+}
+
+/// Logs the errors and messages of an unsuccessful Cloudflare response, with guidance when available.
+fn log_cloudflare_errors(errors: &[CloudflareError], messages: &[CloudflareError]) {
+    for e in errors {
+        match e.guidance() {
+            Some(g) => error!("Cloudflare error {}: {} - {}", e.code, e.message, g),
+            None => error!("Cloudflare error {}: {}", e.code, e.message),
+        }
+    }
+
+    for m in messages {
+        warn!("Cloudflare message {}: {}", m.code, m.message);
+    }
+}
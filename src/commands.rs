@@ -0,0 +1,700 @@
+use clap::CommandFactory;
+use clap_complete::Shell;
+use log::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+use crate::cli::Cli;
+use crate::clock::SystemClock;
+use crate::cloudflare_api::{CloudflareDnsRecord, CloudflareDnsResult};
+use crate::cloudflare_client::CloudflareClient;
+use crate::config::{Config, ConfigError, ConfigOverrides, RecordConfig};
+use crate::constants::{CLOUDFLARE_API_BASE_URL, DEFAULT_CONFIG_HEADER, DEFAULT_NOT_SET, EXIT_CONFIG_ERROR, MANAGED_RECORD_COMMENT, MIN_RECORD_TTL};
+use crate::control::{self, ControlHandle};
+use crate::failure::FailureKind;
+use crate::http_client;
+use crate::interface;
+use crate::ip_file;
+use crate::migration;
+use crate::public_ip::{self, IpSource};
+use crate::secret;
+use crate::state::{self, State};
+use crate::updater::Updater;
+
+/// Unwraps a value derived from the configuration, printing a diagnostic and exiting if the
+/// configuration is invalid.
+pub fn unwrap_config<T>(result: Result<T, ConfigError>) -> T {
+    match result {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        }
+    }
+}
+
+/// Lists the DNS records in the configured zone.
+pub async fn list_records(overrides: &ConfigOverrides, json: bool) {
+    let config = unwrap_config(Config::load_for_zone(overrides));
+    let http_client = unwrap_config(http_client::build(&config));
+    let cloudflare_config = config.cloudflare.unwrap();
+
+    let client = CloudflareClient::new(http_client, cloudflare_config.api_token.unwrap().as_str());
+    let records = client.list_dns_records(cloudflare_config.zone_id.unwrap().as_str())
+        .await;
+
+    let records = match records {
+        Ok(r) => r.result,
+        Err(kind) => std::process::exit(kind.exit_code()),
+    };
+
+    if json {
+        let output: Vec<serde_json::Value> = records.iter()
+            .map(|r| serde_json::json!({
+                "id": r.id,
+                "name": r.name,
+                "type": r.dns_type,
+                "content": r.content,
+                "proxied": r.proxied,
+            }))
+            .collect();
+
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        return;
+    }
+
+    let name_width = records.iter().map(|r| r.name.len()).chain([4]).max().unwrap();
+    let type_width = records.iter().map(|r| r.dns_type.len()).chain([4]).max().unwrap();
+    let content_width = records.iter().map(|r| r.content.len()).chain([7]).max().unwrap();
+    let id_width = records.iter().map(|r| r.id.len()).chain([2]).max().unwrap();
+
+    println!("{:name_width$}  {:type_width$}  {:content_width$}  {:id_width$}  PROXIED", "NAME", "TYPE", "CONTENT", "ID");
+    for record in &records {
+        println!("{:name_width$}  {:type_width$}  {:content_width$}  {:id_width$}  {}",
+            record.name, record.dns_type, record.content, record.id, record.proxied);
+    }
+}
+
+/// Checks every configured record once, printing the changes an update would make without
+/// sending them.
+pub async fn dry_run(overrides: &ConfigOverrides, json: bool) {
+    let config = unwrap_config(Config::load(overrides));
+    let handle = Arc::new(ControlHandle::in_memory(State::default()));
+    let mut updater = unwrap_config(Updater::new(&config, handle, Arc::new(SystemClock)));
+
+    let planned = match updater.plan().await {
+        Ok(p) => p,
+        Err(kind) => std::process::exit(kind.exit_code()),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&planned).unwrap());
+        return;
+    }
+
+    if planned.is_empty() {
+        println!("No changes, every record is up to date.");
+    }
+
+    for change in &planned {
+        println!("{}", change);
+    }
+}
+
+/// Creates a record with the current public IP in the configured zone, then adds it to the
+/// records of the configuration file.
+pub async fn create_record(overrides: &ConfigOverrides, name: Option<String>, record_type: String, ttl: i64, proxied: bool) {
+    let config = unwrap_config(Config::load_for_zone(overrides));
+    let http_client = unwrap_config(http_client::build(&config));
+    let ip_check_client = unwrap_config(http_client::build_for_ip_check(&config));
+    let ip_check_config = config.ip_check.clone().unwrap();
+    let cloudflare_config = config.cloudflare.clone().unwrap();
+
+    let name = name.unwrap_or_else(|| prompt("Record name: "));
+    let zone_id = cloudflare_config.zone_id.unwrap();
+    let client = CloudflareClient::new(http_client, cloudflare_config.api_token.unwrap().as_str());
+    let mut new_dns_record = CloudflareDnsRecord {
+        dns_type: record_type.clone(),
+        name,
+        content: String::new(),
+        ttl,
+        proxied,
+        comment: Some(MANAGED_RECORD_COMMENT.to_string()),
+        tags: Vec::new(),
+    };
+
+    // The zone is only known if the token may list zones, otherwise its name is not checked.
+    let zone = client.list_zones().await.ok()
+        .and_then(|r| r.result.into_iter().find(|z| z.id == zone_id));
+    let (zone_name, min_ttl) = zone.map(|z| (z.name.clone(), z.min_ttl())).unwrap_or((String::new(), MIN_RECORD_TTL));
+
+    if let Err(e) = new_dns_record.validate(zone_name.as_str(), min_ttl) {
+        error!("Not creating the record, {}.", e);
+        std::process::exit(EXIT_CONFIG_ERROR);
+    }
+
+    let ipv6 = record_type == "AAAA";
+    let urls = if ipv6 { ip_check_config.ipv6_urls.unwrap() } else { ip_check_config.urls.unwrap() };
+
+    let mut content = None;
+    for url in &urls {
+        if let Ok(ip) = public_ip::get_public_ip_of_family(&ip_check_client, url, ipv6).await {
+            content = Some(ip);
+            break;
+        }
+    }
+
+    new_dns_record.content = match content {
+        Some(c) => c,
+        None => {
+            error!("Unable to detect the public IPv{} address.", if ipv6 { 6 } else { 4 });
+            std::process::exit(FailureKind::Network.exit_code());
+        }
+    };
+
+    let created = match client.create_dns_record(zone_id.as_str(), &new_dns_record).await {
+        Ok(r) => r.result,
+        Err(kind) => std::process::exit(kind.exit_code()),
+    };
+
+    let path = unwrap_config(Config::get_config_file_path(overrides));
+    match add_record_to_config_file(&path, &config, created.id.as_str()) {
+        Ok(()) => info!("Added record {} ({}) to {}.", created.name, created.id, path.display()),
+        Err(e) => {
+            error!("Unable to add the record to {}: {}", path.display(), e);
+            println!("[[records]]\ndns_record_id = \"{}\"", created.id);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        }
+    }
+}
+
+/// Appends the record to the configuration file, keeping its comments. The record configured
+/// as `cloudflare.dns_record_id` is listed too, since it is ignored once records are listed.
+fn add_record_to_config_file(path: &Path, config: &Config, dns_record_id: &str) -> Result<(), ConfigError> {
+    let io_error = |source| ConfigError::Io { path: path.to_path_buf(), source };
+    let contents = fs::read_to_string(path).map_err(io_error)?;
+
+    let mut updated: String = contents.lines()
+        .filter(|l| l.trim() != "records = []")
+        .map(|l| format!("{}\n", l))
+        .collect();
+
+    let legacy_record_id = config.cloudflare.as_ref().unwrap().dns_record_id.as_ref().unwrap();
+    if config.records.as_ref().unwrap().is_empty() && legacy_record_id != DEFAULT_NOT_SET {
+        updated.push_str(&format!("\n[[records]]\ndns_record_id = \"{}\"\n", legacy_record_id));
+    }
+
+    updated.push_str(&format!("\n[[records]]\ndns_record_id = \"{}\"\n", dns_record_id));
+
+    Config::parse(updated.as_str()).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })?;
+    fs::write(path, updated).map_err(io_error)
+}
+
+/// Prints the question to stderr, returning the answer read from stdin.
+fn prompt(question: &str) -> String {
+    eprint!("{}", question);
+
+    let mut answer = String::new();
+    if let Err(e) = std::io::stdin().read_line(&mut answer) {
+        error!("Unable to read the answer from stdin: {}", e);
+        std::process::exit(1);
+    }
+
+    answer.trim().to_string()
+}
+
+/// Lists the records created by the updater which no longer match any configured record,
+/// deleting them if requested.
+pub async fn cleanup(overrides: &ConfigOverrides, delete: bool) {
+    let config = unwrap_config(Config::load(overrides));
+    let http_client = unwrap_config(http_client::build(&config));
+    let records = config.records();
+
+    let mut zones: Vec<(String, String)> = Vec::new();
+    for r in &records {
+        let zone = (r.zone_id.clone().unwrap(), r.api_token.clone().unwrap());
+        if !zones.contains(&zone) {
+            zones.push(zone);
+        }
+    }
+
+    let mut stale_count = 0;
+
+    for (zone_id, api_token) in zones {
+        let client = CloudflareClient::new(http_client.clone(), api_token.as_str());
+        let listed = match client.list_dns_records(zone_id.as_str()).await {
+            Ok(r) => r.result,
+            Err(kind) => std::process::exit(kind.exit_code()),
+        };
+
+        let configured: Vec<&RecordConfig> = records.iter()
+            .filter(|r| r.zone_id.as_ref() == Some(&zone_id) && r.api_token.as_ref() == Some(&api_token))
+            .collect();
+
+        let is_configured = |record: &CloudflareDnsResult| configured.iter().any(|c| c.selects(record));
+        let is_companion = |record: &CloudflareDnsResult| record.dns_type == "TXT" && configured.iter()
+            .any(|c| c.txt_record == Some(true) && listed.iter().any(|r| r.name == record.name && c.selects(r)));
+
+        let stale: Vec<&CloudflareDnsResult> = listed.iter()
+            .filter(|r| r.comment.as_deref() == Some(MANAGED_RECORD_COMMENT))
+            .filter(|r| !is_configured(r) && !is_companion(r))
+            .collect();
+
+        for record in stale {
+            stale_count += 1;
+            println!("{}  {}  {}  {}", record.name, record.dns_type, record.content, record.id);
+
+            if delete {
+                if let Err(kind) = client.delete_dns_record(zone_id.as_str(), record.id.as_str()).await {
+                    std::process::exit(kind.exit_code());
+                }
+
+                info!("Deleted the {} record of {}.", record.dns_type, record.name);
+            }
+        }
+    }
+
+    if stale_count == 0 {
+        println!("No stale records found.");
+    } else if !delete {
+        println!("Run with --delete to delete these {} records.", stale_count);
+    }
+}
+
+/// Lists the zones accessible to the configured API token.
+pub async fn list_zones(overrides: &ConfigOverrides, json: bool) {
+    let config = unwrap_config(Config::load_for_token(overrides));
+    let http_client = unwrap_config(http_client::build(&config));
+    let cloudflare_config = config.cloudflare.unwrap();
+
+    let client = CloudflareClient::new(http_client, cloudflare_config.api_token.unwrap().as_str());
+    let zones = client.list_zones()
+        .await;
+
+    let zones = match zones {
+        Ok(r) => r.result,
+        Err(kind) => std::process::exit(kind.exit_code()),
+    };
+
+    if json {
+        let output: Vec<serde_json::Value> = zones.iter()
+            .map(|z| serde_json::json!({
+                "id": z.id,
+                "name": z.name,
+                "status": z.status,
+                "plan": z.plan.name,
+            }))
+            .collect();
+
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        return;
+    }
+
+    let name_width = zones.iter().map(|z| z.name.len()).chain([4]).max().unwrap();
+    let id_width = zones.iter().map(|z| z.id.len()).chain([2]).max().unwrap();
+    let status_width = zones.iter().map(|z| z.status.len()).chain([6]).max().unwrap();
+
+    println!("{:name_width$}  {:id_width$}  {:status_width$}  PLAN", "NAME", "ID", "STATUS");
+    for zone in &zones {
+        println!("{:name_width$}  {:id_width$}  {:status_width$}  {}",
+            zone.name, zone.id, zone.status, zone.plan.name);
+    }
+}
+
+/// Returns the configured control socket path.
+fn get_control_socket_path(overrides: &ConfigOverrides) -> PathBuf {
+    let config = unwrap_config(Config::read(overrides));
+    PathBuf::from(config.general.unwrap().control_socket.unwrap())
+}
+
+/// Sends a command to the running updater over the control socket.
+pub async fn send_control_command(overrides: &ConfigOverrides, command: &str) {
+    let path = get_control_socket_path(overrides);
+
+    match control::send_command(&path, command).await {
+        Ok(r) if r.success => println!("{}", r.message),
+        Ok(r) => {
+            error!("{}", r.message);
+            std::process::exit(1);
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+            error!("Unable to reach the updater: {}.", e);
+            std::process::exit(1);
+        },
+        Err(e) => {
+            error!("Unable to reach the updater at {}. Is it running? {:?}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Prints the state of the running updater, falling back to the last saved state.
+pub async fn print_status(overrides: &ConfigOverrides, json: bool) {
+    let path = get_control_socket_path(overrides);
+    let live_status = match control::send_command(&path, "status").await {
+        Ok(r) => r.status,
+        Err(e) => {
+            debug!("Unable to query the running updater, using the state file: {:?}", e);
+            None
+        }
+    };
+
+    let paused = live_status.as_ref().map(|s| s.paused);
+    let state = match live_status.map(|s| s.state).or_else(State::load) {
+        Some(s) => s,
+        None => {
+            error!("No state found at {}. Has the updater run yet?", State::get_state_file_path().display());
+            std::process::exit(1);
+        }
+    };
+
+    let now = OffsetDateTime::now_utc();
+    if json {
+        let mut output = serde_json::to_value(&state).unwrap();
+        output["running"] = serde_json::json!(paused.is_some());
+        if let Some(p) = paused {
+            output["paused"] = serde_json::json!(p);
+            if let Some(uptime) = state.stats.uptime(now) {
+                output["uptime_seconds"] = serde_json::json!(uptime.whole_seconds());
+            }
+        }
+
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        return;
+    }
+
+    match paused {
+        Some(true) => println!("Updater:         running (paused)"),
+        Some(false) => println!("Updater:         running"),
+        None => println!("Updater:         not running"),
+    }
+
+    println!("Public IP:       {}", state.public_ip.as_deref().unwrap_or("unknown"));
+    if state.records.is_empty() {
+        println!("Records:         unknown");
+    } else {
+        println!("Records:");
+        for r in &state.records {
+            println!("  {}  {}{}",
+                r.name.as_deref().unwrap_or(r.id.as_str()),
+                r.content.as_deref().unwrap_or("unknown"),
+                if r.proxied { "  (proxied)" } else { "" });
+        }
+    }
+    println!("Last checked:    {}", state::format_timestamp(state.last_checked));
+    println!("Last changed:    {}", state::format_timestamp(state.last_changed));
+
+    let stats = &state.stats;
+    if let Some(uptime) = stats.uptime(now).filter(|_| paused.is_some()) {
+        println!("Uptime:          {}s", uptime.whole_seconds());
+    }
+    if let Some(t) = state.last_changed {
+        println!("Since change:    {}s", (now - t).whole_seconds());
+    }
+    println!("Checks:          {}", stats.checks);
+    println!("Updates:         {}", stats.updates);
+    match &state.requests_today {
+        b if b.limit > 0 => println!("Requests today:  {} of {}", b.used, b.limit),
+        b if b.date.is_some() => println!("Requests today:  {}", b.used),
+        _ => {},
+    }
+    println!("Failures:        {} network, {} auth, {} rate limit, {} api, {} mismatch",
+        stats.failures.network,
+        stats.failures.auth,
+        stats.failures.rate_limit,
+        stats.failures.api,
+        stats.failures.mismatch);
+
+    if !state.providers.is_empty() {
+        println!("IP providers:");
+        for p in &state.providers {
+            println!("  {}  {:.0}% of {} requests succeeded, {}",
+                p.url,
+                p.success_rate().unwrap_or(0.0) * 100.0,
+                p.successes + p.failures,
+                p.latency_ms.map(|l| format!("{}ms", l)).unwrap_or_else(|| "latency unknown".to_string()));
+        }
+    }
+
+    if state.recent_errors.is_empty() {
+        println!("Recent errors:   none");
+    } else {
+        println!("Recent errors:");
+        for e in &state.recent_errors {
+            println!("  {}  {}", state::format_timestamp(Some(e.timestamp)), e.message);
+        }
+    }
+}
+
+/// Encrypts the API token read from stdin with the configured key file or passphrase.
+pub fn encrypt_token(overrides: &ConfigOverrides) {
+    let config = unwrap_config(Config::read(overrides));
+    let key_file = config.cloudflare.unwrap().api_token_key_file.unwrap();
+
+    let key = match secret::load_key(key_file.as_str()) {
+        Ok(k) => k,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        }
+    };
+
+    println!("{}", secret::encrypt_token(read_token().as_str(), &key));
+}
+
+/// Stores the API token read from stdin in the platform keyring.
+pub fn set_token() {
+    match secret::set_keyring_token(read_token().as_str()) {
+        Ok(()) => info!("API token stored in the keyring. Set `cloudflare.token_source = \"keyring\"` to use it."),
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Reads an API token from the first line of stdin.
+fn read_token() -> String {
+    let mut token = String::new();
+    if let Err(e) = std::io::stdin().read_line(&mut token) {
+        error!("Unable to read the API token from stdin: {}", e);
+        std::process::exit(1);
+    }
+
+    token.trim().to_string()
+}
+
+/// Checks step by step that the updater can work with its configuration, printing a pass or
+/// fail line for each check and exiting with an error if any failed.
+pub async fn doctor(overrides: &ConfigOverrides) {
+    let mut report = Report::default();
+
+    let config = match Config::load(overrides) {
+        Ok(c) => {
+            report.pass("Configuration", "valid".to_string());
+            c
+        },
+        Err(e) => {
+            report.fail("Configuration", e.to_string());
+            report.finish();
+        }
+    };
+
+    let (http_client, ip_check_client) = match (http_client::build(&config), http_client::build_for_ip_check(&config)) {
+        (Ok(c), Ok(i)) => (c, i),
+        (Err(e), _) | (_, Err(e)) => {
+            report.fail("HTTP client", e.to_string());
+            report.finish();
+        }
+    };
+
+    match http_client.get(CLOUDFLARE_API_BASE_URL).send().await {
+        Ok(_) => report.pass("Connectivity", format!("reached {}", CLOUDFLARE_API_BASE_URL)),
+        Err(e) => report.fail("Connectivity", e.to_string()),
+    }
+
+    let ip_check_config = config.ip_check.as_ref().unwrap();
+    match ip_check_config.ip_source.unwrap() {
+        IpSource::File => {
+            let path = ip_check_config.ip_file.as_ref().unwrap();
+            match ip_file::read(Path::new(path)) {
+                Ok(ips) => report.pass("IP file", format!("{} contains {}", path, ips.primary().unwrap_or("no address"))),
+                Err(kind) => report.fail("IP file", format!("unable to read {} ({})", path, kind.as_str())),
+            }
+        },
+        IpSource::Interface => {
+            let name = ip_check_config.interface.as_ref().unwrap();
+            match interface::read(name, ip_check_config.ipv6_selection.unwrap()) {
+                Ok(ips) => report.pass("Interface", format!("{} has {}", name, ips.primary().unwrap_or("no address"))),
+                Err(kind) => report.fail("Interface", format!("unable to read the addresses of {} ({})", name, kind.as_str())),
+            }
+        },
+        IpSource::Http => {
+            for &ipv6 in ip_check_config.address_policy.unwrap().families() {
+                let urls = if ipv6 { ip_check_config.ipv6_urls.as_ref() } else { ip_check_config.urls.as_ref() };
+
+                for url in urls.unwrap() {
+                    match public_ip::get_public_ip_of_family(&ip_check_client, url, ipv6).await {
+                        Ok(ip) => report.pass("IP provider", format!("{} answered {}", url, ip)),
+                        Err(kind) => report.fail("IP provider", format!("{} failed ({})", url, kind.as_str())),
+                    }
+                }
+            }
+        },
+    }
+
+    let records = config.records();
+
+    let mut tokens: Vec<String> = Vec::new();
+    for r in &records {
+        if !tokens.contains(r.api_token.as_ref().unwrap()) {
+            tokens.push(r.api_token.clone().unwrap());
+        }
+    }
+
+    // Tokens are numbered rather than printed, so that the report can be shared.
+    for (i, token) in tokens.iter().enumerate() {
+        match CloudflareClient::new(http_client.clone(), token).verify_token().await {
+            Ok(r) if r.result.status == "active" => report.pass("API token", format!("token {} is active", i + 1)),
+            Ok(r) => report.fail("API token", format!("token {} is {}", i + 1, r.result.status)),
+            Err(kind) => report.fail("API token", format!("unable to verify token {} ({})", i + 1, kind.as_str())),
+        }
+    }
+
+    let mut listings: HashMap<(String, String), Option<Vec<CloudflareDnsResult>>> = HashMap::new();
+
+    for r in &records {
+        let zone_id = r.zone_id.clone().unwrap();
+        let api_token = r.api_token.clone().unwrap();
+        let client = CloudflareClient::new(http_client.clone(), api_token.as_str());
+        let selector = r.name.clone().or(r.dns_record_id.clone()).unwrap();
+
+        let key = (zone_id.clone(), api_token.clone());
+        if !listings.contains_key(&key) {
+            let listed = match client.list_dns_records(zone_id.as_str()).await {
+                Ok(l) => {
+                    report.pass("Zone access", format!("listed {} records of zone {}", l.result.len(), zone_id));
+                    Some(l.result)
+                },
+                Err(kind) => {
+                    report.fail("Zone access", format!("unable to list the records of zone {} ({})", zone_id, kind.as_str()));
+                    None
+                }
+            };
+
+            listings.insert(key.clone(), listed);
+        }
+
+        let Some(listed) = listings[&key].as_ref() else { continue };
+        let selected: Vec<&CloudflareDnsResult> = listed.iter().filter(|l| r.selects(l)).collect();
+
+        if selected.is_empty() {
+            match r.create == Some(true) {
+                true => report.pass("Record access", format!("{} does not exist yet and will be created", selector)),
+                false => report.fail("Record access", format!("{} does not exist in zone {}", selector, zone_id)),
+            }
+            continue;
+        }
+
+        for record in selected {
+            report.pass("Record access", format!("{} {} points to {}", record.name, record.dns_type, record.content));
+
+            // Sending the comment unchanged proves the token may edit the record without
+            // changing anything.
+            match client.patch_dns_record_comment(zone_id.as_str(), record.id.as_str(), record.comment.as_deref()).await {
+                Ok(_) => report.pass("Write permission", format!("able to edit {} {}", record.name, record.dns_type)),
+                Err(kind) => report.fail("Write permission", format!("unable to edit {} {} ({})", record.name, record.dns_type, kind.as_str())),
+            }
+        }
+    }
+
+    report.finish();
+}
+
+/// Results of the doctor checks, printed as they are recorded.
+#[derive(Default)]
+struct Report {
+    passed: usize,
+    failed: usize,
+}
+
+impl Report {
+    fn pass(&mut self, check: &str, detail: String) {
+        self.passed += 1;
+        println!("[PASS] {}: {}", check, detail);
+    }
+
+    fn fail(&mut self, check: &str, reason: String) {
+        self.failed += 1;
+        println!("[FAIL] {}: {}", check, reason);
+    }
+
+    /// Prints the summary and exits, with an error if any check failed.
+    fn finish(&self) -> ! {
+        println!();
+        println!("{} checks passed, {} failed.", self.passed, self.failed);
+        std::process::exit(if self.failed == 0 { 0 } else { EXIT_CONFIG_ERROR });
+    }
+}
+
+/// Prints the effective configuration with its secrets redacted.
+pub fn export_config(overrides: &ConfigOverrides) {
+    print!("{}", unwrap_config(Config::export(overrides)));
+}
+
+/// Makes the provided file, or the configuration read from stdin, the configuration file.
+pub fn import_config(overrides: &ConfigOverrides, file: Option<PathBuf>) {
+    let contents = match file {
+        Some(path) => fs::read_to_string(&path).map_err(|source| ConfigError::Io { path, source }),
+        None => std::io::read_to_string(std::io::stdin()).map_err(|source| ConfigError::Io { path: PathBuf::from("-"), source }),
+    };
+
+    let path = unwrap_config(contents.and_then(|c| Config::import(c.as_str(), overrides)));
+    info!("Imported the configuration to {}.", path.display());
+}
+
+/// Replaces the record IDs of the configuration file by the names and types of the records,
+/// as found on Cloudflare, keeping the original file as a backup. Prints the result instead
+/// of writing it with `dry_run`.
+pub async fn migrate_config(overrides: &ConfigOverrides, dry_run: bool) {
+    let config = unwrap_config(Config::load(overrides));
+    let http_client = unwrap_config(http_client::build(&config));
+    let path = unwrap_config(Config::get_config_file_path(overrides));
+
+    let mut names = HashMap::new();
+    for r in config.records() {
+        let Some(id) = r.dns_record_id.as_ref().filter(|id| *id != DEFAULT_NOT_SET && !r.is_selector()) else {
+            continue;
+        };
+
+        let client = CloudflareClient::new(http_client.clone(), r.api_token.as_ref().unwrap());
+        match client.get_dns_record(r.zone_id.as_ref().unwrap(), id).await {
+            // A wildcard name would select every record it matches.
+            Ok(found) if found.result.name.contains(['*', '?']) =>
+                warn!("Keeping the ID of {}, as its name would select other records.", found.result.name),
+            Ok(found) => {
+                names.insert(id.clone(), (found.result.name, found.result.dns_type));
+            },
+            Err(kind) => std::process::exit(kind.exit_code()),
+        }
+    }
+
+    if names.is_empty() {
+        println!("No records are configured by ID.");
+        return;
+    }
+
+    let io_error = |source| ConfigError::Io { path: path.clone(), source };
+    let contents = unwrap_config(fs::read_to_string(&path).map_err(io_error));
+    let Ok(toml::Value::Table(mut table)) = contents.parse::<toml::Value>() else {
+        error!("Unable to parse {}.", path.display());
+        std::process::exit(EXIT_CONFIG_ERROR);
+    };
+
+    let replaced = migration::replace_record_ids(&mut table, &names);
+    let migrated = format!("{}\n{}", DEFAULT_CONFIG_HEADER, toml::to_string(&toml::Value::Table(table)).unwrap());
+    unwrap_config(Config::parse(migrated.as_str()).map_err(|source| ConfigError::Parse { path: path.clone(), source }));
+
+    if dry_run {
+        print!("{}", migrated);
+        return;
+    }
+
+    let mut backup = path.file_name().unwrap_or_default().to_os_string();
+    backup.push(".bak");
+    let backup = path.with_file_name(backup);
+
+    unwrap_config(fs::copy(&path, &backup).map_err(io_error));
+    unwrap_config(fs::write(&path, migrated).map_err(io_error));
+    info!("Replaced the IDs of {} records by their names in {}, the original was saved to {}.", replaced, path.display(), backup.display());
+}
+
+/// Prints shell completions for the provided shell.
+pub fn print_completions(shell: Shell) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+}
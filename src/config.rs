@@ -1,34 +1,410 @@
-use merge::Merge;
+use figment::providers::{Env, Format, Serialized, Toml};
+use figment::error::Kind;
+use figment::{Figment, Metadata, Source};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::io::{BufReader, Read, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use log::{debug, info, warn};
+use std::time::Duration;
+use log::{debug, info};
+use thiserror::Error;
 
+use crate::backoff::{Backoff, BackoffStrategy};
+use crate::cloudflare_api::CloudflareDnsResult;
 use crate::constants::*;
+use crate::interface::Ipv6Selection;
+use crate::migration;
+use crate::notification::EventKind;
+use crate::pattern;
+use crate::public_ip::{AddressPolicy, DetectionStrategy, IpSource, Transport};
+use crate::secret::{self, SecretError};
+use crate::state::State;
+use crate::template;
 
-#[derive(Serialize, Deserialize, Merge, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct GeneralConfig {
-    pub(crate) wait_duration: Option<u64>
+    pub(crate) wait_duration: Option<u64>,
+    pub(crate) schedule: Option<String>,
+    pub(crate) wait_jitter: Option<u64>,
+    pub(crate) refresh_interval: Option<u64>,
+    pub(crate) selection_refresh: Option<u64>,
+    pub(crate) max_consecutive_failures: Option<u64>,
+    pub(crate) update_concurrency: Option<usize>,
+    pub(crate) observe_only: Option<bool>,
+    pub(crate) heartbeat_interval: Option<u64>,
+    pub(crate) health_file: Option<String>,
+    pub(crate) control_socket: Option<String>,
+    pub(crate) connect_timeout: Option<u64>,
+    pub(crate) request_timeout: Option<u64>,
+    pub(crate) proxy: Option<String>,
+    pub(crate) bind_address: Option<String>,
+    pub(crate) interface: Option<String>,
+    pub(crate) ca_file: Option<String>,
+    pub(crate) tls_accept_invalid_certs: Option<bool>,
+    pub(crate) tls_accept_invalid_hostnames: Option<bool>,
+    pub(crate) user: Option<String>,
+    pub(crate) group: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Merge, Clone)]
+/// Service the records are kept in.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Cloudflare,
+    /// A plugin command speaking the JSON protocol of `ExecProvider`.
+    Exec,
+}
+
+/// Where the Cloudflare API token is read from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenSource {
+    /// The `cloudflare-api-token` systemd credential if passed, otherwise the output of
+    /// `api_token_cmd` if set, otherwise the `api_token` value, optionally encrypted.
+    Config,
+    /// The platform keyring, as stored by `set-token`.
+    Keyring,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct CloudflareConfig {
     pub(crate) zone_id: Option<String>,
+    pub(crate) token_source: Option<TokenSource>,
     pub(crate) api_token: Option<String>,
+    pub(crate) api_token_key_file: Option<String>,
+    pub(crate) api_token_cmd: Option<String>,
     pub(crate) dns_record_id: Option<String>,
+    pub(crate) rate_limit_requests: Option<u64>,
+    pub(crate) rate_limit_period: Option<u64>,
+}
+
+/// DNS record kept in sync, selected by ID, by name or by marker. Unset intervals are
+/// inherited from the general configuration, and an unset zone or API token from the
+/// Cloudflare configuration.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RecordConfig {
+    pub(crate) dns_record_id: Option<String>,
+    /// Name pattern selecting every matching record of the zone instead of an ID, which may
+    /// contain `{hostname}` and `{env:NAME}` placeholders.
+    pub(crate) name: Option<String>,
+    #[serde(rename = "type")]
+    pub(crate) record_type: Option<String>,
+    pub(crate) zone_id: Option<String>,
+    pub(crate) api_token: Option<String>,
+    pub(crate) api_token_cmd: Option<String>,
+    pub(crate) wait_duration: Option<u64>,
+    pub(crate) schedule: Option<String>,
+    pub(crate) refresh_interval: Option<u64>,
+    /// Interface identifier of the target host, published by AAAA records after the detected
+    /// prefix.
+    pub(crate) ipv6_suffix: Option<String>,
+    pub(crate) ipv6_prefix_length: Option<u8>,
+    /// Keeps a TXT record of the same name telling when, by which version and from which host
+    /// the record was updated.
+    pub(crate) txt_record: Option<bool>,
+    /// Creates a record of the name and type if none exists.
+    pub(crate) create: Option<bool>,
+    /// Applied whenever the record is updated or created.
+    pub(crate) ttl: Option<i64>,
+    /// Applied whenever the record is updated or created.
+    pub(crate) proxied: Option<bool>,
+    /// Selects every A and AAAA record whose comment or tags carry it.
+    pub(crate) marker: Option<String>,
+    /// Name of the group the record is listed in.
+    #[serde(skip)]
+    pub(crate) group: Option<String>,
+}
+
+/// Named set of records sharing the settings they do not set themselves, and notifiers told
+/// about their changes.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RecordGroupConfig {
+    pub(crate) name: Option<String>,
+    pub(crate) zone_id: Option<String>,
+    pub(crate) api_token: Option<String>,
+    pub(crate) api_token_cmd: Option<String>,
+    #[serde(rename = "type")]
+    pub(crate) record_type: Option<String>,
+    pub(crate) wait_duration: Option<u64>,
+    pub(crate) schedule: Option<String>,
+    pub(crate) refresh_interval: Option<u64>,
+    pub(crate) ttl: Option<i64>,
+    pub(crate) proxied: Option<bool>,
+    pub(crate) txt_record: Option<bool>,
+    pub(crate) create: Option<bool>,
+    pub(crate) notification_urls: Option<Vec<String>>,
+    pub(crate) records: Option<Vec<RecordConfig>>,
+}
+
+impl RecordConfig {
+    /// Returns whether the provided Cloudflare record is the one configured, by ID or by name.
+    pub(crate) fn selects(&self, record: &CloudflareDnsResult) -> bool {
+        if let Some(marker) = self.marker.as_ref() {
+            return record.has_marker(marker)
+                && self.name.as_ref().is_none_or(|p| pattern::matches(p, record.name.as_str()))
+                && match self.record_type.as_ref() {
+                    Some(t) => t.eq_ignore_ascii_case(record.dns_type.as_str()),
+                    None => ["A", "AAAA"].contains(&record.dns_type.as_str()),
+                };
+        }
+
+        match self.name.as_ref() {
+            Some(pattern) => pattern::matches(pattern, record.name.as_str())
+                && self.record_type.as_ref().is_none_or(|t| t.eq_ignore_ascii_case(record.dns_type.as_str())),
+            None => self.dns_record_id.as_ref() == Some(&record.id),
+        }
+    }
+
+    /// Returns whether the record is selected by name or marker rather than by ID.
+    pub(crate) fn is_selector(&self) -> bool {
+        self.name.is_some() || self.marker.is_some()
+    }
+
+    /// Describes what selects the record, as its name pattern or marker.
+    pub(crate) fn selection(&self) -> String {
+        match (self.name.as_ref(), self.marker.as_ref()) {
+            (Some(name), Some(marker)) => format!("{} marked {}", name, marker),
+            (None, Some(marker)) => format!("records marked {}", marker),
+            (name, None) => name.cloned().unwrap_or_default(),
+        }
+    }
+}
+
+/// Hostnames of a zone kept in sync, `@` standing for the zone itself. Each hostname gets a
+/// record for every address family of the address policy, created if missing.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HostnamesConfig {
+    pub(crate) zone: Option<String>,
+    pub(crate) names: Option<Vec<String>>,
+    pub(crate) create: Option<bool>,
+}
+
+/// Marker selecting every A and AAAA record of the zone whose comment or tags carry it, so
+/// that records can be added from the Cloudflare dashboard.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    pub(crate) marker: Option<String>,
+}
+
+/// Mode for metered links, detecting the public IP over DNS only, revalidating the records
+/// rather than fetching them again where Cloudflare allows it, and waiting longer between
+/// checks while nothing changes. Requests sent each day are counted against a budget, past
+/// which the longest wait is used.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LowBandwidthConfig {
+    pub(crate) enabled: Option<bool>,
+    pub(crate) daily_request_budget: Option<u64>,
+    pub(crate) max_wait_duration: Option<u64>,
+}
+
+/// Times of the day, such as `02:00-02:30` in local time, within which records may or may not
+/// be updated. Changes detected while updates are not allowed are applied once they are.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateWindowsConfig {
+    pub(crate) allowed: Option<Vec<String>>,
+    pub(crate) blocked: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IpCheckConfig {
+    pub(crate) ip_source: Option<IpSource>,
+    pub(crate) ip_file: Option<String>,
+    pub(crate) interface: Option<String>,
+    pub(crate) ipv6_selection: Option<Ipv6Selection>,
+    pub(crate) address_policy: Option<AddressPolicy>,
+    pub(crate) strategy: Option<DetectionStrategy>,
+    pub(crate) urls: Option<Vec<String>>,
+    pub(crate) ipv6_urls: Option<Vec<String>>,
+    pub(crate) failure_threshold: Option<u64>,
+    pub(crate) cooldown: Option<u64>,
+    pub(crate) cache_ttl: Option<u64>,
+    pub(crate) probe_interval: Option<u64>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) headers: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HttpApiConfig {
+    pub(crate) enabled: Option<bool>,
+    pub(crate) listen_address: Option<String>,
+    pub(crate) auth_token: Option<String>,
+    pub(crate) webhook_secret: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Merge, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GrpcConfig {
+    pub(crate) enabled: Option<bool>,
+    pub(crate) listen_address: Option<String>,
+    pub(crate) auth_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SentryConfig {
+    pub(crate) dsn: Option<String>,
+    pub(crate) failure_threshold: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationsConfig {
+    pub(crate) urls: Option<Vec<String>>,
+    pub(crate) failure_threshold: Option<u64>,
+    pub(crate) rate_limit: Option<u64>,
+    pub(crate) digest: Option<bool>,
+    pub(crate) digest_interval: Option<u64>,
+    pub(crate) routes: Option<Vec<NotificationRouteConfig>>,
+    pub(crate) thresholds: Option<FailureThresholdsConfig>,
+}
+
+/// Consecutive failures tolerated before notifying, by operation or kind of failure, each
+/// falling back to `failure_threshold` when unset.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FailureThresholdsConfig {
+    pub(crate) detect_ip: Option<u64>,
+    pub(crate) fetch_records: Option<u64>,
+    pub(crate) update_records: Option<u64>,
+    pub(crate) network: Option<u64>,
+    pub(crate) auth: Option<u64>,
+    pub(crate) rate_limit: Option<u64>,
+    pub(crate) api: Option<u64>,
+    pub(crate) mismatch: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationRouteConfig {
+    pub(crate) url: String,
+    pub(crate) events: Vec<EventKind>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UptimeKumaConfig {
+    pub(crate) push_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    pub(crate) ip_check: Option<BackoffConfig>,
+    pub(crate) cloudflare: Option<BackoffConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BackoffConfig {
+    pub(crate) strategy: Option<BackoffStrategy>,
+    pub(crate) base: Option<u64>,
+    pub(crate) cap: Option<u64>,
+    pub(crate) max_attempts: Option<u32>,
+}
+
+impl BackoffConfig {
+    pub(crate) fn backoff(&self) -> Backoff {
+        Backoff {
+            strategy: self.strategy.unwrap(),
+            base: Duration::from_secs(self.base.unwrap()),
+            cap: Duration::from_secs(self.cap.unwrap()),
+            max_attempts: self.max_attempts.unwrap(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DnsPrecheckConfig {
+    pub(crate) enabled: Option<bool>,
+    pub(crate) doh_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderConfig {
+    #[serde(rename = "type")]
+    pub(crate) kind: Option<ProviderKind>,
+    pub(crate) command: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GeoIpConfig {
+    pub(crate) mmdb_file: Option<String>,
+    pub(crate) api_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TelemetryConfig {
+    pub(crate) otlp_endpoint: Option<String>,
+    pub(crate) service_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    pub(crate) version: Option<u64>,
+    pub(crate) records: Option<Vec<RecordConfig>>,
+    pub(crate) groups: Option<Vec<RecordGroupConfig>>,
     pub(crate) general: Option<GeneralConfig>,
     pub(crate) cloudflare: Option<CloudflareConfig>,
+    pub(crate) provider: Option<ProviderConfig>,
+    pub(crate) ip_check: Option<IpCheckConfig>,
+    pub(crate) http_api: Option<HttpApiConfig>,
+    pub(crate) grpc: Option<GrpcConfig>,
+    pub(crate) sentry: Option<SentryConfig>,
+    pub(crate) notifications: Option<NotificationsConfig>,
+    pub(crate) uptime_kuma: Option<UptimeKumaConfig>,
+    pub(crate) geoip: Option<GeoIpConfig>,
+    pub(crate) dns_precheck: Option<DnsPrecheckConfig>,
+    pub(crate) retry: Option<RetryConfig>,
+    pub(crate) telemetry: Option<TelemetryConfig>,
+    pub(crate) hostnames: Option<HostnamesConfig>,
+    pub(crate) discovery: Option<DiscoveryConfig>,
+    pub(crate) update_windows: Option<UpdateWindowsConfig>,
+    pub(crate) low_bandwidth: Option<LowBandwidthConfig>,
 }
 
 impl Default for GeneralConfig {
     fn default() -> Self {
         GeneralConfig {
-            wait_duration: Some(DEFAULT_WAIT_TIME)
+            wait_duration: Some(DEFAULT_WAIT_TIME),
+            schedule: Some(DEFAULT_NOT_SET.to_string()),
+            wait_jitter: Some(DEFAULT_WAIT_JITTER),
+            refresh_interval: Some(DEFAULT_REFRESH_INTERVAL),
+            selection_refresh: Some(DEFAULT_SELECTION_REFRESH),
+            heartbeat_interval: Some(DEFAULT_HEARTBEAT_INTERVAL),
+            health_file: Some(DEFAULT_NOT_SET.to_string()),
+            max_consecutive_failures: Some(DEFAULT_MAX_CONSECUTIVE_FAILURES),
+            update_concurrency: Some(DEFAULT_UPDATE_CONCURRENCY),
+            observe_only: Some(false),
+            control_socket: Some(State::get_state_dir()
+                .join(CONTROL_SOCKET_FILE_NAME)
+                .display()
+                .to_string()),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            request_timeout: Some(DEFAULT_REQUEST_TIMEOUT),
+            proxy: Some(DEFAULT_NOT_SET.to_string()),
+            bind_address: Some(DEFAULT_NOT_SET.to_string()),
+            interface: Some(DEFAULT_NOT_SET.to_string()),
+            ca_file: Some(DEFAULT_NOT_SET.to_string()),
+            tls_accept_invalid_certs: Some(false),
+            tls_accept_invalid_hostnames: Some(false),
+            user: Some(DEFAULT_NOT_SET.to_string()),
+            group: Some(DEFAULT_NOT_SET.to_string()),
         }
     }
 }
@@ -37,8 +413,179 @@ impl Default for CloudflareConfig {
     fn default() -> Self {
         CloudflareConfig {
             zone_id: Some(DEFAULT_NOT_SET.to_string()),
+            token_source: Some(TokenSource::Config),
             api_token: Some(DEFAULT_NOT_SET.to_string()),
-            dns_record_id: Some(DEFAULT_NOT_SET.to_string())
+            api_token_key_file: Some(DEFAULT_NOT_SET.to_string()),
+            api_token_cmd: Some(DEFAULT_NOT_SET.to_string()),
+            dns_record_id: Some(DEFAULT_NOT_SET.to_string()),
+            rate_limit_requests: Some(DEFAULT_CLOUDFLARE_RATE_LIMIT_REQUESTS),
+            rate_limit_period: Some(DEFAULT_CLOUDFLARE_RATE_LIMIT_PERIOD),
+        }
+    }
+}
+
+impl Default for IpCheckConfig {
+    fn default() -> Self {
+        IpCheckConfig {
+            ip_source: Some(IpSource::Http),
+            ip_file: Some(DEFAULT_NOT_SET.to_string()),
+            interface: Some(DEFAULT_NOT_SET.to_string()),
+            ipv6_selection: Some(Ipv6Selection::Stable),
+            address_policy: Some(AddressPolicy::V4Only),
+            strategy: Some(DetectionStrategy::Sequential),
+            urls: Some(DEFAULT_IP_CHECK_URLS.iter().map(|u| u.to_string()).collect()),
+            ipv6_urls: Some(DEFAULT_IPV6_CHECK_URLS.iter().map(|u| u.to_string()).collect()),
+            failure_threshold: Some(DEFAULT_IP_PROVIDER_FAILURE_THRESHOLD),
+            cooldown: Some(DEFAULT_IP_PROVIDER_COOLDOWN),
+            cache_ttl: Some(DEFAULT_IP_CACHE_TTL),
+            probe_interval: Some(DEFAULT_PROVIDER_PROBE_INTERVAL),
+            user_agent: Some(DEFAULT_NOT_SET.to_string()),
+            headers: Some(BTreeMap::new()),
+        }
+    }
+}
+
+impl Default for HttpApiConfig {
+    fn default() -> Self {
+        HttpApiConfig {
+            enabled: Some(false),
+            listen_address: Some(DEFAULT_HTTP_API_LISTEN_ADDRESS.to_string()),
+            auth_token: Some(DEFAULT_NOT_SET.to_string()),
+            webhook_secret: Some(DEFAULT_NOT_SET.to_string()),
+        }
+    }
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        GrpcConfig {
+            enabled: Some(false),
+            listen_address: Some(DEFAULT_GRPC_LISTEN_ADDRESS.to_string()),
+            auth_token: Some(DEFAULT_NOT_SET.to_string()),
+        }
+    }
+}
+
+impl Default for SentryConfig {
+    fn default() -> Self {
+        SentryConfig {
+            dsn: Some(DEFAULT_NOT_SET.to_string()),
+            failure_threshold: Some(DEFAULT_SENTRY_FAILURE_THRESHOLD),
+        }
+    }
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        NotificationsConfig {
+            urls: Some(Vec::new()),
+            failure_threshold: Some(DEFAULT_NOTIFICATION_FAILURE_THRESHOLD),
+            rate_limit: Some(0),
+            digest: Some(false),
+            digest_interval: Some(DEFAULT_NOTIFICATION_DIGEST_INTERVAL),
+            routes: Some(Vec::new()),
+            thresholds: Some(FailureThresholdsConfig::default()),
+        }
+    }
+}
+
+impl Default for UptimeKumaConfig {
+    fn default() -> Self {
+        UptimeKumaConfig {
+            push_url: Some(DEFAULT_NOT_SET.to_string()),
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            ip_check: Some(BackoffConfig::default()),
+            cloudflare: Some(BackoffConfig::default()),
+        }
+    }
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        BackoffConfig {
+            strategy: Some(BackoffStrategy::Exponential),
+            base: Some(DEFAULT_RETRY_BASE),
+            cap: Some(DEFAULT_RETRY_CAP),
+            max_attempts: Some(DEFAULT_RETRY_MAX_ATTEMPTS),
+        }
+    }
+}
+
+impl Default for DnsPrecheckConfig {
+    fn default() -> Self {
+        DnsPrecheckConfig {
+            enabled: Some(false),
+            doh_url: Some(DEFAULT_DOH_URL.to_string()),
+        }
+    }
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        ProviderConfig {
+            kind: Some(ProviderKind::Cloudflare),
+            command: Some(DEFAULT_NOT_SET.to_string()),
+        }
+    }
+}
+
+impl Default for GeoIpConfig {
+    fn default() -> Self {
+        GeoIpConfig {
+            mmdb_file: Some(DEFAULT_NOT_SET.to_string()),
+            api_url: Some(DEFAULT_NOT_SET.to_string()),
+        }
+    }
+}
+
+impl Default for HostnamesConfig {
+    fn default() -> Self {
+        HostnamesConfig {
+            zone: Some(DEFAULT_NOT_SET.to_string()),
+            names: Some(Vec::new()),
+            create: Some(true),
+        }
+    }
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        DiscoveryConfig {
+            marker: Some(DEFAULT_NOT_SET.to_string()),
+        }
+    }
+}
+
+impl Default for LowBandwidthConfig {
+    fn default() -> Self {
+        LowBandwidthConfig {
+            enabled: Some(false),
+            daily_request_budget: Some(DEFAULT_LOW_BANDWIDTH_DAILY_BUDGET),
+            max_wait_duration: Some(DEFAULT_LOW_BANDWIDTH_MAX_WAIT),
+        }
+    }
+}
+
+impl Default for UpdateWindowsConfig {
+    fn default() -> Self {
+        UpdateWindowsConfig {
+            allowed: Some(Vec::new()),
+            blocked: Some(Vec::new()),
+        }
+    }
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            otlp_endpoint: Some(DEFAULT_NOT_SET.to_string()),
+            service_name: Some(TELEMETRY_SCOPE.to_string()),
         }
     }
 }
@@ -46,95 +593,577 @@ impl Default for CloudflareConfig {
 impl Default for Config {
     fn default() -> Self {
         Config {
+            version: Some(CONFIG_VERSION),
+            records: Some(Vec::new()),
+            groups: Some(Vec::new()),
             general: Some(GeneralConfig::default()),
             cloudflare: Some(CloudflareConfig::default()),
+            provider: Some(ProviderConfig::default()),
+            ip_check: Some(IpCheckConfig::default()),
+            http_api: Some(HttpApiConfig::default()),
+            grpc: Some(GrpcConfig::default()),
+            sentry: Some(SentryConfig::default()),
+            notifications: Some(NotificationsConfig::default()),
+            uptime_kuma: Some(UptimeKumaConfig::default()),
+            geoip: Some(GeoIpConfig::default()),
+            dns_precheck: Some(DnsPrecheckConfig::default()),
+            retry: Some(RetryConfig::default()),
+            telemetry: Some(TelemetryConfig::default()),
+            hostnames: Some(HostnamesConfig::default()),
+            discovery: Some(DiscoveryConfig::default()),
+            update_windows: Some(UpdateWindowsConfig::default()),
+            low_bandwidth: Some(LowBandwidthConfig::default()),
+        }
+    }
+}
+
+/// Values provided on the command line, taking precedence over every other source.
+#[derive(Default, Debug, Clone)]
+pub struct ConfigOverrides {
+    pub path: Option<PathBuf>,
+    pub wait_duration: Option<u64>,
+    pub zone_id: Option<String>,
+    pub dns_record_id: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Cannot find the config folder or home directory.")]
+    NoConfigDir,
+    #[error("Unable to access the configuration file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Unable to load the configuration from {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: Box<figment::Error>,
+    },
+    #[error("Unknown configuration key `{key}` in {origin}{}", did_you_mean(suggestion))]
+    UnknownKey {
+        key: String,
+        suggestion: Option<&'static str>,
+        origin: String,
+    },
+    #[error("{path} uses configuration version {version}, which is newer than this release supports.")]
+    UnsupportedVersion {
+        version: u64,
+        path: PathBuf,
+    },
+    #[error("Invalid value for `{key}`: {reason}")]
+    Invalid {
+        key: &'static str,
+        reason: String,
+    },
+    #[error(transparent)]
+    Secret(#[from] SecretError),
+    #[error("`{key}` is not set. Please configure it in {path} and restart.")]
+    NotSet {
+        key: &'static str,
+        path: PathBuf,
+    },
+}
+
+impl ConfigError {
+    /// Wraps an error raised while extracting the configuration from `path`.
+    fn from_figment(path: &Path, error: figment::Error) -> ConfigError {
+        match &error.kind {
+            Kind::UnknownField(field, expected) => {
+                let origin = match &error.metadata {
+                    Some(Metadata { source: Some(Source::File(file)), .. }) => file.display().to_string(),
+                    Some(metadata) => metadata.name.to_string(),
+                    None => path.display().to_string(),
+                };
+
+                ConfigError::UnknownKey { key: error.path.join("."), suggestion: closest_key(field, expected), origin }
+            },
+            _ => ConfigError::Parse { path: path.to_path_buf(), source: Box::new(error) },
         }
     }
 }
 
+/// Returns the valid key closest to the provided unknown key, if any is close enough.
+fn closest_key(field: &str, expected: &[&'static str]) -> Option<&'static str> {
+    expected.iter()
+        .map(|key| (strsim::damerau_levenshtein(field, key), *key))
+        .filter(|(distance, _)| *distance <= MAX_KEY_SUGGESTION_DISTANCE)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, key)| key)
+}
+
+fn did_you_mean(suggestion: &Option<&'static str>) -> String {
+    match suggestion {
+        Some(key) => format!(", did you mean `{}`?", key),
+        None => String::new(),
+    }
+}
+
 impl Config {
-    pub fn load() -> Config {
-        let dir = Config::get_config_dir();
-        let filepath = dir.join(CONFIG_FILE_NAME);
+    /// Loads the configuration, ensuring every value required by the updater is set.
+    pub fn load(overrides: &ConfigOverrides) -> Result<Config, ConfigError> {
+        let mut config = Config::read(overrides)?;
+
+        if config.groups.as_ref().unwrap().iter().any(|g| g.name.is_none()) {
+            return Err(ConfigError::NotSet { key: "groups.name", path: Config::get_config_file_path(overrides)? });
+        }
+
+        config.expand_groups();
+        let records = config.records.as_ref().unwrap();
+
+        let provider_config = config.provider.as_ref().unwrap();
+        let exec = provider_config.kind == Some(ProviderKind::Exec);
+
+        if exec && provider_config.command.as_ref().unwrap() == DEFAULT_NOT_SET {
+            return Err(ConfigError::NotSet { key: "provider.command", path: Config::get_config_file_path(overrides)? });
+        }
+
+        // The default token is only required by records without their own, and plugins need none.
+        let default_token_required = !exec && (records.is_empty() || records.iter().any(|r| r.api_token.is_none() && r.api_token_cmd.is_none()));
+        config.resolve_token(overrides, default_token_required)?;
+        config.resolve_record_tokens()?;
+
+        if config.records().iter().any(|r| r.zone_id.as_ref().unwrap() == DEFAULT_NOT_SET) {
+            return Err(ConfigError::NotSet { key: "cloudflare.zone_id", path: Config::get_config_file_path(overrides)? });
+        }
+
+        for record in config.records.as_mut().unwrap() {
+            if let Some(name) = record.name.as_mut() {
+                *name = template::render(name).map_err(|reason| ConfigError::Invalid { key: "records.name", reason })?;
+            }
+        }
+
+        let ip_check_config = config.ip_check.as_ref().unwrap();
+        if ip_check_config.ip_source == Some(IpSource::File) && ip_check_config.ip_file.as_ref().unwrap() == DEFAULT_NOT_SET {
+            return Err(ConfigError::NotSet { key: "ip_check.ip_file", path: Config::get_config_file_path(overrides)? });
+        }
+
+        if ip_check_config.ip_source == Some(IpSource::Interface) && ip_check_config.interface.as_ref().unwrap() == DEFAULT_NOT_SET {
+            return Err(ConfigError::NotSet { key: "ip_check.interface", path: Config::get_config_file_path(overrides)? });
+        }
+
+        for (key, urls) in [("ip_check.urls", &ip_check_config.urls), ("ip_check.ipv6_urls", &ip_check_config.ipv6_urls)] {
+            for url in urls.as_ref().unwrap() {
+                Transport::of(url).map_err(|reason| ConfigError::Invalid { key, reason })?;
+            }
+        }
+
+        let hostnames_config = config.hostnames.as_mut().unwrap();
+        for name in hostnames_config.names.as_mut().unwrap() {
+            *name = template::render(name).map_err(|reason| ConfigError::Invalid { key: "hostnames.names", reason })?;
+        }
+
+        let has_hostnames = !hostnames_config.names.as_ref().unwrap().is_empty();
+        if has_hostnames && hostnames_config.zone.as_ref().unwrap() == DEFAULT_NOT_SET {
+            return Err(ConfigError::NotSet { key: "hostnames.zone", path: Config::get_config_file_path(overrides)? });
+        }
+
+        let records = config.records.as_ref().unwrap();
+
+        if records.iter().any(|r| r.create == Some(true) && (r.record_type.is_none() || r.name.as_ref().is_none_or(|n| n.contains(['*', '?'])))) {
+            return Err(ConfigError::Invalid {
+                key: "records.create",
+                reason: "requires `records.type` and a `records.name` without wildcards".to_string(),
+            });
+        }
+
+        if let Some(record_type) = records.iter().filter_map(|r| r.record_type.as_deref()).find(|t| !["A", "AAAA"].contains(&t.to_uppercase().as_str())) {
+            return Err(ConfigError::Invalid {
+                key: "records.type",
+                reason: format!("must be A or AAAA, not {}", record_type),
+            });
+        }
+
+        if let Some(ttl) = records.iter().filter_map(|r| r.ttl).find(|&t| t != 1 && !(MIN_RECORD_TTL..=MAX_RECORD_TTL).contains(&t)) {
+            return Err(ConfigError::Invalid {
+                key: "records.ttl",
+                reason: format!("must be 1 (automatic) or between {} and {} seconds, not {}", MIN_RECORD_TTL, MAX_RECORD_TTL, ttl),
+            });
+        }
+
+        let has_discovery = config.discovery.as_ref().unwrap().marker.as_ref().unwrap() != DEFAULT_NOT_SET;
+        if records.is_empty() && !has_hostnames && !has_discovery && config.cloudflare.as_ref().unwrap().dns_record_id.as_ref().unwrap() == DEFAULT_NOT_SET {
+            return Err(ConfigError::NotSet { key: "cloudflare.dns_record_id", path: Config::get_config_file_path(overrides)? });
+        }
+
+        let has_id = |r: &RecordConfig| r.dns_record_id.as_deref().unwrap_or(DEFAULT_NOT_SET) != DEFAULT_NOT_SET;
+
+        if records.iter().any(|r| has_id(r) && r.name.is_some()) {
+            return Err(ConfigError::Invalid {
+                key: "records.name",
+                reason: "cannot be set together with `records.dns_record_id`".to_string(),
+            });
+        }
+
+        if records.iter().any(|r| !has_id(r) && !r.is_selector()) {
+            return Err(ConfigError::NotSet { key: "records.dns_record_id", path: Config::get_config_file_path(overrides)? });
+        }
+
+        Ok(config)
+    }
+
+    /// Moves the records of each group to the other records, inheriting the settings of the
+    /// group they do not set themselves.
+    fn expand_groups(&mut self) {
+        let records = self.records.as_mut().unwrap();
+
+        for group in self.groups.as_mut().unwrap() {
+            records.extend(group.records.take().unwrap_or_default().into_iter().map(|r| RecordConfig {
+                zone_id: r.zone_id.or(group.zone_id.clone()),
+                api_token: r.api_token.or(group.api_token.clone()),
+                api_token_cmd: r.api_token_cmd.or(group.api_token_cmd.clone()),
+                record_type: r.record_type.or(group.record_type.clone()),
+                wait_duration: r.wait_duration.or(group.wait_duration),
+                schedule: r.schedule.or(group.schedule.clone()),
+                refresh_interval: r.refresh_interval.or(group.refresh_interval),
+                ttl: r.ttl.or(group.ttl),
+                proxied: r.proxied.or(group.proxied),
+                txt_record: r.txt_record.or(group.txt_record),
+                create: r.create.or(group.create),
+                group: group.name.clone(),
+                ..r
+            }));
+        }
+    }
+
+    /// Returns the records to keep in sync, with their intervals resolved. Falls back to
+    /// `cloudflare.dns_record_id` when no records are listed.
+    pub fn records(&self) -> Vec<RecordConfig> {
+        let general_config = self.general.as_ref().unwrap();
+        let cloudflare_config = self.cloudflare.as_ref().unwrap();
+        let mut records = self.records.clone().unwrap();
+        records.extend(self.hostname_records());
+
+        let marker = self.discovery.as_ref().unwrap().marker.as_ref().unwrap();
+        if marker != DEFAULT_NOT_SET {
+            records.push(RecordConfig { marker: Some(marker.clone()), ..RecordConfig::default() });
+        }
+
+        if records.is_empty() {
+            records.push(RecordConfig {
+                dns_record_id: cloudflare_config.dns_record_id.clone(),
+                ..RecordConfig::default()
+            });
+        }
+
+        records.into_iter()
+            .map(|r| RecordConfig {
+                zone_id: r.zone_id.or(cloudflare_config.zone_id.clone()),
+                api_token: r.api_token.or(cloudflare_config.api_token.clone()),
+                wait_duration: r.wait_duration.or(general_config.wait_duration),
+                schedule: r.schedule.or(general_config.schedule.clone()),
+                refresh_interval: r.refresh_interval.or(general_config.refresh_interval),
+                ipv6_prefix_length: r.ipv6_prefix_length.or(Some(DEFAULT_IPV6_PREFIX_LENGTH)),
+                txt_record: r.txt_record.or(Some(false)),
+                create: r.create.or(Some(false)),
+                ..r
+            })
+            .collect()
+    }
+
+    /// Returns a record of every type of the address policy for each configured hostname.
+    fn hostname_records(&self) -> Vec<RecordConfig> {
+        let hostnames_config = self.hostnames.as_ref().unwrap();
+        let zone = hostnames_config.zone.as_ref().unwrap();
+        let families = self.ip_check.as_ref().unwrap().address_policy.unwrap().published_families();
+
+        hostnames_config.names.as_ref().unwrap().iter()
+            .flat_map(|name| {
+                let name = match name.as_str() {
+                    "@" => zone.clone(),
+                    _ => format!("{}.{}", name, zone),
+                };
+
+                families.iter().map(move |&ipv6| RecordConfig {
+                    name: Some(name.clone()),
+                    record_type: Some(if ipv6 { "AAAA" } else { "A" }.to_string()),
+                    create: hostnames_config.create,
+                    ..RecordConfig::default()
+                })
+            })
+            .collect()
+    }
+
+    /// Loads the configuration, only ensuring the values required to query the zone are set.
+    pub fn load_for_zone(overrides: &ConfigOverrides) -> Result<Config, ConfigError> {
+        let config = Config::load_for_token(overrides)?;
+        let cloudflare_config = config.cloudflare.as_ref().unwrap();
+
+        if cloudflare_config.zone_id.as_ref().unwrap() == DEFAULT_NOT_SET {
+            return Err(ConfigError::NotSet { key: "cloudflare.zone_id", path: Config::get_config_file_path(overrides)? });
+        }
+
+        Ok(config)
+    }
+
+    /// Loads the configuration, only ensuring the API token is set and resolving it from its source.
+    pub fn load_for_token(overrides: &ConfigOverrides) -> Result<Config, ConfigError> {
+        let mut config = Config::read(overrides)?;
+        config.resolve_token(overrides, true)?;
+
+        Ok(config)
+    }
+
+    /// Resolves the default API token from its source, failing if it is required but not set.
+    fn resolve_token(&mut self, overrides: &ConfigOverrides, required: bool) -> Result<(), ConfigError> {
+        let cloudflare_config = self.cloudflare.as_mut().unwrap();
+
+        if cloudflare_config.token_source.unwrap() == TokenSource::Keyring {
+            debug!("Reading the API token from the keyring.");
+            cloudflare_config.api_token = Some(secret::get_keyring_token()?);
+            return Ok(());
+        }
+
+        if let Some(token) = secret::read_credential_token()? {
+            debug!("Reading the API token from the {} systemd credential.", API_TOKEN_CREDENTIAL);
+            cloudflare_config.api_token = Some(token);
+            return Ok(());
+        }
+
+        let api_token_cmd = cloudflare_config.api_token_cmd.as_ref().unwrap();
+        if api_token_cmd != DEFAULT_NOT_SET {
+            debug!("Reading the API token from `{}`.", api_token_cmd);
+            cloudflare_config.api_token = Some(secret::run_token_command(api_token_cmd)?);
+            return Ok(());
+        }
+
+        let api_token = cloudflare_config.api_token.as_ref().unwrap();
+
+        if api_token == DEFAULT_NOT_SET {
+            if !required {
+                return Ok(());
+            }
+
+            return Err(ConfigError::NotSet { key: "cloudflare.api_token", path: Config::get_config_file_path(overrides)? });
+        }
+
+        if secret::is_encrypted(api_token) {
+            debug!("Decrypting the API token.");
+            let key = secret::load_key(cloudflare_config.api_token_key_file.as_ref().unwrap())?;
+            cloudflare_config.api_token = Some(secret::decrypt_token(api_token, &key)?);
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the API tokens set on records from their command, or decrypts them.
+    fn resolve_record_tokens(&mut self) -> Result<(), ConfigError> {
+        let key_file = self.cloudflare.as_ref().unwrap().api_token_key_file.clone().unwrap();
+
+        for record in self.records.as_mut().unwrap() {
+            if let Some(api_token_cmd) = record.api_token_cmd.take() {
+                debug!("Reading the API token of {} from `{}`.", record.dns_record_id.as_deref().unwrap_or_default(), api_token_cmd);
+                record.api_token = Some(secret::run_token_command(api_token_cmd.as_str())?);
+                continue;
+            }
+
+            if let Some(api_token) = record.api_token.as_ref().filter(|t| secret::is_encrypted(t)) {
+                let key = secret::load_key(key_file.as_str())?;
+                record.api_token = Some(secret::decrypt_token(api_token, &key)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the configuration without validating it, creating the default file if missing.
+    pub fn read(overrides: &ConfigOverrides) -> Result<Config, ConfigError> {
+        let filepath = Config::get_config_file_path(overrides)?;
 
         if !filepath.exists() {
             debug!("Creating default config.");
-            Config::create_default_config_file()
-                .expect("Unable to create default config file.");
+            Config::create_default_config_file(&filepath)
+                .map_err(|source| ConfigError::Io { path: filepath.clone(), source })?;
 
-            info!("Default configuration file created at {}.\nPlease fill it out and restart.", filepath.display());
+            info!("Default configuration file created at {}.", filepath.display());
+        }
 
-            std::process::exit(0);
+        migration::migrate(&filepath)?;
+
+        debug!("Loading config from {}.", filepath.display());
+        Config::figment(&filepath, overrides)
+            .extract()
+            .map_err(|e| ConfigError::from_figment(&filepath, e))
+    }
+
+    /// Layers the configuration sources, from lowest to highest precedence: defaults,
+    /// the configuration file, `CLOUDFLARE_DDNS_` environment variables and CLI flags.
+    pub fn figment(filepath: &Path, overrides: &ConfigOverrides) -> Figment {
+        let mut figment = Figment::from(Serialized::defaults(Config::default()))
+            .merge(Toml::file(filepath))
+            .merge(Env::prefixed(CONFIG_ENV_PREFIX).ignore(&[TOKEN_PASSPHRASE_KEY]).split("__"));
+
+        if let Some(wait_duration) = overrides.wait_duration {
+            figment = figment.merge(Serialized::default("general.wait_duration", wait_duration));
         }
 
-        debug!("Config exists at {}, attempting to load.", filepath.display());
-        let config_file = fs::File::open(&filepath).unwrap_or_else(|_| {
-            panic!("Unable to load config file: {}", filepath.display());
-        });
+        if let Some(zone_id) = &overrides.zone_id {
+            figment = figment.merge(Serialized::default("cloudflare.zone_id", zone_id));
+        }
 
-        let mut reader = BufReader::new(config_file);
-        let mut contents = String::new();
+        if let Some(dns_record_id) = &overrides.dns_record_id {
+            figment = figment.merge(Serialized::default("cloudflare.dns_record_id", dns_record_id));
+        }
 
-        reader.read_to_string(&mut contents)
-            .expect("Unable to read config file. Is the encoding UTF-8?");
+        figment
+    }
 
-        let config = toml::from_str::<Config>(contents.as_str())
-            .expect("Unable to parse config file. Is it a valid TOML?")
-            .merge_custom(Config::default());
+    /// Parses the provided TOML, filling any missing value with its default.
+    pub fn parse(contents: &str) -> Result<Config, Box<figment::Error>> {
+        let mut config: Config = Figment::from(Serialized::defaults(Config::default()))
+            .merge(Toml::string(contents))
+            .extract()
+            .map_err(Box::new)?;
 
-        let cloudflare_config = config.cloudflare.as_ref().unwrap();
+        config.expand_groups();
+        Ok(config)
+    }
 
-        if cloudflare_config.api_token.as_ref().unwrap() == DEFAULT_NOT_SET
-            || cloudflare_config.zone_id.as_ref().unwrap() == DEFAULT_NOT_SET
-            || cloudflare_config.dns_record_id.as_ref().unwrap() == DEFAULT_NOT_SET {
-            warn!("Please ensure all values are configured in the configuration file located at {} and restart.", filepath.display());
+    /// Returns the effective configuration as TOML, merged from every source, with the
+    /// API tokens and other secrets redacted so that it can be shared.
+    pub fn export(overrides: &ConfigOverrides) -> Result<String, ConfigError> {
+        let mut config = Config::read(overrides)?;
+        let redact = |value: &mut Option<String>| {
+            if value.as_deref().is_some_and(|v| v != DEFAULT_NOT_SET) {
+                *value = Some(REDACTED_VALUE.to_string());
+            }
+        };
+
+        let cloudflare_config = config.cloudflare.as_mut().unwrap();
+        redact(&mut cloudflare_config.api_token);
 
-            std::process::exit(0);
+        for record in config.records.as_mut().unwrap() {
+            redact(&mut record.api_token);
+        }
+
+        for group in config.groups.as_mut().unwrap() {
+            redact(&mut group.api_token);
+            for record in group.records.iter_mut().flatten() {
+                redact(&mut record.api_token);
+            }
         }
 
-        config
+        let http_api_config = config.http_api.as_mut().unwrap();
+        redact(&mut http_api_config.auth_token);
+        redact(&mut http_api_config.webhook_secret);
+        redact(&mut config.grpc.as_mut().unwrap().auth_token);
+
+        redact(&mut config.sentry.as_mut().unwrap().dsn);
+        redact(&mut config.uptime_kuma.as_mut().unwrap().push_url);
+
+        // Proxy and lookup URLs may carry a password or an API key, the rest helps debugging.
+        for url in [&mut config.general.as_mut().unwrap().proxy, &mut config.geoip.as_mut().unwrap().api_url] {
+            if let Some(url) = url.as_mut().filter(|u| u.as_str() != DEFAULT_NOT_SET) {
+                *url = redact_url_credentials(url);
+            }
+        }
+
+        // Notification URLs carry the credentials of their service.
+        let notifications_config = config.notifications.as_mut().unwrap();
+        let route_urls = notifications_config.routes.as_mut().unwrap().iter_mut().map(|r| &mut r.url);
+        let group_urls = config.groups.as_mut().unwrap().iter_mut().flat_map(|g| g.notification_urls.iter_mut().flatten());
+        for url in notifications_config.urls.as_mut().unwrap().iter_mut().chain(route_urls).chain(group_urls) {
+            *url = match url.split_once("://") {
+                Some((scheme, _)) => format!("{}://{}", scheme, REDACTED_VALUE),
+                None => REDACTED_VALUE.to_string(),
+            };
+        }
+
+        // Converted to a value first, so that arrays of tables like the records of a group are
+        // emitted after the plain values.
+        Ok(toml::to_string(&toml::Value::try_from(&config).unwrap()).unwrap())
+    }
+
+    /// Replaces the configuration file with the provided TOML once it loads successfully,
+    /// keeping the previous file as a backup. Returns the path of the configuration file.
+    pub fn import(contents: &str, overrides: &ConfigOverrides) -> Result<PathBuf, ConfigError> {
+        let filepath = Config::get_config_file_path(overrides)?;
+
+        // Staged outside the configuration directory, so that a migration backup of the
+        // imported file does not end up next to the configuration file.
+        let staging_dir = std::env::temp_dir().join(format!("cloudflare-ddns-import-{}", std::process::id()));
+        let staged = staging_dir.join(CONFIG_FILE_NAME);
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| ConfigError::Io { path, source }
+        };
+
+        fs::create_dir_all(&staging_dir).map_err(io_error(&staging_dir))?;
+        let result = fs::write(&staged, contents)
+            .map_err(io_error(&staged))
+            .and_then(|_| Config::load(&ConfigOverrides { path: Some(staged.clone()), ..overrides.clone() }))
+            .and_then(|_| fs::read_to_string(&staged).map_err(io_error(&staged)));
+        let _ = fs::remove_dir_all(&staging_dir);
+        let imported = result?;
+
+        if filepath.exists() {
+            let mut name = filepath.file_name().unwrap_or_default().to_os_string();
+            name.push(".bak");
+            fs::copy(&filepath, filepath.with_file_name(name)).map_err(io_error(&filepath))?;
+        } else if let Some(dir) = filepath.parent() {
+            fs::create_dir_all(dir).map_err(io_error(dir))?;
+        }
+
+        fs::write(&filepath, imported).map_err(io_error(&filepath))?;
+        Ok(filepath)
+    }
+
+    /// Returns the path of the configuration file.
+    pub fn get_config_file_path(overrides: &ConfigOverrides) -> Result<PathBuf, ConfigError> {
+        match &overrides.path {
+            Some(path) => Ok(path.clone()),
+            None => Ok(Config::get_config_dir()?.join(CONFIG_FILE_NAME)),
+        }
     }
 
     /// Initializes the default configuration file.
-    fn create_default_config_file() -> Result<(), std::io::Error> {
-        let dir = Config::get_config_dir();
-        let filepath = dir.join(CONFIG_FILE_NAME);
+    fn create_default_config_file(filepath: &Path) -> Result<(), std::io::Error> {
         let config = Config::default();
 
-        fs::create_dir_all(Config::get_config_dir())?;
-        let mut config_file_path = fs::File::create(filepath)?;
+        if let Some(dir) = filepath.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut config_file = fs::File::create(filepath)?;
 
-        config_file_path.write_all(
+        config_file.write_all(DEFAULT_CONFIG_HEADER.as_bytes())?;
+        config_file.write_all(
             toml::to_string(&config)
                 .unwrap()
-                .as_bytes())
-            .unwrap();
+                .as_bytes())?;
 
         Ok(())
     }
 
     /// Returns the configuration directory.
-    fn get_config_dir() -> PathBuf {
+    fn get_config_dir() -> Result<PathBuf, ConfigError> {
         match dirs::config_dir() {
             Some(dir) => {
-                dir.join(Path::new(CONFIG_FOLDER_NAME))
+                Ok(dir.join(Path::new(CONFIG_FOLDER_NAME)))
             },
             None => {
-                dirs::config_dir()
-                    .expect("Cannot get config folder or home directory.")
-                    .join(format!(".{}", CONFIG_FOLDER_NAME))
+                dirs::home_dir()
+                    .map(|dir| dir.join(format!(".{}", CONFIG_FOLDER_NAME)))
+                    .ok_or(ConfigError::NoConfigDir)
             }
         }
     }
+}
 
-    /// Custom merge for the Config object and it's children.
-    fn merge_custom(mut self, other: Config) -> Self {
-        self.merge(other.clone());
+/// Replaces the user info and the query of the URL, keeping the rest of it as written.
+fn redact_url_credentials(url: &str) -> String {
+    let (url, query) = match url.split_once('?') {
+        Some((url, _)) => (url, format!("?{}", REDACTED_VALUE)),
+        None => (url, String::new()),
+    };
 
-        let mut cloudflare = self.cloudflare.unwrap();
-        cloudflare.merge(other.cloudflare.unwrap());
-        self.cloudflare = Some(cloudflare);
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return format!("{}{}", url, query);
+    };
 
-        self
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    match rest[..authority_end].rfind('@') {
+        Some(at) => format!("{}://{}{}{}", scheme, REDACTED_VALUE, &rest[at..], query),
+        None => format!("{}://{}{}", scheme, rest, query),
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,95 @@
 pub const CONFIG_FOLDER_NAME: &str = "cloudflare-dynamic-ip-updater";
 pub const CONFIG_FILE_NAME: &str = "config.toml";
+pub const CONFIG_VERSION: u64 = 1;
+pub const CONFIG_ENV_PREFIX: &str = "CLOUDFLARE_DDNS_";
+pub const TOKEN_PASSPHRASE_KEY: &str = "TOKEN_PASSPHRASE";
+pub const TOKEN_PASSPHRASE_ENV: &str = "CLOUDFLARE_DDNS_TOKEN_PASSPHRASE";
+pub const CREDENTIALS_DIRECTORY_ENV: &str = "CREDENTIALS_DIRECTORY";
+pub const API_TOKEN_CREDENTIAL: &str = "cloudflare-api-token";
+pub const KEYRING_TOKEN_USER: &str = "api_token";
+pub const ENCRYPTED_TOKEN_PREFIX: &str = "encrypted:";
+pub const MAX_KEY_SUGGESTION_DISTANCE: usize = 2;
+pub const DEFAULT_CONFIG_HEADER: &str = "\
+# Configuration for cloudflare-dynamic-ip-updater.
+#
+# Values are layered from lowest to highest precedence:
+#   1. Built-in defaults.
+#   2. This file.
+#   3. Environment variables prefixed with CLOUDFLARE_DDNS_, using `__` between
+#      the section and the key, e.g. CLOUDFLARE_DDNS_CLOUDFLARE__API_TOKEN.
+#   4. Command line flags, e.g. --wait-duration.
+
+";
+
+pub const CLOUDFLARE_API_BASE_URL: &str = "https://api.cloudflare.com/client/v4";
+pub const DEFAULT_CLOUDFLARE_RATE_LIMIT_REQUESTS: u64 = 1200;
+pub const DEFAULT_CLOUDFLARE_RATE_LIMIT_PERIOD: u64 = 300;
+pub const CLOUDFLARE_DNS_RECORDS_PAGE_SIZE: u64 = 100;
+pub const CLOUDFLARE_ZONES_PAGE_SIZE: u64 = 50;
+pub const REQUEST_DURATION_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+pub const DEFAULT_IP_CHECK_URLS: [&str; 3] = ["https://checkip.amazonaws.com", "https://api.ipify.org", "https://ipv4.icanhazip.com"];
+pub const DEFAULT_IPV6_CHECK_URLS: [&str; 2] = ["https://api6.ipify.org", "https://ipv6.icanhazip.com"];
+pub const DEFAULT_DNS_IP_CHECK_URLS: [&str; 2] = ["dns://208.67.222.222/myip.opendns.com", "dns://1.1.1.1/whoami.cloudflare?type=TXT&class=CH"];
+pub const DEFAULT_DNS_IPV6_CHECK_URLS: [&str; 2] = ["dns://[2620:119:35::35]/myip.opendns.com", "dns://[2606:4700:4700::1111]/whoami.cloudflare?type=TXT&class=CH"];
+pub const DEFAULT_LOW_BANDWIDTH_DAILY_BUDGET: u64 = 500;
+pub const DEFAULT_LOW_BANDWIDTH_MAX_WAIT: u64 = 3600;
+pub const DEFAULT_IP_PROVIDER_FAILURE_THRESHOLD: u64 = 3;
+pub const DEFAULT_IP_PROVIDER_COOLDOWN: u64 = 300;
+pub const DEFAULT_IP_CACHE_TTL: u64 = 5;
+pub const DNS_QUERY_TIMEOUT: u64 = 5;
+pub const DEFAULT_PROVIDER_PROBE_INTERVAL: u64 = 3600;
+pub const PROVIDER_LATENCY_WEIGHT: f64 = 0.3;
+pub const IP_FILE_POLL_INTERVAL: u64 = 1;
 
 pub const DEFAULT_WAIT_TIME: u64 = 60;
-pub const DEFAULT_NOT_SET: &str = "NOT SET";
\ No newline at end of file
+pub const DEFAULT_WAIT_JITTER: u64 = 0;
+pub const DEFAULT_HEARTBEAT_INTERVAL: u64 = 60;
+pub const DEFAULT_SELECTION_REFRESH: u64 = 3600;
+pub const DEFAULT_IPV6_PREFIX_LENGTH: u8 = 64;
+pub const COMPANION_TXT_TTL: i64 = 300;
+pub const COMPANION_TXT_PREFIX: &str = "updated=";
+pub const CREATED_RECORD_TTL: i64 = 1;
+pub const MIN_RECORD_TTL: i64 = 60;
+pub const MIN_ENTERPRISE_RECORD_TTL: i64 = 30;
+pub const MAX_RECORD_TTL: i64 = 86400;
+pub const PROXIABLE_RECORD_TYPES: [&str; 3] = ["A", "AAAA", "CNAME"];
+pub const MANAGED_RECORD_COMMENT: &str = "Managed by cloudflare-dynamic-ip-updater";
+pub const DEFAULT_REFRESH_INTERVAL: u64 = 3600;
+pub const DEFAULT_MAX_CONSECUTIVE_FAILURES: u64 = 0;
+pub const DEFAULT_UPDATE_CONCURRENCY: usize = 4;
+pub const DEFAULT_RETRY_BASE: u64 = 5;
+pub const DEFAULT_RETRY_CAP: u64 = 300;
+pub const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 0;
+pub const EXEC_PROVIDER_TIMEOUT: u64 = 30;
+pub const DEFAULT_DOH_URL: &str = "https://cloudflare-dns.com/dns-query";
+pub const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+pub const DEFAULT_REQUEST_TIMEOUT: u64 = 30;
+pub const DEFAULT_NOT_SET: &str = "NOT SET";
+pub const REDACTED_VALUE: &str = "REDACTED";
+pub const STATE_FILE_NAME: &str = "state.json";
+pub const CONTROL_SOCKET_FILE_NAME: &str = "control.sock";
+pub const LOCK_FILE_NAME: &str = "updater.lock";
+pub const EVENT_LOG_SOURCE: &str = "cloudflare-dynamic-ip-updater";
+pub const MAX_RECENT_ERRORS: usize = 10;
+pub const MAX_HISTORY_ENTRIES: usize = 50;
+pub const MAX_TUI_LOG_ENTRIES: usize = 500;
+
+pub const PEM_CERTIFICATE_END: &str = "-----END CERTIFICATE-----";
+
+pub const DEFAULT_HTTP_API_LISTEN_ADDRESS: &str = "127.0.0.1:8080";
+pub const DEFAULT_GRPC_LISTEN_ADDRESS: &str = "127.0.0.1:50051";
+
+pub const DEFAULT_SENTRY_FAILURE_THRESHOLD: u64 = 3;
+pub const DEFAULT_NOTIFICATION_FAILURE_THRESHOLD: u64 = 3;
+pub const DEFAULT_NOTIFICATION_DIGEST_INTERVAL: u64 = 3600;
+pub const NOTIFICATION_RATE_LIMIT_PERIOD: u64 = 3600;
+pub const SENTRY_FLUSH_TIMEOUT: u64 = 2;
+
+pub const TELEMETRY_SCOPE: &str = "cloudflare-dynamic-ip-updater";
+
+pub const EXIT_NETWORK_ERROR: i32 = 69;
+pub const EXIT_ALREADY_RUNNING: i32 = 75;
+pub const EXIT_AUTH_ERROR: i32 = 77;
+pub const EXIT_CONFIG_ERROR: i32 = 78;
+
+pub const CLOUDFLARE_AUTH_ERROR_CODES: [i64; 5] = [6003, 6111, 9106, 9109, 10000];
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use log::info;
+#[cfg(unix)]
+use log::{debug, error, warn};
+#[cfg(unix)]
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{watch, Notify};
+
+use crate::metrics::RequestMetrics;
+use crate::state::State;
+
+/// Runtime state shared between the updater loop and the control surfaces.
+#[derive(Default)]
+pub struct ControlHandle {
+    pub state: Mutex<State>,
+    pub paused: AtomicBool,
+    pub check_now: Notify,
+    pub reload: Notify,
+    pub next_check: Mutex<Option<Instant>>,
+    /// Public IP pushed by a router or script, used by the next check instead of detecting it.
+    pub pushed_ip: Mutex<Option<IpAddr>>,
+    /// Durations and failures of the requests of the updater.
+    pub requests: Arc<RequestMetrics>,
+    /// Bumped each time the state is published, for streaming subscribers.
+    pub updates: watch::Sender<u64>,
+    persist: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    #[serde(flatten)]
+    pub state: State,
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub success: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<DaemonStatus>,
+}
+
+impl ControlHandle {
+    pub fn new(state: State) -> ControlHandle {
+        ControlHandle {
+            state: Mutex::new(state),
+            persist: true,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a handle whose state is never written to the state file.
+    pub fn in_memory(state: State) -> ControlHandle {
+        ControlHandle {
+            state: Mutex::new(state),
+            ..Default::default()
+        }
+    }
+
+    /// Persists the provided state and makes it visible to the control surfaces.
+    pub fn update_state(&self, state: &State) {
+        if self.persist {
+            state.save();
+        }
+
+        *self.state.lock().unwrap() = state.clone();
+        self.updates.send_modify(|n| *n += 1);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Hands the provided public IP to the updater and checks the records immediately.
+    pub fn push_ip(&self, ip: IpAddr) -> ControlResponse {
+        info!("Public IP {} pushed, checking now.", ip);
+        *self.pushed_ip.lock().unwrap() = Some(ip);
+        self.check_now.notify_one();
+        ControlResponse::ok("IP received.")
+    }
+
+    /// Returns a snapshot of the current daemon status.
+    pub fn status(&self) -> DaemonStatus {
+        DaemonStatus {
+            state: self.state.lock().unwrap().clone(),
+            paused: self.is_paused(),
+        }
+    }
+
+    /// Executes a single control command.
+    pub fn execute(&self, command: &str) -> ControlResponse {
+        match command {
+            "check-now" => {
+                info!("Immediate check requested.");
+                self.check_now.notify_one();
+                ControlResponse::ok("Check triggered.")
+            },
+            "reload" => {
+                info!("Configuration reload requested.");
+                self.reload.notify_one();
+                ControlResponse::ok("Reload triggered.")
+            },
+            "pause" => {
+                info!("Updater paused, DNS records will not be updated.");
+                self.paused.store(true, Ordering::SeqCst);
+                ControlResponse::ok("Updater paused.")
+            },
+            "resume" => {
+                info!("Updater resumed.");
+                self.paused.store(false, Ordering::SeqCst);
+                ControlResponse::ok("Updater resumed.")
+            },
+            "status" => ControlResponse {
+                status: Some(self.status()),
+                ..ControlResponse::ok("Status retrieved.")
+            },
+            _ => ControlResponse {
+                success: false,
+                message: format!("Unknown command: {}", command),
+                status: None,
+            },
+        }
+    }
+}
+
+impl ControlResponse {
+    fn ok(message: &str) -> ControlResponse {
+        ControlResponse {
+            success: true,
+            message: message.to_string(),
+            status: None,
+        }
+    }
+}
+
+/// Pauses the updater on `SIGUSR1`, resumes it on `SIGUSR2` and reloads its configuration
+/// on `SIGHUP`.
+#[cfg(unix)]
+pub async fn handle_signals(handle: Arc<ControlHandle>) {
+    let signals = (signal(SignalKind::user_defined1()), signal(SignalKind::user_defined2()), signal(SignalKind::hangup()));
+    let (mut pause, mut resume, mut reload) = match signals {
+        (Ok(p), Ok(r), Ok(h)) => (p, r, h),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            warn!("Unable to listen for pause, resume and reload signals: {:?}", e);
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            _ = pause.recv() => handle.execute("pause"),
+            _ = resume.recv() => handle.execute("resume"),
+            _ = reload.recv() => handle.execute("reload"),
+        };
+    }
+}
+
+/// Listens on the provided Unix socket for control commands, one per line.
+#[cfg(unix)]
+pub async fn serve(path: &Path, handle: Arc<ControlHandle>) {
+    if path.exists() {
+        debug!("Removing stale control socket at {}.", path.display());
+        let _ = std::fs::remove_file(path);
+    }
+
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+
+    let listener = match UnixListener::bind(path) {
+        Ok(l) => l,
+        Err(e) => {
+            error!("Unable to bind control socket at {}: {:?}", path.display(), e);
+            return;
+        }
+    };
+
+    if let Err(e) = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)) {
+        warn!("Unable to restrict control socket permissions: {:?}", e);
+    }
+
+    debug!("Listening for control commands on {}.", path.display());
+    loop {
+        let stream = match listener.accept().await {
+            Ok((s, _)) => s,
+            Err(e) => {
+                warn!("Issue accepting control connection: {:?}", e);
+                continue;
+            }
+        };
+
+        let handle = handle.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, handle).await {
+                warn!("Issue handling control connection: {:?}", e);
+            }
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn handle_connection(stream: UnixStream, handle: Arc<ControlHandle>) -> Result<(), std::io::Error> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let response = handle.execute(line.trim());
+        let mut body = serde_json::to_string(&response).unwrap();
+        body.push('\n');
+
+        writer.write_all(body.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+/// Sends a single command to a running daemon and returns its response.
+#[cfg(unix)]
+pub async fn send_command(path: &Path, command: &str) -> Result<ControlResponse, std::io::Error> {
+    let stream = UnixStream::connect(path).await?;
+    let (reader, mut writer) = stream.into_split();
+
+    writer.write_all(format!("{}\n", command).as_bytes()).await?;
+    writer.shutdown().await?;
+
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await?;
+
+    serde_json::from_str(line.as_str())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(not(unix))]
+pub async fn send_command(_: &Path, _: &str) -> Result<ControlResponse, std::io::Error> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "the control socket is unsupported on this platform"))
+}
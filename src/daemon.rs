@@ -0,0 +1,57 @@
+use std::fs;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// Detaches from the terminal by forking twice and starting a new session, with the standard
+/// streams redirected to `/dev/null`. The working directory is kept so that relative paths in
+/// the configuration still resolve.
+///
+/// Must be called before any thread is started, including the async runtime.
+#[cfg(unix)]
+pub fn daemonize() -> io::Result<()> {
+    fork_and_exit_parent()?;
+
+    if unsafe { libc::setsid() } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    fork_and_exit_parent()?;
+
+    let null = fs::File::options().read(true).write(true).open("/dev/null")?;
+    for fd in 0..3 {
+        if unsafe { libc::dup2(null.as_raw_fd(), fd) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn fork_and_exit_parent() -> io::Result<()> {
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(()),
+        _ => std::process::exit(0),
+    }
+}
+
+/// File holding the PID of the running updater, removed when dropped.
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn create(path: &Path) -> io::Result<PidFile> {
+        fs::write(path, format!("{}\n", std::process::id()))?;
+        Ok(PidFile { path: path.to_path_buf() })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
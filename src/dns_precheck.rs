@@ -0,0 +1,62 @@
+use log::debug;
+use reqwest::Client;
+use serde::Deserialize;
+
+/// Resolves records through a DNS over HTTPS resolver, using the JSON API offered by
+/// Cloudflare and Google, to find out whether a record already points to an address without
+/// asking the Cloudflare API.
+pub struct DnsPrecheck {
+    client: Client,
+    url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DohResponse {
+    status: u16,
+    #[serde(default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+impl DnsPrecheck {
+    pub fn new(client: Client, url: &str) -> DnsPrecheck {
+        DnsPrecheck { client, url: url.to_string() }
+    }
+
+    /// Returns the addresses the name currently resolves to for the provided record type.
+    pub async fn resolve(&self, name: &str, dns_type: &str) -> Result<Vec<String>, String> {
+        let type_code = match dns_type {
+            "A" => 1,
+            "AAAA" => 28,
+            _ => return Err(format!("{} records cannot be resolved", dns_type)),
+        };
+
+        let response: DohResponse = self.client.get(self.url.as_str())
+            .query(&[("name", name), ("type", dns_type)])
+            .header("Accept", "application/dns-json")
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // NXDOMAIN and the like leave the answer empty, which simply means no match.
+        if response.status != 0 {
+            debug!("The resolver answered {} with status {}.", name, response.status);
+        }
+
+        Ok(response.answer.into_iter()
+            .filter(|a| a.record_type == type_code)
+            .map(|a| a.data)
+            .collect())
+    }
+}
@@ -0,0 +1,63 @@
+use crate::cloudflare_api::{CloudflareBatchRequest, CloudflareBatchResult, CloudflareDnsRecord, CloudflareDnsResult, CloudflareResponse};
+use crate::cloudflare_client::CloudflareClient;
+use crate::exec_provider::ExecProvider;
+use crate::failure::FailureKind;
+
+/// Where the records are kept, Cloudflare or a provider plugin.
+#[derive(Clone)]
+pub enum DnsProvider {
+    Cloudflare(CloudflareClient),
+    Exec(ExecProvider),
+}
+
+impl DnsProvider {
+    pub async fn get_dns_record(&self, zone_id: &str, dns_record_id: &str) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        match self {
+            DnsProvider::Cloudflare(c) => c.get_dns_record(zone_id, dns_record_id).await,
+            DnsProvider::Exec(e) => e.get_dns_record(zone_id, dns_record_id).await,
+        }
+    }
+
+    pub async fn list_dns_records(&self, zone_id: &str) -> Result<CloudflareResponse<Vec<CloudflareDnsResult>>, FailureKind> {
+        match self {
+            DnsProvider::Cloudflare(c) => c.list_dns_records(zone_id).await,
+            DnsProvider::Exec(e) => e.list_dns_records(zone_id).await,
+        }
+    }
+
+    pub async fn update_dns_record(&self, zone_id: &str, dns_record_id: &str, dns_record: &CloudflareDnsRecord) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        match self {
+            DnsProvider::Cloudflare(c) => c.update_dns_record(zone_id, dns_record_id, dns_record).await,
+            DnsProvider::Exec(e) => e.update_dns_record(zone_id, dns_record_id, dns_record).await,
+        }
+    }
+
+    pub async fn create_dns_record(&self, zone_id: &str, dns_record: &CloudflareDnsRecord) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        match self {
+            DnsProvider::Cloudflare(c) => c.create_dns_record(zone_id, dns_record).await,
+            DnsProvider::Exec(e) => e.create_dns_record(zone_id, dns_record).await,
+        }
+    }
+
+    /// Updates the records in a single request with Cloudflare. Plugins update them one at a
+    /// time, stopping at the first failure.
+    pub async fn batch_update_dns_records(&self, zone_id: &str, batch: &CloudflareBatchRequest) -> Result<CloudflareResponse<CloudflareBatchResult>, FailureKind> {
+        let plugin = match self {
+            DnsProvider::Cloudflare(c) => return c.batch_update_dns_records(zone_id, batch).await,
+            DnsProvider::Exec(e) => e,
+        };
+
+        let mut puts = Vec::new();
+        for put in &batch.puts {
+            puts.push(plugin.update_dns_record(zone_id, put.id.as_str(), &put.record).await?.result);
+        }
+
+        Ok(CloudflareResponse {
+            result: CloudflareBatchResult { puts },
+            success: true,
+            errors: Vec::new(),
+            messages: Vec::new(),
+            result_info: None,
+        })
+    }
+}
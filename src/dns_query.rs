@@ -0,0 +1,187 @@
+use reqwest::Url;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+const TYPE_A: u16 = 1;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const CLASS_CH: u16 = 3;
+const DNS_PORT: u16 = 53;
+const MAX_RESPONSE_SIZE: usize = 512;
+
+/// Question sent over UDP to a resolver answering with the address the question came from,
+/// such as `dns://208.67.222.222/myip.opendns.com`. The record type defaults to A or AAAA
+/// depending on the family, and can be set along with the class, as in
+/// `dns://1.1.1.1/whoami.cloudflare?type=TXT&class=CH`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuery {
+    host: String,
+    port: u16,
+    name: String,
+    record_type: u16,
+    class: u16,
+}
+
+impl DnsQuery {
+    pub fn parse(url: &str, ipv6: bool) -> Result<DnsQuery, String> {
+        let url = Url::parse(url).map_err(|e| e.to_string())?;
+        let host = url.host_str().ok_or_else(|| "no resolver is set".to_string())?;
+        let name = url.path().trim_matches('/');
+
+        if name.is_empty() {
+            return Err("no name to query is set".to_string());
+        }
+
+        let mut record_type = if ipv6 { TYPE_AAAA } else { TYPE_A };
+        let mut class = CLASS_IN;
+
+        for (key, value) in url.query_pairs() {
+            match (key.as_ref(), value.to_uppercase().as_str()) {
+                ("type", "A") => record_type = TYPE_A,
+                ("type", "AAAA") => record_type = TYPE_AAAA,
+                ("type", "TXT") => record_type = TYPE_TXT,
+                ("class", "IN") => class = CLASS_IN,
+                ("class", "CH") => class = CLASS_CH,
+                (key, value) => return Err(format!("unsupported query parameter {}={}", key, value)),
+            }
+        }
+
+        Ok(DnsQuery {
+            host: host.trim_matches(|c| c == '[' || c == ']').to_string(),
+            port: url.port().unwrap_or(DNS_PORT),
+            name: name.to_string(),
+            record_type,
+            class,
+        })
+    }
+
+    /// Sends the question from an address of the provided family, returning the addresses or
+    /// texts of the answer.
+    pub async fn send(&self, ipv6: bool, timeout: Duration) -> Result<Vec<String>, String> {
+        let server = tokio::net::lookup_host((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| e.to_string())?
+            .find(|a| a.is_ipv6() == ipv6)
+            .ok_or_else(|| format!("{} has no IPv{} address", self.host, if ipv6 { 6 } else { 4 }))?;
+
+        let local: SocketAddr = match ipv6 {
+            true => (Ipv6Addr::UNSPECIFIED, 0).into(),
+            false => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        };
+
+        let socket = UdpSocket::bind(local).await.map_err(|e| e.to_string())?;
+        socket.connect(server).await.map_err(|e| e.to_string())?;
+
+        let id = fastrand::u16(..);
+        socket.send(self.encode(id)?.as_slice()).await.map_err(|e| e.to_string())?;
+
+        let mut response = [0; MAX_RESPONSE_SIZE];
+        let length = tokio::time::timeout(timeout, socket.recv(&mut response))
+            .await
+            .map_err(|_| format!("{} did not answer within {}s", server, timeout.as_secs()))?
+            .map_err(|e| e.to_string())?;
+
+        self.decode(&response[..length], id)
+    }
+
+    fn encode(&self, id: u16) -> Result<Vec<u8>, String> {
+        // Header asking for recursion, with a single question.
+        let mut packet = Vec::with_capacity(MAX_RESPONSE_SIZE);
+        packet.extend_from_slice(&id.to_be_bytes());
+        packet.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+        for label in self.name.split('.') {
+            if label.is_empty() || label.len() > 63 {
+                return Err(format!("{} is not a valid name", self.name));
+            }
+
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+
+        packet.push(0);
+        packet.extend_from_slice(&self.record_type.to_be_bytes());
+        packet.extend_from_slice(&self.class.to_be_bytes());
+        Ok(packet)
+    }
+
+    /// Returns the answers of the queried type found in the response to the question.
+    fn decode(&self, response: &[u8], id: u16) -> Result<Vec<String>, String> {
+        let malformed = || "the response is malformed".to_string();
+
+        if response.len() < 12 {
+            return Err(malformed());
+        }
+
+        let read_u16 = |at: usize| response.get(at..at + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(malformed);
+
+        if read_u16(0)? != id || response[2] & 0x80 == 0 {
+            return Err("the response does not answer the question".to_string());
+        }
+
+        match response[3] & 0x0f {
+            0 => {},
+            code => return Err(format!("the resolver answered with error code {}", code)),
+        }
+
+        let questions = read_u16(4)?;
+        let answers = read_u16(6)?;
+        let mut at = 12;
+
+        for _ in 0..questions {
+            at = skip_name(response, at).ok_or_else(malformed)? + 4;
+        }
+
+        let mut results = Vec::new();
+
+        for _ in 0..answers {
+            at = skip_name(response, at).ok_or_else(malformed)?;
+            let record_type = read_u16(at)?;
+            let length = read_u16(at + 8)? as usize;
+            let data = response.get(at + 10..at + 10 + length).ok_or_else(malformed)?;
+            at += 10 + length;
+
+            if record_type != self.record_type {
+                continue;
+            }
+
+            results.push(match (record_type, data.len()) {
+                (TYPE_A, 4) => IpAddr::from(<[u8; 4]>::try_from(data).unwrap()).to_string(),
+                (TYPE_AAAA, 16) => IpAddr::from(<[u8; 16]>::try_from(data).unwrap()).to_string(),
+                (TYPE_TXT, _) => decode_txt(data).ok_or_else(malformed)?,
+                _ => return Err(malformed()),
+            });
+        }
+
+        Ok(results)
+    }
+}
+
+/// Returns the position following the name starting at the provided position.
+fn skip_name(packet: &[u8], mut at: usize) -> Option<usize> {
+    loop {
+        match *packet.get(at)? {
+            0 => return Some(at + 1),
+            // Pointer to a name appearing earlier in the packet.
+            length if length & 0xc0 == 0xc0 => return Some(at + 2),
+            length => at += 1 + length as usize,
+        }
+    }
+}
+
+/// Joins the character strings of a TXT record.
+fn decode_txt(mut data: &[u8]) -> Option<String> {
+    let mut text = String::new();
+
+    while let Some((&length, rest)) = data.split_first() {
+        let part = rest.get(..length as usize)?;
+        text.push_str(String::from_utf8_lossy(part).as_ref());
+        data = &rest[length as usize..];
+    }
+
+    Some(text)
+}
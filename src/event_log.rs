@@ -0,0 +1,54 @@
+use log::Level;
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::EventLog::{DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE, EVENTLOG_WARNING_TYPE};
+
+/// Source registered with the Windows Event Log, which warnings and errors are reported to.
+pub struct EventLog {
+    handle: HANDLE,
+}
+
+// The handle is only passed to the Event Log functions, which may be called from any thread.
+unsafe impl Send for EventLog {}
+unsafe impl Sync for EventLog {}
+
+impl EventLog {
+    /// Registers the source, returning `None` if the Event Log is unavailable.
+    pub fn register(source: &str) -> Option<EventLog> {
+        let source = wide(source);
+        let handle = unsafe { RegisterEventSourceW(std::ptr::null(), source.as_ptr()) };
+
+        (!handle.is_null()).then_some(EventLog { handle })
+    }
+
+    /// Reports the message as an event of the level, ignoring levels below warnings. Returns
+    /// whether an event was written.
+    pub fn report(&self, level: Level, message: &str) -> bool {
+        let event_type = match level {
+            Level::Error => EVENTLOG_ERROR_TYPE,
+            Level::Warn => EVENTLOG_WARNING_TYPE,
+            _ => return false,
+        };
+
+        let message = wide(message);
+        let strings = [message.as_ptr()];
+
+        unsafe {
+            ReportEventW(self.handle, event_type, 0, 0, std::ptr::null_mut(), 1, 0, strings.as_ptr(), std::ptr::null()) != 0
+        }
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        unsafe {
+            DeregisterEventSource(self.handle);
+        }
+    }
+}
+
+/// Encodes the string as the null terminated UTF-16 expected by the Windows API.
+fn wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().chain(std::iter::once(0)).collect()
+}
@@ -0,0 +1,68 @@
+use serde::Serialize;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Occurrence in the lifecycle of the updater, written as a JSON line for wrapper scripts and
+/// supervisors.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LifecycleEvent {
+    CheckStarted {
+        records: usize,
+    },
+    IpDetected {
+        ipv4: Option<String>,
+        ipv6: Option<String>,
+    },
+    UpdateApplied {
+        name: String,
+        #[serde(rename = "type")]
+        record_type: String,
+        previous: Option<String>,
+        current: String,
+    },
+    Error {
+        operation: &'static str,
+        kind: &'static str,
+        message: String,
+        consecutive_failures: u64,
+    },
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    event: &'a LifecycleEvent,
+}
+
+/// Writes lifecycle events as one JSON object per line, shared with every clone.
+#[derive(Clone)]
+pub struct EventStream {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl EventStream {
+    pub fn new(writer: impl Write + Send + 'static) -> EventStream {
+        EventStream { writer: Arc::new(Mutex::new(Box::new(writer))) }
+    }
+
+    pub fn stdout() -> EventStream {
+        EventStream::new(std::io::stdout())
+    }
+
+    /// Writes the event, flushing it right away so that readers see it as it happens.
+    pub fn emit(&self, now: OffsetDateTime, event: &LifecycleEvent) {
+        let line = Line {
+            timestamp: now.format(&Rfc3339).unwrap_or_default(),
+            event,
+        };
+
+        let mut writer = self.writer.lock().unwrap();
+        let _ = serde_json::to_writer(&mut *writer, &line);
+        let _ = writer.write_all(b"\n");
+        let _ = writer.flush();
+    }
+}
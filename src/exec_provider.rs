@@ -0,0 +1,142 @@
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::cloudflare_api::{CloudflareDnsRecord, CloudflareDnsResult, CloudflareResponse};
+use crate::constants::*;
+use crate::failure::FailureKind;
+
+/// Request written to the plugin.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum PluginRequest<'a> {
+    Get { zone_id: &'a str, record_id: &'a str },
+    Update { zone_id: &'a str, record_id: &'a str, record: &'a CloudflareDnsRecord },
+    List { zone_id: &'a str },
+    Create { zone_id: &'a str, record: &'a CloudflareDnsRecord },
+}
+
+/// Response printed by the plugin.
+#[derive(Deserialize)]
+struct PluginResponse<T> {
+    success: bool,
+    result: Option<T>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// DNS provider implemented by a plugin command, run through the shell once per request.
+///
+/// The command reads a JSON object from stdin whose `action` is `get` or `update`, or `list`
+/// and `create` for the records selected by name, along with the `zone_id`, `record_id` and
+/// new `record` as they apply. It prints `{"success": true, "result": ...}` on stdout, or
+/// `{"success": false, "error": "..."}`. Records have the `id`, `name`, `type`, `content`,
+/// `ttl` and `proxied` fields of Cloudflare records.
+#[derive(Clone)]
+pub struct ExecProvider {
+    command: String,
+    timeout: Duration,
+}
+
+impl ExecProvider {
+    pub fn new(command: &str) -> ExecProvider {
+        ExecProvider {
+            command: command.to_string(),
+            timeout: Duration::from_secs(EXEC_PROVIDER_TIMEOUT),
+        }
+    }
+
+    pub async fn get_dns_record(&self, zone_id: &str, record_id: &str) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        self.call(&PluginRequest::Get { zone_id, record_id }).await
+    }
+
+    pub async fn update_dns_record(&self, zone_id: &str, record_id: &str, record: &CloudflareDnsRecord) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        self.call(&PluginRequest::Update { zone_id, record_id, record }).await
+    }
+
+    pub async fn list_dns_records(&self, zone_id: &str) -> Result<CloudflareResponse<Vec<CloudflareDnsResult>>, FailureKind> {
+        self.call(&PluginRequest::List { zone_id }).await
+    }
+
+    pub async fn create_dns_record(&self, zone_id: &str, record: &CloudflareDnsRecord) -> Result<CloudflareResponse<CloudflareDnsResult>, FailureKind> {
+        self.call(&PluginRequest::Create { zone_id, record }).await
+    }
+
+    /// Runs the plugin with the request, classifying its failure. Failing to run it counts as
+    /// a network failure, a failed or unreadable response as an API failure.
+    async fn call<T: DeserializeOwned>(&self, request: &PluginRequest<'_>) -> Result<CloudflareResponse<T>, FailureKind> {
+        let input = serde_json::to_vec(request).unwrap();
+        let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+
+        let child = Command::new(shell)
+            .args([flag, self.command.as_str()])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+
+        let mut child = match child {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Unable to run the DNS provider plugin `{}`: {}", self.command, e);
+                return Err(FailureKind::Network);
+            }
+        };
+
+        let mut stdin = child.stdin.take().unwrap();
+        let run = async move {
+            stdin.write_all(&input).await?;
+            drop(stdin);
+            child.wait_with_output().await
+        };
+
+        let output = match tokio::time::timeout(self.timeout, run).await {
+            Ok(Ok(o)) => o,
+            Ok(Err(e)) => {
+                warn!("Unable to run the DNS provider plugin `{}`: {}", self.command, e);
+                return Err(FailureKind::Network);
+            },
+            Err(_) => {
+                warn!("The DNS provider plugin `{}` did not answer within {}s.", self.command, self.timeout.as_secs());
+                return Err(FailureKind::Network);
+            }
+        };
+
+        if !output.status.success() {
+            warn!("The DNS provider plugin `{}` failed with {}: {}",
+                self.command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim());
+            return Err(FailureKind::Api);
+        }
+
+        let response: PluginResponse<T> = match serde_json::from_slice(&output.stdout) {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Unable to read the response of the DNS provider plugin `{}`: {}", self.command, e);
+                return Err(FailureKind::Api);
+            }
+        };
+
+        match response.result {
+            Some(result) if response.success => Ok(CloudflareResponse {
+                result,
+                success: true,
+                errors: Vec::new(),
+                messages: Vec::new(),
+                result_info: None,
+            }),
+            _ => {
+                warn!("The DNS provider plugin `{}` reported an error: {}",
+                    self.command,
+                    response.error.as_deref().unwrap_or("no details given"));
+                Err(FailureKind::Api)
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use log::{error, info, warn};
+use reqwest::StatusCode;
+use std::collections::{HashMap, HashSet};
+
+use crate::cloudflare_api::CloudflareError;
+use crate::constants::*;
+
+/// Categories of failures encountered while talking to remote services.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureKind {
+    /// The request could not be sent or its response could not be read.
+    Network,
+    /// Cloudflare rejected the API token.
+    Auth,
+    /// The remote service asked us to slow down.
+    RateLimit,
+    /// Cloudflare answered, but the request was not successful.
+    Api,
+    /// The detected address does not fit the type of the record.
+    Mismatch,
+}
+
+impl FailureKind {
+    /// Classifies an unsuccessful Cloudflare response from its status and errors.
+    pub fn from_cloudflare(status: StatusCode, errors: &[CloudflareError]) -> FailureKind {
+        if status == StatusCode::UNAUTHORIZED
+            || status == StatusCode::FORBIDDEN
+            || errors.iter().any(|e| CLOUDFLARE_AUTH_ERROR_CODES.contains(&e.code)) {
+            FailureKind::Auth
+        } else {
+            FailureKind::Api
+        }
+    }
+
+    /// Returns the name of the category, as used in metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureKind::Network => "network",
+            FailureKind::Auth => "auth",
+            FailureKind::RateLimit => "rate_limit",
+            FailureKind::Api => "api",
+            FailureKind::Mismatch => "mismatch",
+        }
+    }
+
+    /// Returns the process exit code used when giving up because of this failure.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FailureKind::Network => EXIT_NETWORK_ERROR,
+            FailureKind::Auth => EXIT_AUTH_ERROR,
+            FailureKind::RateLimit => EXIT_NETWORK_ERROR,
+            FailureKind::Api => EXIT_NETWORK_ERROR,
+            FailureKind::Mismatch => EXIT_CONFIG_ERROR,
+        }
+    }
+}
+
+/// Part of a check whose failures are counted separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    DetectIp,
+    FetchRecords,
+    UpdateRecords,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 3] = [Operation::DetectIp, Operation::FetchRecords, Operation::UpdateRecords];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::DetectIp => "detect_ip",
+            Operation::FetchRecords => "fetch_records",
+            Operation::UpdateRecords => "update_records",
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Operation::DetectIp => "get the current public IP",
+            Operation::FetchRecords => "get the current Cloudflare DNS records",
+            Operation::UpdateRecords => "update the Cloudflare DNS records",
+        }
+    }
+}
+
+/// Consecutive failures of each operation. Failures are logged as warnings until an operation
+/// has failed `threshold` times in a row, then as errors until it succeeds again.
+///
+/// The threshold can be tuned for a kind of failure or an operation, the kind taking
+/// precedence, with 0 never escalating.
+pub struct FailureStreaks {
+    threshold: u64,
+    kind_thresholds: HashMap<FailureKind, u64>,
+    operation_thresholds: HashMap<Operation, u64>,
+    streaks: HashMap<Operation, u64>,
+    escalated: HashSet<Operation>,
+}
+
+impl FailureStreaks {
+    pub fn new(threshold: u64) -> FailureStreaks {
+        FailureStreaks {
+            threshold,
+            kind_thresholds: HashMap::new(),
+            operation_thresholds: HashMap::new(),
+            streaks: HashMap::new(),
+            escalated: HashSet::new(),
+        }
+    }
+
+    /// Escalates failures of the provided kind after `threshold` of them in a row.
+    pub fn with_kind_threshold(mut self, kind: FailureKind, threshold: u64) -> FailureStreaks {
+        self.kind_thresholds.insert(kind, threshold);
+        self
+    }
+
+    /// Escalates failures of the provided operation after `threshold` of them in a row.
+    pub fn with_operation_threshold(mut self, operation: Operation, threshold: u64) -> FailureStreaks {
+        self.operation_thresholds.insert(operation, threshold);
+        self
+    }
+
+    fn threshold(&self, operation: Operation, kind: FailureKind) -> u64 {
+        self.kind_thresholds.get(&kind)
+            .or(self.operation_thresholds.get(&operation))
+            .copied()
+            .unwrap_or(self.threshold)
+    }
+
+    /// Counts the failure and logs it, returning whether the operation just escalated.
+    pub fn record_failure(&mut self, operation: Operation, kind: FailureKind) -> bool {
+        let threshold = self.threshold(operation, kind);
+        let streak = self.streaks.entry(operation).or_insert(0);
+        *streak += 1;
+
+        if threshold == 0 || *streak < threshold {
+            warn!("Unable to {} ({} error).", operation.describe(), kind.as_str());
+            return false;
+        }
+
+        error!("Unable to {} {} times in a row ({} error).", operation.describe(), streak, kind.as_str());
+        self.escalated.insert(operation)
+    }
+
+    pub fn record_success(&mut self, operation: Operation) {
+        let streak = self.streaks.remove(&operation);
+
+        if let (true, Some(streak)) = (self.escalated.remove(&operation), streak) {
+            info!("Able to {} again after {} failures.", operation.describe(), streak);
+        }
+    }
+
+    /// Returns how many times in a row the operation failed.
+    pub fn get(&self, operation: Operation) -> u64 {
+        self.streaks.get(&operation).copied().unwrap_or(0)
+    }
+
+    /// Returns whether the failures of the operation were escalated.
+    pub fn is_escalated(&self, operation: Operation) -> bool {
+        self.escalated.contains(&operation)
+    }
+
+    /// Continues a streak counted before a restart, without logging or escalating it again.
+    pub fn restore(&mut self, operation: Operation, failures: u64, escalated: bool) {
+        if failures == 0 {
+            return;
+        }
+
+        self.streaks.insert(operation, failures);
+
+        if escalated {
+            self.escalated.insert(operation);
+        }
+    }
+}
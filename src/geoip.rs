@@ -0,0 +1,132 @@
+use log::{debug, warn};
+use maxminddb::{path, Reader};
+use reqwest::Client;
+use serde::Deserialize;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::config::ConfigError;
+
+/// Where an address is located, and the network it belongs to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IpInfo {
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<u32>,
+    pub organization: Option<String>,
+}
+
+impl IpInfo {
+    /// Returns the network as `AS3320 Deutsche Telekom AG`, if known.
+    pub fn network(&self) -> Option<String> {
+        match (self.asn, self.organization.as_deref()) {
+            (Some(asn), Some(organization)) => Some(format!("AS{} {}", asn, organization)),
+            (Some(asn), None) => Some(format!("AS{}", asn)),
+            (None, organization) => organization.map(str::to_string),
+        }
+    }
+}
+
+impl fmt::Display for IpInfo {
+    /// Formats as `Berlin, DE, AS3320 Deutsche Telekom AG`, leaving out what is unknown.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = [self.city.clone(), self.country.clone(), self.network()].into_iter().flatten().collect();
+        f.write_str(parts.join(", ").as_str())
+    }
+}
+
+/// Looks addresses up in a local MaxMind database or through an ipinfo.io style API.
+pub enum GeoIp {
+    Mmdb(Reader<Vec<u8>>),
+    /// URL with `{ip}` replaced by the address.
+    Api { client: Client, url: String },
+}
+
+/// Response of the lookup API, accepting both the ipinfo.io and ip-api.com field names.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiResponse {
+    country: Option<String>,
+    country_code: Option<String>,
+    city: Option<String>,
+    org: Option<String>,
+    #[serde(rename = "as")]
+    network: Option<String>,
+}
+
+impl GeoIp {
+    pub fn mmdb(path: &Path) -> Result<GeoIp, ConfigError> {
+        Reader::open_readfile(path)
+            .map(GeoIp::Mmdb)
+            .map_err(|e| ConfigError::Invalid { key: "geoip.mmdb_file", reason: e.to_string() })
+    }
+
+    pub fn api(client: Client, url: &str) -> GeoIp {
+        GeoIp::Api { client, url: url.to_string() }
+    }
+
+    /// Returns what is known about the address, or `None` if the lookup failed, in which case
+    /// the failure is logged.
+    pub async fn lookup(&self, ip: IpAddr) -> Option<IpInfo> {
+        let result = match self {
+            GeoIp::Mmdb(reader) => lookup_mmdb(reader, ip).map_err(|e| e.to_string()),
+            GeoIp::Api { client, url } => lookup_api(client, url.replace("{ip}", ip.to_string().as_str()))
+                .await
+                .map_err(|e| e.to_string()),
+        };
+
+        match result {
+            Ok(info) => {
+                debug!("GeoIP lookup of {}: {:?}", ip, info);
+                Some(info)
+            },
+            Err(e) => {
+                warn!("Unable to look up the location of {}: {}", ip, e);
+                None
+            }
+        }
+    }
+}
+
+fn lookup_mmdb(reader: &Reader<Vec<u8>>, ip: IpAddr) -> Result<IpInfo, maxminddb::MaxMindDbError> {
+    let result = reader.lookup(ip)?;
+
+    Ok(IpInfo {
+        country: result.decode_path(&path!["country", "iso_code"])?,
+        city: result.decode_path(&path!["city", "names", "en"])?,
+        asn: result.decode_path(&path!["autonomous_system_number"])?,
+        organization: result.decode_path(&path!["autonomous_system_organization"])?,
+    })
+}
+
+async fn lookup_api(client: &Client, url: String) -> Result<IpInfo, reqwest::Error> {
+    let response: ApiResponse = client.get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let (asn, organization) = match response.org.or(response.network) {
+        Some(network) => parse_network(network.as_str()),
+        None => (None, None),
+    };
+
+    Ok(IpInfo {
+        country: response.country_code.or(response.country),
+        city: response.city,
+        asn,
+        organization,
+    })
+}
+
+/// Splits `AS3320 Deutsche Telekom AG` into the AS number and organization.
+fn parse_network(network: &str) -> (Option<u32>, Option<String>) {
+    let (asn, organization) = network.split_once(' ').unwrap_or((network, ""));
+
+    match asn.strip_prefix("AS").and_then(|n| n.parse().ok()) {
+        Some(asn) => (Some(asn), Some(organization.trim().to_string()).filter(|o| !o.is_empty())),
+        None => (None, Some(network.to_string())),
+    }
+}
@@ -0,0 +1,139 @@
+use futures::stream::{self, Stream};
+use log::{error, info};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::net::TcpListener;
+use tonic::service::Interceptor;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::control::{ControlHandle, ControlResponse};
+use crate::http_api::constant_time_eq;
+use crate::state;
+
+/// Messages, server and generated client of the gRPC control API.
+pub mod proto {
+    tonic::include_proto!("cloudflare_ddns.control.v1");
+}
+
+use proto::control_server::{Control, ControlServer};
+use proto::{CheckNowRequest, CommandResponse, DaemonStatus, GetStatusRequest, IpChange, PauseRequest, Record, ResumeRequest, StreamHistoryRequest};
+
+/// Binds the gRPC control API to the provided address, logging the failure.
+pub async fn bind(address: SocketAddr) -> Option<TcpListener> {
+    match TcpListener::bind(address).await {
+        Ok(l) => Some(l),
+        Err(e) => {
+            error!("Unable to bind the gRPC control API to {}: {:?}", address, e);
+            None
+        }
+    }
+}
+
+/// Serves the gRPC control API on the bound listener, requiring the auth token as a bearer
+/// token in the `authorization` metadata.
+pub async fn serve(listener: TcpListener, auth_token: String, handle: Arc<ControlHandle>) {
+    if let Ok(address) = listener.local_addr() {
+        info!("gRPC control API listening on {}.", address);
+    }
+
+    let service = ControlServer::with_interceptor(ControlService { handle }, BearerAuth(format!("Bearer {}", auth_token)));
+    let result = Server::builder()
+        .add_service(service)
+        .serve_with_incoming(TcpIncoming::from(listener))
+        .await;
+
+    if let Err(e) = result {
+        error!("gRPC control API stopped: {:?}", e);
+    }
+}
+
+#[derive(Clone)]
+struct BearerAuth(String);
+
+impl Interceptor for BearerAuth {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        match request.metadata().get("authorization").and_then(|v| v.to_str().ok()) {
+            Some(v) if constant_time_eq(v.as_bytes(), self.0.as_bytes()) => Ok(request),
+            _ => Err(Status::unauthenticated("Missing or invalid auth token.")),
+        }
+    }
+}
+
+struct ControlService {
+    handle: Arc<ControlHandle>,
+}
+
+#[tonic::async_trait]
+impl Control for ControlService {
+    async fn get_status(&self, _: Request<GetStatusRequest>) -> Result<Response<DaemonStatus>, Status> {
+        let status = self.handle.status();
+        let state = status.state;
+
+        Ok(Response::new(DaemonStatus {
+            paused: status.paused,
+            public_ip: state.public_ip,
+            records: state.records.into_iter()
+                .map(|r| Record { id: r.id, name: r.name, content: r.content, proxied: r.proxied })
+                .collect(),
+            last_checked: state.last_checked.map(OffsetDateTime::unix_timestamp),
+            last_changed: state.last_changed.map(OffsetDateTime::unix_timestamp),
+            checks: state.stats.checks,
+            updates: state.stats.updates,
+        }))
+    }
+
+    async fn check_now(&self, _: Request<CheckNowRequest>) -> Result<Response<CommandResponse>, Status> {
+        Ok(Response::new(command_response(self.handle.execute("check-now"))))
+    }
+
+    async fn pause(&self, _: Request<PauseRequest>) -> Result<Response<CommandResponse>, Status> {
+        Ok(Response::new(command_response(self.handle.execute("pause"))))
+    }
+
+    async fn resume(&self, _: Request<ResumeRequest>) -> Result<Response<CommandResponse>, Status> {
+        Ok(Response::new(command_response(self.handle.execute("resume"))))
+    }
+
+    type StreamHistoryStream = Pin<Box<dyn Stream<Item = Result<IpChange, Status>> + Send>>;
+
+    async fn stream_history(&self, _: Request<StreamHistoryRequest>) -> Result<Response<Self::StreamHistoryStream>, Status> {
+        let handle = self.handle.clone();
+        let updates = handle.updates.subscribe();
+        let pending: VecDeque<state::IpChange> = handle.state.lock().unwrap().history.iter().cloned().collect();
+
+        // Changes newer than the last one sent are picked up whenever the state is published.
+        let changes = stream::unfold((handle, updates, pending, None), |(handle, mut updates, mut pending, mut last_sent)| async move {
+            loop {
+                if let Some(change) = pending.pop_front() {
+                    last_sent = Some(change.timestamp);
+                    let message = IpChange {
+                        timestamp: change.timestamp.unix_timestamp(),
+                        record: change.record,
+                        from: change.from,
+                        to: change.to,
+                    };
+
+                    return Some((Ok(message), (handle, updates, pending, last_sent)));
+                }
+
+                updates.changed().await.ok()?;
+                let history = handle.state.lock().unwrap().history.clone();
+                pending.extend(history.into_iter().filter(|c| last_sent.is_none_or(|t: OffsetDateTime| c.timestamp > t)));
+            }
+        });
+
+        Ok(Response::new(Box::pin(changes)))
+    }
+}
+
+fn command_response(response: ControlResponse) -> CommandResponse {
+    CommandResponse {
+        success: response.success,
+        message: response.message,
+    }
+}
@@ -0,0 +1,169 @@
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::server::conn::AddrIncoming;
+use hyper::server::Builder;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use log::{debug, error, info};
+use time::OffsetDateTime;
+
+use crate::constants::*;
+use crate::control::{ControlHandle, ControlResponse};
+use crate::metrics;
+use crate::public_ip;
+
+/// Binds the HTTP control API to the provided address, allowing it to be served once
+/// privileges have been dropped.
+pub fn bind(address: SocketAddr) -> Option<Builder<AddrIncoming>> {
+    match Server::try_bind(&address) {
+        Ok(b) => Some(b),
+        Err(e) => {
+            error!("Unable to bind HTTP API to {}: {:?}", address, e);
+            None
+        }
+    }
+}
+
+/// Serves the authenticated HTTP control API on the bound address. The public IP can also
+/// be pushed with the webhook secret, if one is set.
+pub async fn serve(builder: Builder<AddrIncoming>, auth_token: String, webhook_secret: String, handle: Arc<ControlHandle>) {
+    let secrets = Arc::new((auth_token, webhook_secret));
+
+    let make_service = make_service_fn(move |_| {
+        let handle = handle.clone();
+        let secrets = secrets.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let handle = handle.clone();
+                let secrets = secrets.clone();
+
+                async move {
+                    if request.method() == Method::POST && request.uri().path() == "/ip" {
+                        return Ok::<_, Infallible>(push_ip(request, secrets.as_ref(), &handle).await);
+                    }
+
+                    Ok::<_, Infallible>(route(request, secrets.0.as_str(), &handle))
+                }
+            }))
+        }
+    });
+
+    let server = builder.serve(make_service);
+
+    info!("HTTP API listening on {}.", server.local_addr());
+    if let Err(e) = server.await {
+        error!("HTTP API stopped: {:?}", e);
+    }
+}
+
+/// Dispatches a single request to the matching control command.
+fn route(request: Request<Body>, auth_token: &str, handle: &ControlHandle) -> Response<Body> {
+    debug!("HTTP API request: {} {}", request.method(), request.uri().path());
+
+    if !is_authorized(&request, auth_token) {
+        return json_response(StatusCode::UNAUTHORIZED, &ControlResponse {
+            success: false,
+            message: "Missing or invalid bearer token.".to_string(),
+            status: None,
+        });
+    }
+
+    let command = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/status") => "status",
+        (&Method::GET, "/metrics") => {
+            let status = handle.status();
+            let body = metrics::render(&status.state, &handle.requests, status.paused, OffsetDateTime::now_utc());
+
+            return Response::builder()
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+                .body(Body::from(body))
+                .unwrap();
+        },
+        (&Method::GET, "/history") => {
+            let history = handle.state.lock().unwrap().history.clone();
+            return json_response(StatusCode::OK, &history);
+        },
+        (&Method::POST, "/check") => "check-now",
+        (&Method::POST, "/pause") => "pause",
+        (&Method::POST, "/resume") => "resume",
+        (&Method::POST, "/reload") => "reload",
+        _ => {
+            return json_response(StatusCode::NOT_FOUND, &ControlResponse {
+                success: false,
+                message: "Not found.".to_string(),
+                status: None,
+            });
+        }
+    };
+
+    json_response(StatusCode::OK, &handle.execute(command))
+}
+
+/// Reads the public IP from the body of the request, authorized by the auth token or the
+/// webhook secret, as a bearer token or a `secret` query parameter.
+async fn push_ip(request: Request<Body>, (auth_token, webhook_secret): &(String, String), handle: &ControlHandle) -> Response<Body> {
+    debug!("HTTP API request: {} {}", request.method(), request.uri().path());
+
+    let query_secret = request.uri().query()
+        .and_then(|q| q.split('&').find_map(|p| p.strip_prefix("secret=")))
+        .map(str::to_string);
+
+    let authorized = is_authorized(&request, auth_token)
+        || (webhook_secret != DEFAULT_NOT_SET && (is_authorized(&request, webhook_secret)
+            || query_secret.is_some_and(|s| constant_time_eq(s.as_bytes(), webhook_secret.as_bytes()))));
+
+    if !authorized {
+        return json_response(StatusCode::UNAUTHORIZED, &ControlResponse {
+            success: false,
+            message: "Missing or invalid secret.".to_string(),
+            status: None,
+        });
+    }
+
+    let ip = hyper::body::to_bytes(request.into_body()).await.ok()
+        .and_then(|b| String::from_utf8(b.to_vec()).ok())
+        .and_then(|b| b.trim().parse::<IpAddr>().ok());
+
+    match ip {
+        Some(ip) if public_ip::is_public(&ip) => json_response(StatusCode::OK, &handle.push_ip(ip)),
+        _ => json_response(StatusCode::BAD_REQUEST, &ControlResponse {
+            success: false,
+            message: "The body is not a public IP address.".to_string(),
+            status: None,
+        }),
+    }
+}
+
+/// Checks the bearer token of the request.
+fn is_authorized(request: &Request<Body>, auth_token: &str) -> bool {
+    let provided = request.headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match provided {
+        Some(p) => constant_time_eq(p.as_bytes(), auth_token.as_bytes()),
+        None => false,
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_string(body).unwrap()))
+        .unwrap()
+}
@@ -0,0 +1,163 @@
+use log::{debug, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, ClientBuilder, Proxy};
+use std::fs;
+use std::net::{AddrParseError, IpAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::{Config, ConfigError};
+use crate::constants::*;
+
+/// Builds the HTTP client used for Cloudflare requests.
+pub fn build(config: &Config) -> Result<Client, ConfigError> {
+    finish(builder(config)?)
+}
+
+/// Builds the HTTP client used for IP check requests, sending the configured headers.
+pub fn build_for_ip_check(config: &Config) -> Result<Client, ConfigError> {
+    let ip_check_config = config.ip_check.as_ref().unwrap();
+    let invalid = |reason: String| ConfigError::Invalid { key: "ip_check.headers", reason };
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in ip_check_config.headers.as_ref().unwrap() {
+        let name = HeaderName::try_from(name.as_str()).map_err(|e| invalid(e.to_string()))?;
+        let value = HeaderValue::try_from(value.as_str()).map_err(|e| invalid(e.to_string()))?;
+        headers.insert(name, value);
+    }
+
+    let mut builder = builder(config)?.default_headers(headers);
+
+    let user_agent = ip_check_config.user_agent.as_ref().unwrap();
+    if user_agent != DEFAULT_NOT_SET {
+        builder = builder.user_agent(user_agent.as_str());
+    }
+
+    finish(builder)
+}
+
+/// Applies the network and TLS settings shared by every client.
+fn builder(config: &Config) -> Result<ClientBuilder, ConfigError> {
+    let general_config = config.general.as_ref().unwrap();
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(general_config.connect_timeout.unwrap()))
+        .timeout(Duration::from_secs(general_config.request_timeout.unwrap()));
+
+    if let Some(proxy) = get_proxy_url(general_config.proxy.as_ref().unwrap()) {
+        debug!("Sending requests through proxy {}.", proxy);
+        let proxy = Proxy::all(proxy.as_str())
+            .map_err(|e| ConfigError::Invalid { key: "general.proxy", reason: e.to_string() })?;
+
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(address) = get_local_address(general_config.bind_address.as_ref().unwrap(),
+        general_config.interface.as_ref().unwrap())? {
+        debug!("Sending requests from {}.", address);
+        builder = builder.local_address(address);
+    }
+
+    let ca_file = general_config.ca_file.as_ref().unwrap();
+    if ca_file != DEFAULT_NOT_SET {
+        for certificate in load_certificates(Path::new(ca_file))? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if general_config.tls_accept_invalid_certs.unwrap() {
+        warn!("TLS certificate verification is disabled.");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    if general_config.tls_accept_invalid_hostnames.unwrap() {
+        builder = accept_invalid_hostnames(builder)?;
+    }
+
+    Ok(builder)
+}
+
+fn finish(builder: ClientBuilder) -> Result<Client, ConfigError> {
+    builder.build()
+        .map_err(|e| ConfigError::Invalid { key: "general", reason: e.to_string() })
+}
+
+/// Returns the source address requests are bound to, either the configured address or the
+/// first address of the configured interface, preferring IPv4.
+fn get_local_address(bind_address: &str, interface: &str) -> Result<Option<IpAddr>, ConfigError> {
+    match (bind_address != DEFAULT_NOT_SET, interface != DEFAULT_NOT_SET) {
+        (false, false) => Ok(None),
+        (true, true) => Err(ConfigError::Invalid {
+            key: "general.interface",
+            reason: "cannot be set together with `general.bind_address`".to_string(),
+        }),
+        (true, false) => bind_address.parse()
+            .map(Some)
+            .map_err(|e: AddrParseError| ConfigError::Invalid { key: "general.bind_address", reason: e.to_string() }),
+        (false, true) => {
+            let invalid = |reason: String| ConfigError::Invalid { key: "general.interface", reason };
+            let addresses: Vec<IpAddr> = if_addrs::get_if_addrs()
+                .map_err(|e| invalid(e.to_string()))?
+                .into_iter()
+                .filter(|i| i.name == interface)
+                .map(|i| i.ip())
+                .collect();
+
+            addresses.iter().find(|a| a.is_ipv4())
+                .or_else(|| addresses.first())
+                .copied()
+                .map(Some)
+                .ok_or_else(|| invalid(format!("no address found for interface {}", interface)))
+        },
+    }
+}
+
+#[cfg(feature = "native-tls")]
+fn accept_invalid_hostnames(builder: ClientBuilder) -> Result<ClientBuilder, ConfigError> {
+    warn!("TLS hostname verification is disabled.");
+    Ok(builder.danger_accept_invalid_hostnames(true))
+}
+
+#[cfg(not(feature = "native-tls"))]
+fn accept_invalid_hostnames(_builder: ClientBuilder) -> Result<ClientBuilder, ConfigError> {
+    Err(ConfigError::Invalid {
+        key: "general.tls_accept_invalid_hostnames",
+        reason: "only supported when built with the native-tls feature".to_string(),
+    })
+}
+
+/// Reads every certificate from the provided PEM bundle.
+fn load_certificates(path: &Path) -> Result<Vec<Certificate>, ConfigError> {
+    let invalid = |reason: String| ConfigError::Invalid { key: "general.ca_file", reason };
+    let contents = fs::read_to_string(path)
+        .map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })?;
+
+    let certificates = contents.split_inclusive(PEM_CERTIFICATE_END)
+        .filter(|pem| pem.contains(PEM_CERTIFICATE_END))
+        .map(|pem| Certificate::from_pem(pem.as_bytes()).map_err(|e| invalid(e.to_string())))
+        .collect::<Result<Vec<Certificate>, ConfigError>>()?;
+
+    if certificates.is_empty() {
+        return Err(invalid(format!("no certificates found in {}", path.display())));
+    }
+
+    debug!("Loaded {} root certificates from {}.", certificates.len(), path.display());
+    Ok(certificates)
+}
+
+/// Returns the configured proxy, falling back to `ALL_PROXY` when no scheme specific proxy
+/// is set in the environment. Scheme specific proxies are picked up by reqwest itself.
+fn get_proxy_url(configured: &str) -> Option<String> {
+    if configured != DEFAULT_NOT_SET {
+        return Some(configured.to_string());
+    }
+
+    let env = |name: &str| std::env::var(name).ok()
+        .or_else(|| std::env::var(name.to_lowercase()).ok())
+        .filter(|v| !v.is_empty());
+
+    if env("HTTP_PROXY").is_some() || env("HTTPS_PROXY").is_some() {
+        return None;
+    }
+
+    env("ALL_PROXY")
+}
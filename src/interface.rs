@@ -0,0 +1,128 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv6Addr};
+
+use crate::failure::FailureKind;
+use crate::public_ip::{self, PublicIps};
+
+const IF_INET6_PATH: &str = "/proc/net/if_inet6";
+const IFA_F_TEMPORARY: u32 = 0x01;
+const IFA_F_DEPRECATED: u32 = 0x20;
+const IFA_F_PERMANENT: u32 = 0x80;
+
+/// Which IPv6 address of the interface is published.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Ipv6Selection {
+    /// A statically configured address, otherwise an EUI-64 or other stable SLAAC address,
+    /// skipping the temporary privacy addresses which keep rotating.
+    Stable,
+    /// Only an address derived from the MAC address of the interface.
+    Eui64,
+    /// The first public address, temporary or not.
+    Any,
+}
+
+/// Address of a local interface, along with what the kernel tells about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceAddress {
+    pub ip: IpAddr,
+    /// RFC 4941 privacy address, replaced every few hours.
+    pub temporary: bool,
+    /// Address no longer preferred, kept until its connections are closed.
+    pub deprecated: bool,
+    /// Statically configured rather than autoconfigured.
+    pub permanent: bool,
+}
+
+/// Reads the public addresses of the provided interface.
+pub fn read(interface: &str, selection: Ipv6Selection) -> Result<PublicIps, FailureKind> {
+    let addresses = addresses_of(interface).map_err(|e| {
+        warn!("Unable to list the addresses of interface {}: {}", interface, e);
+        FailureKind::Network
+    })?;
+
+    let ips = PublicIps {
+        v4: addresses.iter()
+            .map(|a| a.ip)
+            .find(|ip| ip.is_ipv4() && public_ip::is_public(ip))
+            .map(|ip| ip.to_string()),
+        v6: select_ipv6(&addresses, selection).map(|ip| ip.to_string()),
+    };
+
+    if ips.is_empty() {
+        warn!("Interface {} has no public IP address.", interface);
+        return Err(FailureKind::Network);
+    }
+
+    Ok(ips)
+}
+
+/// Returns the public IPv6 address to publish among the addresses of an interface.
+pub fn select_ipv6(addresses: &[InterfaceAddress], selection: Ipv6Selection) -> Option<Ipv6Addr> {
+    let public = addresses.iter().filter_map(|a| match a.ip {
+        IpAddr::V6(ip) if public_ip::is_public(&a.ip) => Some((a, ip)),
+        _ => None,
+    });
+
+    match selection {
+        Ipv6Selection::Any => public.map(|(_, ip)| ip).next(),
+        Ipv6Selection::Eui64 => public.filter(|(a, ip)| !a.deprecated && is_eui64(ip)).map(|(_, ip)| ip).next(),
+        Ipv6Selection::Stable => public
+            .filter(|(a, _)| !a.temporary && !a.deprecated)
+            .min_by_key(|(a, ip)| (!a.permanent, !is_eui64(ip)))
+            .map(|(_, ip)| ip),
+    }
+}
+
+/// Returns whether the interface identifier is derived from a MAC address, with `ff:fe` in
+/// its middle.
+fn is_eui64(ip: &Ipv6Addr) -> bool {
+    let octets = ip.octets();
+    octets[11] == 0xff && octets[12] == 0xfe
+}
+
+/// Parses the address list of `/proc/net/if_inet6`, keeping the addresses of the interface.
+pub fn parse_if_inet6(contents: &str, interface: &str) -> Vec<InterfaceAddress> {
+    contents.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [address, _, _, _, flags, name] = fields.as_slice() else {
+                return None;
+            };
+
+            if *name != interface {
+                return None;
+            }
+
+            let ip = u128::from_str_radix(address, 16).ok().map(Ipv6Addr::from)?;
+            let flags = u32::from_str_radix(flags, 16).ok()?;
+
+            Some(InterfaceAddress {
+                ip: IpAddr::V6(ip),
+                temporary: flags & IFA_F_TEMPORARY != 0,
+                deprecated: flags & IFA_F_DEPRECATED != 0,
+                permanent: flags & IFA_F_PERMANENT != 0,
+            })
+        })
+        .collect()
+}
+
+/// Lists the addresses of the interface. The flags of IPv6 addresses are only known on Linux,
+/// elsewhere every address is considered stable.
+fn addresses_of(interface: &str) -> Result<Vec<InterfaceAddress>, String> {
+    let mut addresses: Vec<InterfaceAddress> = if_addrs::get_if_addrs()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|i| i.name == interface)
+        .map(|i| InterfaceAddress { ip: i.ip(), temporary: false, deprecated: false, permanent: false })
+        .collect();
+
+    if let Ok(contents) = std::fs::read_to_string(IF_INET6_PATH) {
+        let flagged = parse_if_inet6(contents.as_str(), interface);
+        addresses.retain(|a| a.ip.is_ipv4());
+        addresses.extend(flagged);
+    }
+
+    Ok(addresses)
+}
@@ -0,0 +1,60 @@
+use log::{info, warn};
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::constants::*;
+use crate::control::ControlHandle;
+use crate::failure::FailureKind;
+use crate::public_ip::{self, PublicIps};
+
+/// Reads the public addresses written to the provided file, one per line.
+pub fn read(path: &Path) -> Result<PublicIps, FailureKind> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        warn!("Unable to read the IP file {}: {}", path.display(), e);
+        FailureKind::Network
+    })?;
+
+    let mut ips = PublicIps::default();
+
+    for line in contents.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match line.parse::<IpAddr>() {
+            Ok(ip @ IpAddr::V4(_)) if public_ip::is_public(&ip) => ips.v4 = Some(ip.to_string()),
+            Ok(ip @ IpAddr::V6(_)) if public_ip::is_public(&ip) => ips.v6 = Some(ip.to_string()),
+            _ => {
+                warn!("The IP file {} does not contain a public IP address: {:?}", path.display(), line);
+                return Err(FailureKind::Network);
+            }
+        }
+    }
+
+    if ips.is_empty() {
+        warn!("The IP file {} is empty.", path.display());
+        return Err(FailureKind::Network);
+    }
+
+    Ok(ips)
+}
+
+/// Checks the records immediately whenever the provided file is modified.
+pub async fn watch(path: PathBuf, handle: Arc<ControlHandle>) {
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    let mut last_modified = modified(&path);
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(IP_FILE_POLL_INTERVAL)).await;
+
+        let current = modified(&path);
+        if current == last_modified {
+            continue;
+        }
+
+        last_modified = current;
+        if current.is_some() {
+            info!("The IP file {} changed, checking now.", path.display());
+            handle.check_now.notify_one();
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use log::{info, warn};
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::constants::*;
+use crate::state::ProviderStats;
+
+/// IP echo service, with a circuit breaker skipping it while it keeps failing.
+pub struct IpProvider {
+    pub url: String,
+    /// Average latency of the successful requests, weighting recent ones more.
+    pub latency: Option<Duration>,
+    pub successes: u64,
+    pub failures: u64,
+    consecutive_failures: u64,
+    open_until: Option<Instant>,
+}
+
+impl IpProvider {
+    pub fn new(url: &str) -> IpProvider {
+        IpProvider {
+            url: url.to_string(),
+            latency: None,
+            successes: 0,
+            failures: 0,
+            consecutive_failures: 0,
+            open_until: None,
+        }
+    }
+
+    /// Returns whether the provider should be skipped. Once the cooldown has elapsed the
+    /// provider is probed again, and skipped for another cooldown if it still fails.
+    pub fn is_open(&self, now: Instant) -> bool {
+        matches!(self.open_until, Some(t) if now < t)
+    }
+
+    pub fn stats(&self) -> ProviderStats {
+        ProviderStats {
+            url: self.url.clone(),
+            successes: self.successes,
+            failures: self.failures,
+            latency_ms: self.latency.map(|l| l.as_millis() as u64),
+        }
+    }
+
+    /// Returns the share of requests which succeeded, 1 before any request.
+    pub fn success_rate(&self) -> f64 {
+        match self.successes + self.failures {
+            0 => 1.0,
+            total => self.successes as f64 / total as f64,
+        }
+    }
+
+    pub fn record_success(&mut self, latency: Duration) {
+        if self.open_until.is_some() {
+            info!("IP provider {} is working again.", self.url);
+        }
+
+        self.successes += 1;
+        self.latency = Some(match self.latency {
+            Some(average) => average.mul_f64(1.0 - PROVIDER_LATENCY_WEIGHT) + latency.mul_f64(PROVIDER_LATENCY_WEIGHT),
+            None => latency,
+        });
+
+        self.consecutive_failures = 0;
+        self.open_until = None;
+    }
+
+    pub fn record_failure(&mut self, now: Instant, threshold: u64, cooldown: Duration) {
+        self.failures += 1;
+        self.consecutive_failures += 1;
+
+        if threshold > 0 && self.consecutive_failures >= threshold {
+            warn!("IP provider {} failed {} times in a row, skipping it for {}s.",
+                self.url,
+                self.consecutive_failures,
+                cooldown.as_secs());
+
+            self.open_until = Some(now + cooldown);
+        }
+    }
+}
+
+/// Orders the providers by success rate, then by latency, keeping the configured order of
+/// the providers never measured.
+pub fn rank(providers: &mut [IpProvider]) {
+    providers.sort_by(|a, b| b.success_rate().total_cmp(&a.success_rate())
+        .then_with(|| match (a.latency, b.latency) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }));
+}
@@ -0,0 +1,30 @@
+use std::net::Ipv6Addr;
+
+/// Parses the interface identifier appended to a delegated prefix, either as an IPv6
+/// address such as `::1234:5678:9abc:def0` or as the MAC address of the host, from which
+/// its EUI-64 identifier is derived.
+pub fn parse_suffix(value: &str) -> Result<Ipv6Addr, String> {
+    if let Ok(address) = value.parse::<Ipv6Addr>() {
+        return Ok(address);
+    }
+
+    let mac = value.split([':', '-'])
+        .map(|b| u8::from_str_radix(b, 16).ok().filter(|_| b.len() == 2))
+        .collect::<Option<Vec<u8>>>()
+        .filter(|m| m.len() == 6)
+        .ok_or_else(|| format!("`{}` is neither an IPv6 address nor a MAC address", value))?;
+
+    // Insert ff:fe in the middle and flip the universal/local bit.
+    let eui64 = [mac[0] ^ 0x02, mac[1], mac[2], 0xff, 0xfe, mac[3], mac[4], mac[5]];
+
+    let mut octets = [0u8; 16];
+    octets[8..].copy_from_slice(&eui64);
+    Ok(Ipv6Addr::from(octets))
+}
+
+/// Returns the address made of the first `prefix_length` bits of the prefix followed by
+/// the remaining bits of the suffix.
+pub fn combine(prefix: Ipv6Addr, prefix_length: u8, suffix: Ipv6Addr) -> Ipv6Addr {
+    let mask = u128::MAX.checked_shl(128 - u32::from(prefix_length.min(128))).unwrap_or(0);
+    Ipv6Addr::from((u128::from(prefix) & mask) | (u128::from(suffix) & !mask))
+}
@@ -0,0 +1,48 @@
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("Either the `native-tls` or the `rustls` feature must be enabled.");
+
+pub mod backoff;
+pub mod cli;
+pub mod clock;
+pub mod cloudflare_api;
+pub mod cloudflare_client;
+pub mod commands;
+pub mod config;
+pub mod constants;
+pub mod control;
+pub mod daemon;
+pub mod dns_precheck;
+pub mod dns_query;
+pub mod dns_provider;
+#[cfg(windows)]
+pub mod event_log;
+pub mod events;
+pub mod exec_provider;
+pub mod failure;
+pub mod geoip;
+pub mod http_api;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod http_client;
+pub mod interface;
+pub mod ip_file;
+pub mod ip_provider;
+pub mod ipv6;
+pub mod lock;
+pub mod logging;
+pub mod metrics;
+pub mod migration;
+pub mod notification;
+pub mod pattern;
+pub mod privileges;
+pub mod public_ip;
+pub mod rate_limit;
+pub mod reporting;
+pub mod schedule;
+pub mod secret;
+pub mod state;
+pub mod telemetry;
+pub mod template;
+pub mod tui;
+pub mod update_window;
+pub mod updater;
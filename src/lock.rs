@@ -0,0 +1,58 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LockError {
+    #[error("Another updater is already running against {path} (PID {pid}).")]
+    Held {
+        path: String,
+        pid: String,
+    },
+    #[error("Unable to lock {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+}
+
+/// Exclusive lock preventing two updaters from sharing a state directory, released when
+/// dropped or when the process exits.
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Locks the provided file, recording the current PID in it.
+    pub fn acquire(path: &Path) -> Result<InstanceLock, LockError> {
+        let io = |source: std::io::Error| LockError::Io { path: path.display().to_string(), source };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(io)?;
+
+        match file.try_lock() {
+            Ok(()) => {},
+            Err(TryLockError::WouldBlock) => {
+                let pid = fs::read_to_string(path).unwrap_or_default().trim().to_string();
+                return Err(LockError::Held { path: path.display().to_string(), pid });
+            },
+            Err(TryLockError::Error(e)) => return Err(io(e)),
+        }
+
+        file.set_len(0)
+            .and_then(|_| writeln!(file, "{}", std::process::id()))
+            .map_err(io)?;
+
+        Ok(InstanceLock { _file: file })
+    }
+}
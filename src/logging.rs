@@ -0,0 +1,167 @@
+use chrono::{Local, TimeZone};
+use clap::ValueEnum;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::io::{IsTerminal, Write};
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, UtcOffset};
+
+#[cfg(windows)]
+use crate::constants::EVENT_LOG_SOURCE;
+#[cfg(windows)]
+use crate::event_log::EventLog;
+
+/// Layout of each log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Timestamp, level, module and message.
+    Full,
+    /// Level and message only, for init systems adding their own timestamps.
+    Compact,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Time zone of log timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogTimestamps {
+    Utc,
+    Local,
+}
+
+/// Logger writing to stderr in the configured format.
+pub struct Logger {
+    level: LevelFilter,
+    format: LogFormat,
+    timestamps: LogTimestamps,
+    colors: bool,
+    /// Windows Event Log also receiving warnings and errors.
+    #[cfg(windows)]
+    event_log: Option<EventLog>,
+}
+
+impl Logger {
+    /// Creates a logger honoring `RUST_LOG`, or only logging warnings and errors when quiet.
+    /// Colors are only used on a terminal, unless disabled or `NO_COLOR` is set.
+    pub fn new(format: LogFormat, timestamps: LogTimestamps, quiet: bool, no_color: bool) -> Logger {
+        let level = if quiet {
+            LevelFilter::Warn
+        } else {
+            std::env::var("RUST_LOG").ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(LevelFilter::Info)
+        };
+
+        let no_color = no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+
+        Logger {
+            level,
+            format,
+            timestamps,
+            colors: !no_color && std::io::stderr().is_terminal(),
+            #[cfg(windows)]
+            event_log: None,
+        }
+    }
+
+    /// Also reports warnings and errors to the Windows Event Log, if available.
+    #[cfg(windows)]
+    pub fn with_event_log(mut self) -> Logger {
+        self.event_log = EventLog::register(EVENT_LOG_SOURCE);
+
+        if self.event_log.is_none() {
+            eprintln!("Unable to register the {} Windows Event Log source.", EVENT_LOG_SOURCE);
+        }
+
+        self
+    }
+
+    /// Installs the logger.
+    pub fn init(self) {
+        let level = self.level;
+
+        log::set_logger(Box::leak(Box::new(self))).unwrap();
+        log::set_max_level(level);
+    }
+
+    /// Formats a record as a single line, without the trailing newline.
+    pub fn format(&self, record: &Record, timestamp: OffsetDateTime) -> String {
+        match self.format {
+            LogFormat::Full => format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02},{:03} {} [{}] {}",
+                timestamp.year(),
+                timestamp.month() as u8,
+                timestamp.day(),
+                timestamp.hour(),
+                timestamp.minute(),
+                timestamp.second(),
+                timestamp.millisecond(),
+                self.level_label(record.level()),
+                record.target(),
+                record.args()),
+            LogFormat::Compact => format!("{} {}", self.level_label(record.level()), record.args()),
+            LogFormat::Json => serde_json::json!({
+                "timestamp": timestamp.format(&Rfc3339).unwrap_or_default(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            }).to_string(),
+        }
+    }
+
+    fn level_label(&self, level: Level) -> String {
+        let label = format!("{:<5}", level.as_str());
+
+        if !self.colors {
+            return label;
+        }
+
+        let color = match level {
+            Level::Error => "31",
+            Level::Warn => "33",
+            Level::Info => "36",
+            Level::Debug => "35",
+            Level::Trace => return label,
+        };
+
+        format!("\x1b[{}m{}\x1b[0m", color, label)
+    }
+}
+
+/// Returns the offset of the local time zone at the provided time, falling back to UTC.
+fn local_offset(at: OffsetDateTime) -> UtcOffset {
+    let seconds = Local.timestamp_opt(at.unix_timestamp(), 0)
+        .single()
+        .map(|t| t.offset().local_minus_utc())
+        .unwrap_or(0);
+
+    UtcOffset::from_whole_seconds(seconds).unwrap_or(UtcOffset::UTC)
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let timestamp = match self.timestamps {
+            LogTimestamps::Utc => now,
+            LogTimestamps::Local => now.to_offset(local_offset(now)),
+        };
+
+        let line = self.format(record, timestamp);
+        let _ = writeln!(std::io::stderr().lock(), "{}", line);
+
+        #[cfg(windows)]
+        if let Some(event_log) = self.event_log.as_ref() {
+            event_log.report(record.level(), record.args().to_string().as_str());
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
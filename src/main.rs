@@ -1,178 +1,118 @@
-use log::{debug, error, info, LevelFilter, warn};
-use std::{thread, time};
-use reqwest::Client;
-use simple_logger::SimpleLogger;
-
-mod cloudflare_api;
-mod config;
-mod constants;
-
-use crate::cloudflare_api::{CloudflareDnsRecord, CloudflareDnsResult, CloudflareResponse};
-use crate::config::Config;
-
-#[tokio::main]
-async fn main() {
-    SimpleLogger::new()
-        .with_level(LevelFilter::Info)
-        .env()
-        .with_utc_timestamps()
-        .with_colors(true)
-        .init()
-        .unwrap();
+use clap::Parser;
+use log::{debug, error, info};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::runtime::{Builder, Runtime};
+
+use cloudflare_dynamic_ip_updater::cli::{Cli, Command, ConfigCommand};
+use cloudflare_dynamic_ip_updater::commands::{self, unwrap_config};
+use cloudflare_dynamic_ip_updater::config::Config;
+use cloudflare_dynamic_ip_updater::constants::*;
+use cloudflare_dynamic_ip_updater::control::ControlHandle;
+#[cfg(unix)]
+use cloudflare_dynamic_ip_updater::daemon;
+use cloudflare_dynamic_ip_updater::daemon::PidFile;
+use cloudflare_dynamic_ip_updater::events::EventStream;
+use cloudflare_dynamic_ip_updater::lock::InstanceLock;
+use cloudflare_dynamic_ip_updater::logging::Logger;
+use cloudflare_dynamic_ip_updater::reporting;
+use cloudflare_dynamic_ip_updater::state::State;
+use cloudflare_dynamic_ip_updater::{telemetry, tui, updater};
+
+fn main() {
+    let cli = Cli::parse();
+
+    let tui_logger = if cli.tui && cli.command.is_none() {
+        Some(tui::TuiLogger::init())
+    } else {
+        let logger = Logger::new(cli.log_format, cli.log_timestamps, cli.quiet, cli.no_color);
+        #[cfg(windows)]
+        let logger = if cli.event_log { logger.with_event_log() } else { logger };
+        logger.init();
+        None
+    };
     debug!("Initialized logging.");
 
-    debug!("Initializing configuration variables.");
-    let config = Config::load();
-
-    let general_config = config.general.unwrap();
-    let wait_duration = general_config.wait_duration.unwrap();
-
-    let cloudflare_config = config.cloudflare.unwrap();
-    let cloudflare_zone_id = cloudflare_config.zone_id.unwrap();
-    let cloudflare_api_token = cloudflare_config.api_token.unwrap();
-    let cloudflare_dns_record_id = cloudflare_config.dns_record_id.unwrap();
-    debug!("Configuration loaded.");
-
-    debug!("Initializing reqwest client.");
-    let client = reqwest::Client::new();
-
-    let mut current_cloudflare_dns_record: Option<CloudflareResponse<CloudflareDnsResult>> = None;
-
-    debug!("Starting main loop.");
-    loop {
-        debug!("Waiting {}s before next iteration.", wait_duration);
-        thread::sleep(time::Duration::from_secs(wait_duration));
-
-        debug!("Starting iteration.");
-
-        if current_cloudflare_dns_record.is_none() {
-            debug!("Getting the current Cloudflare DNS entry IP.");
-            current_cloudflare_dns_record = get_current_cloudflare_dns_record(&client,
-                cloudflare_zone_id.as_str(),
-                cloudflare_api_token.as_str(),
-                cloudflare_dns_record_id.as_str())
-                .await;
-        }
-
-        // Get the current public IP.
-        debug!("Getting the current public IP.");
-        let current_public_ip = get_current_public_ip(&client)
-            .await;
-
-        if current_public_ip.is_none() || current_cloudflare_dns_record.is_none() {
-            continue;
-        }
-
-        // If the IPs match, then skip this iteration.
-        let current_public_ip_result = current_public_ip.unwrap();
-        let current_cloudflare_dns_record_result = current_cloudflare_dns_record.as_ref().unwrap();
-
-        debug!("Current public IP: {}", current_public_ip_result.trim());
-        debug!("Current Cloudflare DNS IP: {}", current_cloudflare_dns_record_result.result.content.trim());
-
-        if current_public_ip_result.trim() == current_cloudflare_dns_record_result.result.content.trim() {
-            debug!("IP addresses are the same.");
-            continue;
-        }
-
-        // If the IPs do not match, then update the new IP with Cloudflare.
-        info!("IP changed from {} to {}. Updating with Cloudflare.",
-            current_cloudflare_dns_record_result.result.content,
-            current_public_ip_result);
-
-        let new_dns_record = CloudflareDnsRecord {
-            dns_type: current_cloudflare_dns_record_result.result.dns_type.clone(),
-            name: current_cloudflare_dns_record_result.result.name.clone(),
-            content: current_public_ip_result,
-            ttl: current_cloudflare_dns_record_result.result.ttl,
-            proxied: current_cloudflare_dns_record_result.result.proxied
-        };
-
-        current_cloudflare_dns_record = update_cloudflare_dns_record(&client,
-            cloudflare_zone_id.as_str(),
-            cloudflare_api_token.as_str(),
-            cloudflare_dns_record_id.as_str(),
-            &new_dns_record)
-            .await;
-    }
-}
-
-/// Gets the current public IP address.
-async fn get_current_public_ip(client: &Client) -> Option<String> {
-    let body = client.get("https://checkip.amazonaws.com")
-        .send()
-        .await;
+    let overrides = cli.config_overrides();
+
+    match cli.command {
+        Some(Command::ListRecords { json }) => runtime().block_on(commands::list_records(&overrides, json)),
+        Some(Command::CreateRecord { name, record_type, ttl, proxied }) =>
+            runtime().block_on(commands::create_record(&overrides, name, record_type, ttl, proxied)),
+        Some(Command::Cleanup { delete }) => runtime().block_on(commands::cleanup(&overrides, delete)),
+        Some(Command::DryRun { json }) => runtime().block_on(commands::dry_run(&overrides, json)),
+        Some(Command::ListZones { json }) => runtime().block_on(commands::list_zones(&overrides, json)),
+        Some(Command::Status { json }) => runtime().block_on(commands::print_status(&overrides, json)),
+        Some(Command::CheckNow) => runtime().block_on(commands::send_control_command(&overrides, "check-now")),
+        Some(Command::Pause) => runtime().block_on(commands::send_control_command(&overrides, "pause")),
+        Some(Command::Resume) => runtime().block_on(commands::send_control_command(&overrides, "resume")),
+        Some(Command::Reload) => runtime().block_on(commands::send_control_command(&overrides, "reload")),
+        Some(Command::EncryptToken) => commands::encrypt_token(&overrides),
+        Some(Command::SetToken) => commands::set_token(),
+        Some(Command::Doctor) => runtime().block_on(commands::doctor(&overrides)),
+        Some(Command::MigrateConfig { dry_run }) => runtime().block_on(commands::migrate_config(&overrides, dry_run)),
+        Some(Command::Config { command: ConfigCommand::Export }) => commands::export_config(&overrides),
+        Some(Command::Config { command: ConfigCommand::Import { file } }) => commands::import_config(&overrides, file),
+        Some(Command::Completions { shell }) => commands::print_completions(shell),
+        None => {
+            debug!("Initializing configuration variables.");
+            let config = unwrap_config(Config::load(&overrides));
+
+            #[cfg(unix)]
+            if cli.daemon {
+                debug!("Detaching from the terminal.");
+                if let Err(e) = daemon::daemonize() {
+                    error!("Unable to run in the background: {}", e);
+                    std::process::exit(1);
+                }
+            }
 
-    match body {
-        Ok(r) => {
-            match r.text().await {
-                Ok(v) => Some(v),
+            let _lock = match InstanceLock::acquire(&State::get_state_dir().join(LOCK_FILE_NAME)) {
+                Ok(l) => l,
                 Err(e) => {
-                    error!("Error deserializing current IP: {:?}", e);
-                    None
+                    error!("{}", e);
+                    std::process::exit(EXIT_ALREADY_RUNNING);
                 }
-            }
-        },
-        Err(e) => {
-            warn!("Issue trying to get current IP: {:?}", e);
-            None
-        }
-    }
-}
+            };
 
-/// Gets the current IP address set to the provided DNS record.
-async fn get_current_cloudflare_dns_record(client: &Client, zone_id: &str, api_token: &str, dns_record_id: &str) -> Option<CloudflareResponse<CloudflareDnsResult>> {
-    let body = client.get(format!("https://api.cloudflare.com/client/v4/zones/{}/dns_records/{}", zone_id, dns_record_id))
-        .bearer_auth(api_token)
-        .send()
-        .await;
+            let _reporting = unwrap_config(reporting::init(&config));
+            unwrap_config(telemetry::init(&config));
 
-    match body {
-        Ok(r) => {
-            match r.json::<CloudflareResponse<CloudflareDnsResult>>().await {
-                Ok(v) => Some(v),
+            let _pid_file = cli.pidfile.as_ref().map(|path| match PidFile::create(path) {
+                Ok(p) => p,
                 Err(e) => {
-                    error!("Error deserializing current Cloudflare DNS entry: {:?}", e);
-                    None
+                    error!("Unable to write the PID file {}: {}", path.display(), e);
+                    std::process::exit(1);
                 }
-            }
-        },
-        Err(e) => {
-            warn!("Issue trying to get Cloudflare IP: {:?}", e);
-            None
-        }
-    }
-}
+            });
 
-/// Updates the provided DNS record with Cloudflare.
-async fn update_cloudflare_dns_record(client: &Client, zone_id: &str, api_token: &str, dns_record_id: &str, dns_record: &CloudflareDnsRecord) -> Option<CloudflareResponse<CloudflareDnsResult>> {
-    let body = client.post(format!("https://api.cloudflare.com/client/v4/zones/{}/dns_records/{}", zone_id, dns_record_id))
-        .bearer_auth(api_token)
-        .json(dns_record)
-        .send()
-        .await;
+            let handle = Arc::new(ControlHandle::new(State::load().unwrap_or_default()));
+            let runtime_limit = cli.runtime_limit(OffsetDateTime::now_utc());
 
-    match body {
-        Ok(r) => {
-            match r.json::<CloudflareResponse<CloudflareDnsResult>>().await {
-                Ok(v) => {
-                    if !v.success {
-                        error!("Cloudflare update was not successful: {:?}", v);
-                        None
-                    } else {
-                        info!("Cloudflare DNS record updated successfully.");
-                        Some(v)
+            runtime().block_on(async move {
+                let run = async {
+                    match tui_logger {
+                        Some(logger) => tui::run(logger, config, overrides, handle, cli.pidfile).await,
+                        None => updater::run_updater(config, overrides, handle, cli.json_events.then(EventStream::stdout), cli.pidfile).await,
                     }
-                },
-                Err(e) => {
-                    error!("Error deserializing current Cloudflare DNS update response: {:?}", e);
-                    None
+                };
+
+                match runtime_limit {
+                    Some(limit) => if tokio::time::timeout(limit, run).await.is_err() {
+                        info!("Stopping after running for {}s.", limit.as_secs());
+                    },
+                    None => run.await,
                 }
-            }
+            });
+            telemetry::flush();
         },
-        Err(e) => {
-            error!("Cloudflare DNS did not update successfully: {:?}", e);
-            None
-        }
     }
 }
+
+/// Builds the async runtime. Only started once the process is done forking.
+fn runtime() -> Runtime {
+    Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Unable to start the async runtime.")
+}
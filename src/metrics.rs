@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+use crate::constants::*;
+use crate::failure::FailureKind;
+use crate::state::{ProviderStats, State};
+
+/// Durations and failures of the requests sent to Cloudflare and the IP providers since the
+/// updater started, by service and endpoint.
+#[derive(Default)]
+pub struct RequestMetrics {
+    series: Mutex<BTreeMap<(&'static str, String), RequestSeries>>,
+}
+
+#[derive(Default)]
+struct RequestSeries {
+    /// Requests at most as long as each of `REQUEST_DURATION_BUCKETS`.
+    buckets: [u64; REQUEST_DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+    failures: BTreeMap<&'static str, u64>,
+}
+
+impl RequestMetrics {
+    /// Records a request to the endpoint of the service, and the kind of its failure if any.
+    pub fn observe(&self, service: &'static str, endpoint: &str, duration: Duration, failure: Option<FailureKind>) {
+        let mut series = self.series.lock().unwrap();
+        let series = series.entry((service, endpoint.to_string())).or_default();
+        let seconds = duration.as_secs_f64();
+
+        for (bucket, le) in series.buckets.iter_mut().zip(REQUEST_DURATION_BUCKETS) {
+            if seconds <= le {
+                *bucket += 1;
+            }
+        }
+
+        series.count += 1;
+        series.sum += seconds;
+
+        if let Some(kind) = failure {
+            *series.failures.entry(kind.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    /// Returns how many requests were recorded.
+    pub fn total(&self) -> u64 {
+        self.series.lock().unwrap().values().map(|s| s.count).sum()
+    }
+
+    /// Renders the request durations as a histogram, and the failures as a counter.
+    fn render(&self, output: &mut String) {
+        let series = self.series.lock().unwrap();
+        if series.is_empty() {
+            return;
+        }
+
+        let name = "cloudflare_ddns_request_duration_seconds";
+        let _ = writeln!(output, "# HELP {} Duration of the requests to each endpoint.", name);
+        let _ = writeln!(output, "# TYPE {} histogram", name);
+
+        for ((service, endpoint), s) in series.iter() {
+            let labels = format!("service=\"{}\",endpoint=\"{}\"", service, escape_label(endpoint));
+
+            for (count, le) in s.buckets.iter().zip(REQUEST_DURATION_BUCKETS) {
+                let _ = writeln!(output, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, le, count);
+            }
+
+            let _ = writeln!(output, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, s.count);
+            let _ = writeln!(output, "{}_sum{{{}}} {}", name, labels, s.sum);
+            let _ = writeln!(output, "{}_count{{{}}} {}", name, labels, s.count);
+        }
+
+        let name = "cloudflare_ddns_request_failures_total";
+        let _ = writeln!(output, "# HELP {} Failed requests to each endpoint by category.", name);
+        let _ = writeln!(output, "# TYPE {} counter", name);
+
+        for ((service, endpoint), s) in series.iter() {
+            for (kind, count) in &s.failures {
+                let _ = writeln!(output, "{}{{service=\"{}\",endpoint=\"{}\",kind=\"{}\"}} {}",
+                    name, service, escape_label(endpoint), kind, count);
+            }
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Renders the runtime statistics in the Prometheus text exposition format.
+pub fn render(state: &State, requests: &RequestMetrics, paused: bool, now: OffsetDateTime) -> String {
+    let stats = &state.stats;
+    let mut output = String::new();
+
+    let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, f64)]| {
+        let _ = writeln!(output, "# HELP {} {}", name, help);
+        let _ = writeln!(output, "# TYPE {} {}", name, kind);
+
+        for (labels, value) in samples {
+            let _ = writeln!(output, "{}{} {}", name, labels, value);
+        }
+    };
+
+    metric("cloudflare_ddns_checks_total", "counter", "Public IP checks performed.", &[("", stats.checks as f64)]);
+    metric("cloudflare_ddns_updates_total", "counter", "DNS records updated.", &[("", stats.updates as f64)]);
+    metric("cloudflare_ddns_failures_total", "counter", "Failed checks by category.", &[
+        ("{kind=\"network\"}", stats.failures.network as f64),
+        ("{kind=\"auth\"}", stats.failures.auth as f64),
+        ("{kind=\"rate_limit\"}", stats.failures.rate_limit as f64),
+        ("{kind=\"api\"}", stats.failures.api as f64),
+        ("{kind=\"mismatch\"}", stats.failures.mismatch as f64),
+    ]);
+    metric("cloudflare_ddns_paused", "gauge", "Whether the updater is paused.", &[("", if paused { 1.0 } else { 0.0 })]);
+    metric("cloudflare_ddns_records_drifting", "gauge", "Records not pointing to the public IP, while only observing them.",
+        &[("", state.records.iter().filter(|r| r.drift.is_some()).count() as f64)]);
+
+    let providers = &state.providers;
+    if !providers.is_empty() {
+        let labels: Vec<String> = providers.iter()
+            .map(|p| format!("{{url=\"{}\"}}", escape_label(p.url.as_str())))
+            .collect();
+        let samples = |value: fn(&ProviderStats) -> Option<f64>| providers.iter()
+            .zip(&labels)
+            .filter_map(|(p, l)| value(p).map(|v| (l.as_str(), v)))
+            .collect::<Vec<(&str, f64)>>();
+
+        metric("cloudflare_ddns_ip_provider_successes_total", "counter", "Successful requests to each IP provider.",
+            &samples(|p| Some(p.successes as f64)));
+        metric("cloudflare_ddns_ip_provider_failures_total", "counter", "Failed requests to each IP provider.",
+            &samples(|p| Some(p.failures as f64)));
+        metric("cloudflare_ddns_ip_provider_latency_seconds", "gauge", "Average latency of each IP provider.",
+            &samples(|p| p.latency_ms.map(|l| l as f64 / 1000.0)));
+    }
+
+    if let Some(uptime) = stats.uptime(now) {
+        metric("cloudflare_ddns_uptime_seconds", "gauge", "Time since the updater started.", &[("", uptime.as_seconds_f64())]);
+    }
+
+    if let Some(t) = state.last_checked {
+        metric("cloudflare_ddns_last_check_timestamp_seconds", "gauge", "Time of the last check.", &[("", t.unix_timestamp() as f64)]);
+    }
+
+    if let Some(t) = state.last_changed {
+        metric("cloudflare_ddns_last_change_timestamp_seconds", "gauge", "Time of the last IP change.", &[("", t.unix_timestamp() as f64)]);
+        metric("cloudflare_ddns_seconds_since_last_change", "gauge", "Time since the last IP change.", &[("", (now - t).as_seconds_f64())]);
+    }
+
+    requests.render(&mut output);
+    output
+}
@@ -0,0 +1,135 @@
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::{Table, Value};
+
+use crate::config::ConfigError;
+use crate::constants::*;
+
+/// Upgrades a configuration table from one version to the next.
+type Migration = fn(&mut Table);
+
+/// Migrations indexed by the version they upgrade from.
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [
+    migrate_v0_to_v1,
+];
+
+/// Unversioned files predate the `version` key, but share the current layout.
+fn migrate_v0_to_v1(_config: &mut Table) {}
+
+/// Migrates the configuration file to the current version in place, keeping a backup
+/// of the original file next to it.
+pub fn migrate(filepath: &Path) -> Result<(), ConfigError> {
+    let io_error = |source| ConfigError::Io { path: filepath.to_path_buf(), source };
+    let contents = fs::read_to_string(filepath).map_err(io_error)?;
+
+    // Leave invalid files alone so the parse error is reported when loading them.
+    let mut config = match contents.parse::<Value>() {
+        Ok(Value::Table(t)) => t,
+        _ => return Ok(()),
+    };
+
+    let version = match config.get("version") {
+        None => 0,
+        Some(Value::Integer(v)) if *v >= 0 => *v as u64,
+        Some(_) => return Ok(()),
+    };
+
+    if version > CONFIG_VERSION {
+        return Err(ConfigError::UnsupportedVersion { version, path: filepath.to_path_buf() });
+    }
+
+    if version == CONFIG_VERSION {
+        debug!("Configuration is at version {}, nothing to migrate.", version);
+        return Ok(());
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        debug!("Migrating configuration from version {} to {}.", from, from + 1);
+        migration(&mut config);
+    }
+
+    // The version is written separately, as top-level values must precede every table.
+    config.remove("version");
+    let migrated = match toml::to_string(&config) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("Unable to serialize the migrated configuration: {:?}", e);
+            return Ok(());
+        }
+    };
+
+    let backup = get_backup_file_path(filepath, version);
+    fs::copy(filepath, &backup).map_err(io_error)?;
+
+    fs::write(filepath, format!("{}version = {}\n\n{}", DEFAULT_CONFIG_HEADER, CONFIG_VERSION, migrated))
+        .map_err(io_error)?;
+
+    info!("Migrated configuration from version {} to {}, the original was saved to {}.",
+        version,
+        CONFIG_VERSION,
+        backup.display());
+
+    Ok(())
+}
+
+/// Returns the path the original file is backed up to before migrating from `version`.
+fn get_backup_file_path(filepath: &Path, version: u64) -> PathBuf {
+    let mut name = filepath.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+
+    filepath.with_file_name(name)
+}
+
+/// Replaces the ID of every record found in `names` by its name and type, so that the
+/// record is selected by name. The record configured as `cloudflare.dns_record_id` becomes a
+/// listed record, unless records are listed already and it is ignored. Returns how many
+/// records were replaced.
+pub fn replace_record_ids(config: &mut Table, names: &HashMap<String, (String, String)>) -> usize {
+    let mut replaced = 0;
+    let mut replace = |record: &mut Table| {
+        let found = record.get("dns_record_id")
+            .and_then(Value::as_str)
+            .and_then(|id| names.get(id));
+
+        if let Some((name, record_type)) = found {
+            record.remove("dns_record_id");
+            record.insert("name".to_string(), Value::String(name.clone()));
+            record.insert("type".to_string(), Value::String(record_type.clone()));
+            replaced += 1;
+        }
+    };
+
+    let records_listed = config.get("records").and_then(Value::as_array).is_some_and(|r| !r.is_empty());
+    let legacy = config.get_mut("cloudflare")
+        .and_then(Value::as_table_mut)
+        .filter(|_| !records_listed)
+        .and_then(|c| match c.get("dns_record_id").and_then(Value::as_str) {
+            Some(id) if names.contains_key(id) => c.remove("dns_record_id"),
+            _ => None,
+        });
+
+    if let Some(id) = legacy {
+        let mut record = Table::new();
+        record.insert("dns_record_id".to_string(), id);
+        config.insert("records".to_string(), Value::Array(vec![Value::Table(record)]));
+    }
+
+    let groups = config.get_mut("groups").and_then(Value::as_array_mut).into_iter().flatten()
+        .filter_map(Value::as_table_mut)
+        .filter_map(|g| g.get_mut("records"))
+        .collect::<Vec<&mut Value>>();
+
+    for records in groups {
+        for record in records.as_array_mut().into_iter().flatten().filter_map(Value::as_table_mut) {
+            replace(record);
+        }
+    }
+
+    for record in config.get_mut("records").and_then(Value::as_array_mut).into_iter().flatten().filter_map(Value::as_table_mut) {
+        replace(record);
+    }
+
+    replaced
+}
@@ -0,0 +1,564 @@
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::{debug, warn};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+use crate::constants::*;
+use crate::failure::FailureKind;
+
+/// Something worth telling the user about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The content of a record was updated.
+    IpChanged {
+        name: String,
+        previous: Option<String>,
+        current: String,
+        /// Where the new address is located, if looked up.
+        location: Option<String>,
+    },
+    /// The new address of a record belongs to another network than the previous one, such as
+    /// after failing over to a backup link.
+    ProviderChanged {
+        name: String,
+        previous: String,
+        current: String,
+    },
+    /// A record points to another address than the public IP, while only observing the records.
+    Drift {
+        name: String,
+        published: String,
+        detected: String,
+    },
+    /// Checks kept failing.
+    Failure {
+        kind: FailureKind,
+        consecutive_failures: u64,
+    },
+    /// Events held back and summarized together.
+    Digest(Vec<Event>),
+}
+
+/// Types of events a notifier can be limited to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    IpChanged,
+    ProviderChanged,
+    Drift,
+    /// Checks kept failing, for any reason including a rejected API token.
+    Failure,
+}
+
+impl Event {
+    /// Returns the type of the event, `None` for a digest, which only ever holds events
+    /// already routed to the notifier.
+    pub fn kind(&self) -> Option<EventKind> {
+        match self {
+            Event::IpChanged { .. } => Some(EventKind::IpChanged),
+            Event::ProviderChanged { .. } => Some(EventKind::ProviderChanged),
+            Event::Drift { .. } => Some(EventKind::Drift),
+            Event::Failure { .. } => Some(EventKind::Failure),
+            Event::Digest(_) => None,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            Event::IpChanged { name, .. } => format!("IP of {} changed", name),
+            Event::ProviderChanged { name, .. } => format!("Network provider of {} changed", name),
+            Event::Drift { name, .. } => format!("{} does not point to the public IP", name),
+            Event::Failure { .. } => "Cloudflare DNS updates are failing".to_string(),
+            Event::Digest(events) => format!("{} Cloudflare DNS updater events", events.len()),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Event::IpChanged { name, previous, current, location } => {
+                let current = match location {
+                    Some(location) => format!("{} ({})", current, location),
+                    None => current.clone(),
+                };
+
+                match previous {
+                    Some(previous) => format!("{} now points to {}, previously {}.", name, current, previous),
+                    None => format!("{} now points to {}.", name, current),
+                }
+            },
+            Event::ProviderChanged { name, previous, current } =>
+                format!("{} is now served by {}, previously {}.", name, current, previous),
+            Event::Drift { name, published, detected } =>
+                format!("{} points to {} but the public IP is {}.", name, published, detected),
+            Event::Failure { kind, consecutive_failures } =>
+                format!("{} consecutive checks failed ({} error).", consecutive_failures, kind.as_str()),
+            Event::Digest(events) => events.iter()
+                .map(|e| format!("- {}", e.message()))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// Sender of notifications, parsed from an Apprise-style URL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notifier {
+    /// `discord://{webhook_id}/{webhook_token}`
+    Discord {
+        webhook_url: String,
+    },
+    /// `ntfy://{topic}`, `ntfy[s]://[{user}:{password}@]{host}/{topic}`, or with a `token`
+    /// query parameter.
+    Ntfy {
+        url: String,
+        auth: Option<NtfyAuth>,
+    },
+    /// `matrix[s]://{access_token}@{homeserver}/{room}`, the room being an ID such as
+    /// `!abc:example.org` or an alias such as `#home:example.org`.
+    Matrix {
+        homeserver_url: String,
+        access_token: String,
+        room: String,
+    },
+    /// `json[s]://{host}[:{port}]/{path}`, posting the title and message as JSON.
+    Json {
+        url: String,
+    },
+    /// `mailto[s]://{user}:{password}@{domain}`, with optional `smtp`, `from` and `to` query
+    /// parameters. `mailto` uses STARTTLS and `mailtos` implicit TLS.
+    Email {
+        host: String,
+        port: Option<u16>,
+        implicit_tls: bool,
+        username: String,
+        password: String,
+        from: String,
+        to: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NtfyAuth {
+    Basic { username: String, password: String },
+    Token(String),
+}
+
+impl Notifier {
+    /// Parses the provided Apprise-style notification URL.
+    pub fn parse(value: &str) -> Result<Notifier, String> {
+        let url = Url::parse(value).map_err(|e| format!("{}: {}", redact(value), e))?;
+        let host = url.host_str().unwrap_or_default().to_string();
+        let path = url.path().trim_matches('/').to_string();
+        let query = |name: &str| url.query_pairs().find(|(k, _)| k == name).map(|(_, v)| v.to_string());
+        let invalid = |reason: &str| format!("{}: {}", redact(value), reason);
+
+        match url.scheme() {
+            "discord" => {
+                if host.is_empty() || path.is_empty() || path.contains('/') {
+                    return Err(invalid("expected discord://{webhook_id}/{webhook_token}"));
+                }
+
+                Ok(Notifier::Discord { webhook_url: format!("https://discord.com/api/webhooks/{}/{}", host, path) })
+            },
+            scheme @ ("ntfy" | "ntfys") => {
+                if host.is_empty() {
+                    return Err(invalid("expected ntfy://{topic} or ntfy://{host}/{topic}"));
+                }
+
+                // A single segment is a topic on the public server.
+                let topic_url = match path.is_empty() {
+                    true => format!("https://ntfy.sh/{}", host),
+                    false => format!("{}/{}", http_base(scheme == "ntfys", &host, url.port()), path),
+                };
+
+                let auth = match (query("token"), url.username()) {
+                    (Some(token), _) => Some(NtfyAuth::Token(token)),
+                    (None, "") => None,
+                    (None, username) => Some(NtfyAuth::Basic {
+                        username: percent_decode(username),
+                        password: percent_decode(url.password().unwrap_or_default()),
+                    }),
+                };
+
+                Ok(Notifier::Ntfy { url: topic_url, auth })
+            },
+            scheme @ ("matrix" | "matrixs") => {
+                // Room aliases start with `#`, which ends up in the fragment.
+                let room = match url.fragment() {
+                    Some(alias) => format!("#{}", percent_decode(alias)),
+                    None => percent_decode(&path),
+                };
+
+                if host.is_empty() || url.username().is_empty() || room.is_empty() {
+                    return Err(invalid("expected matrix://{access_token}@{homeserver}/{room}"));
+                }
+
+                Ok(Notifier::Matrix {
+                    homeserver_url: http_base(scheme == "matrixs", &host, url.port()),
+                    access_token: percent_decode(url.username()),
+                    room,
+                })
+            },
+            scheme @ ("json" | "jsons") => {
+                if host.is_empty() {
+                    return Err(invalid("expected json://{host}/{path}"));
+                }
+
+                let query = url.query().map(|q| format!("?{}", q)).unwrap_or_default();
+                Ok(Notifier::Json {
+                    url: format!("{}{}{}", http_base(scheme == "jsons", &host, url.port()), url.path(), query),
+                })
+            },
+            scheme @ ("mailto" | "mailtos") => {
+                if host.is_empty() || url.username().is_empty() {
+                    return Err(invalid("expected mailto://{user}:{password}@{domain}"));
+                }
+
+                let username = percent_decode(url.username());
+                let from = query("from").unwrap_or_else(|| match username.contains('@') {
+                    true => username.clone(),
+                    false => format!("{}@{}", username, host),
+                });
+                let to: Vec<String> = match query("to") {
+                    Some(to) => to.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+                    None => vec![from.clone()],
+                };
+
+                for address in to.iter().chain([&from]) {
+                    address.parse::<Mailbox>().map_err(|e| invalid(&format!("invalid address {}: {}", address, e)))?;
+                }
+
+                Ok(Notifier::Email {
+                    host: query("smtp").unwrap_or(host),
+                    port: url.port(),
+                    implicit_tls: scheme == "mailtos",
+                    username,
+                    password: percent_decode(url.password().unwrap_or_default()),
+                    from,
+                    to,
+                })
+            },
+            scheme => Err(invalid(&format!("unsupported scheme {}://", scheme))),
+        }
+    }
+
+    /// Returns the name of the service, as shown in logs.
+    pub fn service(&self) -> &'static str {
+        match self {
+            Notifier::Discord { .. } => "Discord",
+            Notifier::Ntfy { .. } => "ntfy",
+            Notifier::Matrix { .. } => "Matrix",
+            Notifier::Json { .. } => "JSON webhook",
+            Notifier::Email { .. } => "email",
+        }
+    }
+
+    /// Sends the provided event.
+    pub async fn send(&self, client: &Client, event: &Event) -> Result<(), String> {
+        let request = match self {
+            Notifier::Discord { webhook_url } => client.post(webhook_url)
+                .json(&json!({ "content": format!("**{}**\n{}", event.title(), event.message()) })),
+            Notifier::Ntfy { url, auth } => {
+                let request = client.post(url)
+                    .header("Title", event.title())
+                    .body(event.message());
+
+                match auth {
+                    Some(NtfyAuth::Basic { username, password }) => request.basic_auth(username, Some(password)),
+                    Some(NtfyAuth::Token(token)) => request.bearer_auth(token),
+                    None => request,
+                }
+            },
+            Notifier::Json { url } => client.post(url)
+                .json(&json!({ "version": "1.0", "title": event.title(), "message": event.message(), "type": "info" })),
+            Notifier::Matrix { .. } => return self.send_matrix(client, event).await,
+            Notifier::Email { .. } => return self.send_email(event).await,
+        };
+
+        match request.send().await {
+            Ok(r) if r.status().is_success() => Ok(()),
+            Ok(r) => Err(format!("the service returned {}", r.status())),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn send_matrix(&self, client: &Client, event: &Event) -> Result<(), String> {
+        let Notifier::Matrix { homeserver_url, access_token, room } = self else {
+            return Ok(());
+        };
+
+        let room_id = match room.starts_with('#') {
+            true => {
+                let url = format!("{}/_matrix/client/v3/directory/room/{}", homeserver_url, encode_path_segment(room));
+                let response = client.get(url).bearer_auth(access_token).send().await.map_err(|e| e.to_string())?;
+                if !response.status().is_success() {
+                    return Err(format!("unable to resolve {}, the homeserver returned {}", room, response.status()));
+                }
+
+                let body: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+                body["room_id"].as_str().ok_or_else(|| format!("unable to resolve {}", room))?.to_string()
+            },
+            false => room.clone(),
+        };
+
+        let transaction_id = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let url = format!("{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+            homeserver_url,
+            encode_path_segment(&room_id),
+            transaction_id);
+
+        let response = client.put(url)
+            .bearer_auth(access_token)
+            .json(&json!({ "msgtype": "m.text", "body": format!("{}\n{}", event.title(), event.message()) }))
+            .send()
+            .await;
+
+        match response {
+            Ok(r) if r.status().is_success() => Ok(()),
+            Ok(r) => Err(format!("the service returned {}", r.status())),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn send_email(&self, event: &Event) -> Result<(), String> {
+        let Notifier::Email { host, port, implicit_tls, username, password, from, to } = self else {
+            return Ok(());
+        };
+
+        let mut builder = Message::builder()
+            .from(from.parse::<Mailbox>().map_err(|e| e.to_string())?)
+            .subject(event.title());
+
+        for address in to {
+            builder = builder.to(address.parse::<Mailbox>().map_err(|e| e.to_string())?);
+        }
+
+        let email = builder.body(event.message()).map_err(|e| e.to_string())?;
+
+        let mut transport = match implicit_tls {
+            true => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+            false => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+        }.map_err(|e| e.to_string())?;
+
+        if let Some(port) = port {
+            transport = transport.port(*port);
+        }
+
+        transport.credentials(Credentials::new(username.clone(), password.clone()))
+            .build()
+            .send(email)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Sends events to the configured notifiers in the background, so that a slow service
+/// never delays the updates.
+///
+/// Events exceeding the hourly limit of a notifier, or every event in digest mode, are held
+/// back and sent together in a single summary.
+#[derive(Clone, Default)]
+pub struct Notifications {
+    client: Client,
+    notifiers: Arc<Vec<(Route, Mutex<Throttle>)>>,
+    per_hour: u64,
+    digest_interval: Option<Duration>,
+}
+
+/// Notifier and the events sent through it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub notifier: Notifier,
+    /// Types of events sent, every type if `None`.
+    pub events: Option<Vec<EventKind>>,
+    /// Record group whose events are the only ones sent, if set.
+    pub group: Option<String>,
+}
+
+impl Route {
+    /// Sends every event through the notifier.
+    pub fn new(notifier: Notifier) -> Route {
+        Route { notifier, events: None, group: None }
+    }
+
+    /// Returns whether the event of a record of the provided group is sent through the notifier.
+    fn accepts(&self, event: &Event, group: Option<&str>) -> bool {
+        self.events.as_ref().is_none_or(|events| event.kind().is_some_and(|k| events.contains(&k)))
+            && self.group.as_ref().is_none_or(|g| group == Some(g.as_str()))
+    }
+}
+
+/// Notifications recently sent through a notifier, and the ones held back.
+#[derive(Default)]
+struct Throttle {
+    sent: VecDeque<Instant>,
+    pending: Vec<Event>,
+    flush_scheduled: bool,
+}
+
+impl Notifications {
+    pub fn new(client: Client, notifiers: Vec<Notifier>) -> Notifications {
+        Notifications::routed(client, notifiers.into_iter().map(Route::new).collect())
+    }
+
+    /// Sends each event only through the notifiers whose route accepts it.
+    pub fn routed(client: Client, routes: Vec<Route>) -> Notifications {
+        Notifications {
+            client,
+            notifiers: Arc::new(routes.into_iter().map(|r| (r, Mutex::new(Throttle::default()))).collect()),
+            per_hour: 0,
+            digest_interval: None,
+        }
+    }
+
+    /// Returns the client notifications are sent with.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Sends at most `per_hour` notifications per notifier in any hour, 0 meaning no limit.
+    pub fn with_rate_limit(mut self, per_hour: u64) -> Notifications {
+        self.per_hour = per_hour;
+        self
+    }
+
+    /// Summarizes the events of every `interval` in a single notification.
+    pub fn with_digest(mut self, interval: Duration) -> Notifications {
+        self.digest_interval = Some(interval);
+        self
+    }
+
+    pub fn notify(&self, event: Event) {
+        self.notify_group(event, None);
+    }
+
+    /// Sends the event of a record belonging to the provided group.
+    pub fn notify_group(&self, event: Event, group: Option<&str>) {
+        let now = Instant::now();
+        let period = Duration::from_secs(NOTIFICATION_RATE_LIMIT_PERIOD);
+
+        for i in 0..self.notifiers.len() {
+            let (route, throttle) = &self.notifiers[i];
+            if !route.accepts(&event, group) {
+                continue;
+            }
+
+            let notifier = &route.notifier;
+            let mut throttle = throttle.lock().unwrap();
+
+            while throttle.sent.front().is_some_and(|t| now >= *t + period) {
+                throttle.sent.pop_front();
+            }
+
+            let limited = self.per_hour > 0 && throttle.sent.len() as u64 >= self.per_hour;
+
+            if self.digest_interval.is_none() && !limited {
+                throttle.sent.push_back(now);
+                self.send(i, event.clone());
+                continue;
+            }
+
+            if limited && self.digest_interval.is_none() {
+                debug!("Holding back the {} notification, {} were already sent in the last hour.", notifier.service(), self.per_hour);
+            }
+
+            throttle.pending.push(event.clone());
+
+            if !throttle.flush_scheduled {
+                throttle.flush_scheduled = true;
+                let delay = match (self.digest_interval, throttle.sent.front()) {
+                    (Some(interval), _) => interval,
+                    (None, Some(oldest)) => (*oldest + period).saturating_duration_since(now),
+                    (None, None) => Duration::ZERO,
+                };
+
+                let notifications = self.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    notifications.flush(i);
+                });
+            }
+        }
+    }
+
+    /// Sends the events held back for the provided notifier as a single summary.
+    fn flush(&self, index: usize) {
+        let events = {
+            let mut throttle = self.notifiers[index].1.lock().unwrap();
+            throttle.flush_scheduled = false;
+            throttle.sent.push_back(Instant::now());
+            std::mem::take(&mut throttle.pending)
+        };
+
+        match events.len() {
+            0 => {},
+            1 => self.send(index, events.into_iter().next().unwrap()),
+            _ => self.send(index, Event::Digest(events)),
+        }
+    }
+
+    fn send(&self, index: usize, event: Event) {
+        debug!("Sending notification: {}", event.title());
+        let notifications = self.clone();
+
+        tokio::spawn(async move {
+            let notifier = &notifications.notifiers[index].0.notifier;
+            if let Err(e) = notifier.send(&notifications.client, &event).await {
+                warn!("Unable to send the {} notification: {}", notifier.service(), e);
+            }
+        });
+    }
+}
+
+/// Returns the origin of the HTTP URL an Apprise URL stands for.
+fn http_base(secure: bool, host: &str, port: Option<u16>) -> String {
+    let port = port.map(|p| format!(":{}", p)).unwrap_or_default();
+    format!("{}://{}{}", if secure { "https" } else { "http" }, host, port)
+}
+
+/// Encodes the characters of Matrix identifiers which are not allowed in a path segment.
+fn encode_path_segment(value: &str) -> String {
+    value.replace('%', "%25").replace('#', "%23").replace('!', "%21").replace(':', "%3A").replace('/', "%2F")
+}
+
+/// Hides the credentials of the provided URL, for error messages.
+fn redact(value: &str) -> String {
+    match (value.find("://"), value.rfind('@')) {
+        (Some(start), Some(end)) if end > start => format!("{}***{}", &value[..start + 3], &value[end..]),
+        _ => value.to_string(),
+    }
+}
+
+/// Decodes the percent-encoded credentials of a URL.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,110 @@
+#[cfg(unix)]
+use log::{info, warn};
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::ConfigError;
+use crate::constants::*;
+
+/// Switches to the configured user and group, if any. The group defaults to the primary group
+/// of the user. Does nothing when neither is set.
+///
+/// The provided files, created while still privileged, are handed over to the user and group
+/// first so that they can keep being written.
+#[cfg(unix)]
+pub fn drop_privileges(user: &str, group: &str, owned: &[PathBuf]) -> Result<(), ConfigError> {
+    let user = (user != DEFAULT_NOT_SET).then(|| lookup_user(user)).transpose()?;
+    let gid = match (group != DEFAULT_NOT_SET, user) {
+        (true, _) => Some(lookup_group(group)?),
+        (false, Some((_, gid))) => Some(gid),
+        (false, None) => None,
+    };
+
+    if gid.is_some() || user.is_some() {
+        for path in owned {
+            match std::os::unix::fs::chown(path, user.map(|(uid, _)| uid), gid) {
+                Ok(()) => {},
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+                Err(e) => warn!("Unable to hand {} over to the configured user: {}", path.display(), e),
+            }
+        }
+    }
+
+    if let Some(gid) = gid {
+        let failed = |e: io::Error| ConfigError::Invalid { key: "general.group", reason: e.to_string() };
+
+        check(unsafe { libc::setgroups(1, &gid) }).map_err(failed)?;
+        check(unsafe { libc::setgid(gid) }).map_err(failed)?;
+    }
+
+    if let Some((uid, _)) = user {
+        check(unsafe { libc::setuid(uid) })
+            .map_err(|e| ConfigError::Invalid { key: "general.user", reason: e.to_string() })?;
+    }
+
+    if gid.is_some() || user.is_some() {
+        info!("Running as user {} and group {}.", unsafe { libc::getuid() }, unsafe { libc::getgid() });
+    }
+
+    Ok(())
+}
+
+/// Fails when a user or group is configured, switching them is only possible on Unix.
+#[cfg(not(unix))]
+pub fn drop_privileges(user: &str, group: &str, _: &[PathBuf]) -> Result<(), ConfigError> {
+    let unsupported = |key| ConfigError::Invalid { key, reason: "switching users is only supported on Unix".to_string() };
+
+    match (user != DEFAULT_NOT_SET, group != DEFAULT_NOT_SET) {
+        (true, _) => Err(unsupported("general.user")),
+        (false, true) => Err(unsupported("general.group")),
+        (false, false) => Ok(()),
+    }
+}
+
+/// Returns the UID and primary GID of the provided user name or UID.
+#[cfg(unix)]
+fn lookup_user(user: &str) -> Result<(libc::uid_t, libc::gid_t), ConfigError> {
+    let unknown = || ConfigError::Invalid { key: "general.user", reason: format!("unknown user {}", user) };
+    let name = CString::new(user).map_err(|_| unknown())?;
+
+    let passwd = match user.parse::<libc::uid_t>() {
+        Ok(uid) => unsafe { libc::getpwuid(uid) },
+        Err(_) => unsafe { libc::getpwnam(name.as_ptr()) },
+    };
+
+    if passwd.is_null() {
+        return Err(unknown());
+    }
+
+    Ok(unsafe { ((*passwd).pw_uid, (*passwd).pw_gid) })
+}
+
+/// Returns the GID of the provided group name or GID.
+#[cfg(unix)]
+fn lookup_group(group: &str) -> Result<libc::gid_t, ConfigError> {
+    if let Ok(gid) = group.parse::<libc::gid_t>() {
+        return Ok(gid);
+    }
+
+    let unknown = || ConfigError::Invalid { key: "general.group", reason: format!("unknown group {}", group) };
+    let name = CString::new(group).map_err(|_| unknown())?;
+    let entry = unsafe { libc::getgrnam(name.as_ptr()) };
+
+    if entry.is_null() {
+        return Err(unknown());
+    }
+
+    Ok(unsafe { (*entry).gr_gid })
+}
+
+#[cfg(unix)]
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
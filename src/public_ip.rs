@@ -0,0 +1,218 @@
+use log::{error, warn};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::constants::*;
+use crate::dns_query::DnsQuery;
+use crate::failure::FailureKind;
+
+/// Which address families are detected and published.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressPolicy {
+    /// Only detect the IPv4 address.
+    V4Only,
+    /// Only detect the IPv6 address.
+    V6Only,
+    /// Detect the IPv4 address, falling back to the IPv6 address if no provider answers.
+    PreferV4,
+    /// Detect the IPv6 address, falling back to the IPv4 address if no provider answers.
+    PreferV6,
+    /// Detect both addresses, A records getting the IPv4 one and AAAA records the IPv6 one.
+    Both,
+}
+
+impl AddressPolicy {
+    /// Returns the families to detect in order, `true` standing for IPv6.
+    pub fn families(&self) -> &'static [bool] {
+        match self {
+            AddressPolicy::V4Only => &[false],
+            AddressPolicy::V6Only => &[true],
+            AddressPolicy::PreferV4 | AddressPolicy::Both => &[false, true],
+            AddressPolicy::PreferV6 => &[true, false],
+        }
+    }
+
+    /// Returns the families records are published for, the fallback of the preferring
+    /// policies being left out.
+    pub fn published_families(&self) -> &'static [bool] {
+        match self {
+            AddressPolicy::Both => &[false, true],
+            policy => &policy.families()[..1],
+        }
+    }
+}
+
+/// How the IP echo services are asked for the public IP.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectionStrategy {
+    /// Ask the providers one at a time, in order, until one answers.
+    Sequential,
+    /// Ask every provider at once, taking the first valid answer.
+    Fastest,
+    /// Ask the providers one at a time, ordered by success rate and latency as measured by
+    /// asking all of them at every probe interval.
+    Latency,
+}
+
+/// Where the public IP comes from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IpSource {
+    /// Ask the IP echo services.
+    Http,
+    /// Read the file another process writes the public IP to, checking whenever it changes.
+    File,
+    /// Read the addresses of a local interface, for hosts holding their public addresses.
+    Interface,
+}
+
+/// Transport an IP provider is asked over, given by the scheme of its URL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    Https,
+    /// Plain HTTP, sparing constrained devices the TLS handshake.
+    Http,
+    /// A DNS question over UDP, as described by `DnsQuery`.
+    Dns,
+}
+
+impl Transport {
+    /// Returns the transport of the provided provider URL, rejecting URLs it cannot be used with.
+    pub fn of(url: &str) -> Result<Transport, String> {
+        match url.split_once("://").map(|(scheme, _)| scheme.to_lowercase()).as_deref() {
+            Some("https") => Ok(Transport::Https),
+            Some("http") => Ok(Transport::Http),
+            Some("dns") => DnsQuery::parse(url, false)
+                .map(|_| Transport::Dns)
+                .map_err(|e| format!("{}: {}", url, e)),
+            _ => Err(format!("{} does not start with https://, http:// or dns://", url)),
+        }
+    }
+}
+
+/// Public addresses detected during a check.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct PublicIps {
+    pub v4: Option<String>,
+    pub v6: Option<String>,
+}
+
+impl PublicIps {
+    pub fn is_empty(&self) -> bool {
+        self.v4.is_none() && self.v6.is_none()
+    }
+
+    /// Returns the address shown as the public IP, preferring IPv4.
+    pub fn primary(&self) -> Option<&str> {
+        self.v4.as_deref().or(self.v6.as_deref())
+    }
+
+    /// Returns the address to publish in a record of the provided type.
+    pub fn for_record_type(&self, dns_type: &str) -> Option<&str> {
+        match dns_type {
+            "A" => self.v4.as_deref(),
+            "AAAA" => self.v6.as_deref(),
+            _ => self.primary(),
+        }
+    }
+}
+
+/// Returns whether the provided address is routable on the internet, rejecting loopback,
+/// private, link-local and other special-purpose addresses.
+pub fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(a) => !(a.is_loopback() || a.is_private() || a.is_link_local() || a.is_unspecified()
+            || a.is_multicast() || a.is_broadcast() || a.is_documentation()
+            // Shared address space used by carrier-grade NAT.
+            || (a.octets()[0] == 100 && (a.octets()[1] & 0xc0) == 64)),
+        IpAddr::V6(a) => !(a.is_loopback() || a.is_unspecified() || a.is_multicast()
+            // Unique local and link-local addresses.
+            || (a.segments()[0] & 0xfe00) == 0xfc00
+            || (a.segments()[0] & 0xffc0) == 0xfe80),
+    }
+}
+
+/// Gets the current public IP address from the provided IP echo service.
+pub async fn get_current_public_ip(client: &Client, url: &str) -> Result<String, FailureKind> {
+    let body = client.get(url)
+        .send()
+        .await;
+
+    let response = match body {
+        Ok(r) => r,
+        Err(e) => {
+            warn!("Issue trying to get current IP: {:?}", e);
+            return Err(FailureKind::Network);
+        }
+    };
+
+    let status = response.status();
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        warn!("Rate limited by {} while trying to get current IP.", url);
+        return Err(FailureKind::RateLimit);
+    }
+
+    if !status.is_success() {
+        warn!("{} returned {} while trying to get current IP.", url, status);
+        return Err(FailureKind::Network);
+    }
+
+    match response.text().await {
+        Ok(v) => {
+            let ip = v.trim();
+
+            match ip.parse::<IpAddr>() {
+                Ok(_) => Ok(ip.to_string()),
+                Err(_) => {
+                    error!("{} did not return a valid IP address: {:?}", url, ip);
+                    Err(FailureKind::Network)
+                }
+            }
+        },
+        Err(e) => {
+            error!("Error deserializing current IP: {:?}", e);
+            Err(FailureKind::Network)
+        }
+    }
+}
+
+/// Gets the current public IP address of the provided family by asking a resolver.
+pub async fn get_current_public_ip_over_dns(url: &str, ipv6: bool) -> Result<String, FailureKind> {
+    let answers = match DnsQuery::parse(url, ipv6) {
+        Ok(query) => query.send(ipv6, Duration::from_secs(DNS_QUERY_TIMEOUT)).await,
+        Err(e) => Err(e),
+    };
+
+    match answers.as_deref().map(|a| a.first().map(|ip| ip.trim())) {
+        Ok(Some(ip)) if ip.parse::<IpAddr>().is_ok() => Ok(ip.to_string()),
+        Ok(ip) => {
+            error!("{} did not return a valid IP address: {:?}", url, ip);
+            Err(FailureKind::Network)
+        },
+        Err(e) => {
+            warn!("Issue trying to get current IP from {}: {}", url, e);
+            Err(FailureKind::Network)
+        }
+    }
+}
+
+/// Gets the public IP from the provided IP echo service, failing if it is not of the
+/// provided family.
+pub async fn get_public_ip_of_family(client: &Client, url: &str, ipv6: bool) -> Result<String, FailureKind> {
+    let ip = match Transport::of(url) {
+        Ok(Transport::Dns) => get_current_public_ip_over_dns(url, ipv6).await?,
+        _ => get_current_public_ip(client, url).await?,
+    };
+
+    match ip.parse::<IpAddr>() {
+        Ok(a) if a.is_ipv6() == ipv6 => Ok(ip),
+        _ => {
+            warn!("{} returned {}, which is not an IPv{} address.", url, ip, if ipv6 { 6 } else { 4 });
+            Err(FailureKind::Network)
+        },
+    }
+}
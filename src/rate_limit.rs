@@ -0,0 +1,56 @@
+use log::debug;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Token bucket keeping requests within a quota of `requests` per `period`.
+///
+/// The bucket holds a tenth of the quota, and refills at a rate leaving room for that burst,
+/// so that no window of `period` can exceed the quota.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests: u64, period: Duration) -> RateLimiter {
+        let capacity = (requests / 10).max(1);
+        let refill = requests.saturating_sub(capacity).max(1);
+
+        RateLimiter {
+            capacity: capacity as f64,
+            refill_per_second: refill as f64 / period.as_secs_f64().max(f64::EPSILON),
+            bucket: Mutex::new(Bucket { tokens: capacity as f64, last_refill: Instant::now() }),
+        }
+    }
+
+    /// Waits until a request can be sent without exceeding the quota.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+
+                bucket.tokens = (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_second)
+            };
+
+            debug!("Cloudflare request quota reached, waiting {}ms.", wait.as_millis());
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::config::{Config, ConfigError};
+use crate::constants::*;
+use crate::failure::FailureKind;
+use crate::state::StateError;
+
+/// Context sent along with a failure report.
+pub struct FailureReport<'a> {
+    pub kind: FailureKind,
+    pub consecutive_failures: u64,
+    pub records: Vec<String>,
+    pub provider: Option<&'a str>,
+    pub recent_errors: &'a [StateError],
+}
+
+/// Keeps the error reporting client alive, sending pending reports when dropped.
+pub struct ReportingGuard {
+    #[cfg(feature = "sentry")]
+    _guard: Option<sentry::ClientInitGuard>,
+}
+
+/// Starts reporting panics and failures to Sentry if `sentry.dsn` is set.
+#[cfg(feature = "sentry")]
+pub fn init(config: &Config) -> Result<ReportingGuard, ConfigError> {
+    let dsn = config.sentry.as_ref().unwrap().dsn.as_ref().unwrap();
+    if dsn == DEFAULT_NOT_SET {
+        return Ok(ReportingGuard { _guard: None });
+    }
+
+    let dsn = dsn.parse::<sentry::types::Dsn>()
+        .map_err(|e| ConfigError::Invalid { key: "sentry.dsn", reason: e.to_string() })?;
+
+    let mut options = sentry::ClientOptions::default();
+    options.dsn = Some(dsn);
+    options.release = sentry::release_name!();
+
+    let guard = sentry::init(options);
+
+    log::debug!("Reporting errors to Sentry.");
+    Ok(ReportingGuard { _guard: Some(guard) })
+}
+
+#[cfg(not(feature = "sentry"))]
+pub fn init(config: &Config) -> Result<ReportingGuard, ConfigError> {
+    if config.sentry.as_ref().unwrap().dsn.as_ref().unwrap() == DEFAULT_NOT_SET {
+        return Ok(ReportingGuard {});
+    }
+
+    Err(ConfigError::Invalid {
+        key: "sentry.dsn",
+        reason: "only supported when built with the sentry feature".to_string(),
+    })
+}
+
+/// Reports repeated failed checks.
+#[cfg(feature = "sentry")]
+pub fn report_failure(report: &FailureReport) {
+    let recent_errors: Vec<&str> = report.recent_errors.iter().map(|e| e.message.as_str()).collect();
+
+    sentry::with_scope(|scope| {
+        scope.set_tag("failure_kind", format!("{:?}", report.kind));
+        scope.set_tag("exit_code", report.kind.exit_code());
+        if let Some(provider) = report.provider {
+            scope.set_tag("ip_provider", provider);
+        }
+        scope.set_extra("records", serde_json::json!(report.records));
+        scope.set_extra("recent_errors", serde_json::json!(recent_errors));
+    }, || {
+        sentry::capture_message(
+            format!("{} consecutive failed checks ({:?})", report.consecutive_failures, report.kind).as_str(),
+            sentry::Level::Error)
+    });
+}
+
+#[cfg(not(feature = "sentry"))]
+pub fn report_failure(_report: &FailureReport) {}
+
+/// Sends pending reports, before the process exits.
+#[cfg(feature = "sentry")]
+pub fn flush() {
+    if let Some(client) = sentry::Hub::current().client() {
+        client.flush(Some(std::time::Duration::from_secs(SENTRY_FLUSH_TIMEOUT)));
+    }
+}
+
+#[cfg(not(feature = "sentry"))]
+pub fn flush() {}
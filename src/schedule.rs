@@ -0,0 +1,59 @@
+use chrono::{Local, TimeZone};
+use croner::Cron;
+use log::warn;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+use crate::config::ConfigError;
+use crate::constants::*;
+
+/// When a record is checked, either at a fixed interval or on a cron schedule in local time.
+pub enum Schedule {
+    Interval(Duration),
+    Cron(Box<Cron>),
+}
+
+impl Schedule {
+    /// Uses the cron expression if one is set, otherwise the interval in seconds.
+    pub fn new(wait_duration: u64, schedule: &str) -> Result<Schedule, ConfigError> {
+        if schedule == DEFAULT_NOT_SET {
+            return Ok(Schedule::Interval(Duration::from_secs(wait_duration)));
+        }
+
+        Cron::new(schedule)
+            .parse()
+            .map(|c| Schedule::Cron(Box::new(c)))
+            .map_err(|e| ConfigError::Invalid { key: "schedule", reason: e.to_string() })
+    }
+
+    /// Returns how long to wait from the provided time until the next check, randomized
+    /// within `jitter` either way.
+    pub fn next_wait_jittered(&self, now_utc: OffsetDateTime, jitter: Duration) -> Duration {
+        let wait = self.next_wait(now_utc);
+
+        if jitter.is_zero() {
+            return wait;
+        }
+
+        let offset = Duration::from_millis(fastrand::u64(0..=jitter.as_millis() as u64 * 2));
+        (wait + offset).saturating_sub(jitter)
+    }
+
+    /// Returns how long to wait from the provided time until the next check.
+    pub fn next_wait(&self, now_utc: OffsetDateTime) -> Duration {
+        let cron = match self {
+            Schedule::Interval(d) => return *d,
+            Schedule::Cron(c) => c,
+        };
+
+        let now = Local.timestamp_nanos(now_utc.unix_timestamp_nanos() as i64);
+
+        match cron.find_next_occurrence(&now, false) {
+            Ok(next) => (next - now).to_std().unwrap_or(Duration::ZERO),
+            Err(e) => {
+                warn!("Unable to find the next time matching `{}`, retrying in {}s: {:?}", cron.as_str(), DEFAULT_WAIT_TIME, e);
+                Duration::from_secs(DEFAULT_WAIT_TIME)
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use thiserror::Error;
+
+use crate::constants::*;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("No key is available to decrypt the API token. Set `cloudflare.api_token_key_file` or the {} environment variable.", TOKEN_PASSPHRASE_ENV)]
+    NoKey,
+    #[error("Unable to read the API token credential {path}: {source}")]
+    Credential {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Unable to read the token key file {path}: {source}")]
+    KeyFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("The encrypted API token is malformed.")]
+    Malformed,
+    #[error("Unable to decrypt the API token, the key or passphrase is wrong.")]
+    WrongKey,
+    #[error("Unable to get the API token from `{command}`: {reason}")]
+    Command {
+        command: String,
+        reason: String,
+    },
+    #[error("Unable to access the API token in the keyring: {0}")]
+    Keyring(#[from] keyring::Error),
+}
+
+/// Returns whether the configured token is stored encrypted.
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_TOKEN_PREFIX)
+}
+
+/// Returns the key material from the key file if configured, otherwise from the passphrase
+/// environment variable.
+pub fn load_key(key_file: &str) -> Result<Vec<u8>, SecretError> {
+    if key_file != DEFAULT_NOT_SET {
+        let path = PathBuf::from(key_file);
+
+        return match fs::read(&path) {
+            Ok(key) => Ok(key.trim_ascii().to_vec()),
+            Err(source) => Err(SecretError::KeyFile { path, source }),
+        };
+    }
+
+    match std::env::var(TOKEN_PASSPHRASE_ENV) {
+        Ok(passphrase) if !passphrase.is_empty() => Ok(passphrase.into_bytes()),
+        _ => Err(SecretError::NoKey),
+    }
+}
+
+/// Returns the API token passed by systemd with `LoadCredential=`, if `$CREDENTIALS_DIRECTORY`
+/// is set and holds the credential.
+pub fn read_credential_token() -> Result<Option<String>, SecretError> {
+    let Some(dir) = std::env::var_os(CREDENTIALS_DIRECTORY_ENV) else {
+        return Ok(None);
+    };
+
+    let path = PathBuf::from(dir).join(API_TOKEN_CREDENTIAL);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(token) => Ok(Some(token.trim().to_string())),
+        Err(source) => Err(SecretError::Credential { path, source }),
+    }
+}
+
+/// Encrypts the token, returning the value to store in the configuration.
+pub fn encrypt_token(token: &str, key: &[u8]) -> String {
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher(key, &salt)
+        .encrypt(&nonce, token.as_bytes())
+        .expect("Encrypting in memory cannot fail.");
+
+    let mut blob = salt.to_vec();
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+
+    format!("{}{}", ENCRYPTED_TOKEN_PREFIX, STANDARD.encode(blob))
+}
+
+/// Decrypts a value produced by `encrypt_token`.
+pub fn decrypt_token(value: &str, key: &[u8]) -> Result<String, SecretError> {
+    let blob = value.strip_prefix(ENCRYPTED_TOKEN_PREFIX)
+        .and_then(|v| STANDARD.decode(v).ok())
+        .filter(|b| b.len() > SALT_LENGTH + NONCE_LENGTH)
+        .ok_or(SecretError::Malformed)?;
+
+    let (salt, rest) = blob.split_at(SALT_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+
+    let token = cipher(key, salt)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| SecretError::WrongKey)?;
+
+    String::from_utf8(token).map_err(|_| SecretError::Malformed)
+}
+
+/// Reads the API token from the platform keyring.
+pub fn get_keyring_token() -> Result<String, SecretError> {
+    Ok(keyring_entry()?.get_password()?)
+}
+
+/// Stores the API token in the platform keyring.
+pub fn set_keyring_token(token: &str) -> Result<(), SecretError> {
+    Ok(keyring_entry()?.set_password(token)?)
+}
+
+fn keyring_entry() -> Result<keyring::Entry, SecretError> {
+    Ok(keyring::Entry::new(CONFIG_FOLDER_NAME, KEYRING_TOKEN_USER)?)
+}
+
+/// Runs the provided shell command, returning the first line it prints as the API token.
+pub fn run_token_command(command: &str) -> Result<String, SecretError> {
+    let error = |reason: String| SecretError::Command { command: command.to_string(), reason };
+
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    };
+
+    let output = output.map_err(|e| error(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(error(format!("{} {}", output.status, stderr.trim())));
+    }
+
+    let stdout = String::from_utf8(output.stdout).map_err(|_| error("the output is not valid UTF-8".to_string()))?;
+
+    match stdout.lines().next().map(str::trim) {
+        Some(token) if !token.is_empty() => Ok(token.to_string()),
+        _ => Err(error("the command printed nothing".to_string())),
+    }
+}
+
+/// Derives the cipher from the key material and salt.
+fn cipher(key: &[u8], salt: &[u8]) -> ChaCha20Poly1305 {
+    let mut derived = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(key, salt, &mut derived)
+        .expect("Salt and output lengths are valid.");
+
+    ChaCha20Poly1305::new(Key::from_slice(&derived))
+}
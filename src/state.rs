@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::{debug, warn};
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+
+use crate::constants::*;
+use crate::failure::FailureKind;
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct State {
+    pub public_ip: Option<String>,
+    #[serde(default)]
+    pub records: Vec<RecordState>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_checked: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_changed: Option<OffsetDateTime>,
+    pub recent_errors: Vec<StateError>,
+    #[serde(default)]
+    pub history: Vec<IpChange>,
+    #[serde(default)]
+    pub stats: Stats,
+    /// Results of each IP provider since the updater started.
+    #[serde(default)]
+    pub providers: Vec<ProviderStats>,
+    /// Failures in a row and pending retries, kept so that restarting does not reset them.
+    #[serde(default)]
+    pub backoff: BackoffState,
+    #[serde(default)]
+    pub requests_today: RequestBudget,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct RecordState {
+    pub id: String,
+    pub name: Option<String>,
+    pub content: Option<String>,
+    /// Whether the record is proxied, so that its name resolves to Cloudflare instead.
+    #[serde(default)]
+    pub proxied: bool,
+    /// Failed attempts at updating the record since the last successful update.
+    #[serde(default)]
+    pub update_attempts: u32,
+    /// When the record is checked again, while retrying after a failure.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub retry_at: Option<OffsetDateTime>,
+    /// Public IP the record does not point to, while only observing the records.
+    #[serde(default)]
+    pub drift: Option<String>,
+}
+
+/// Requests sent during the current UTC day, against the budget of the low-bandwidth mode.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestBudget {
+    pub date: Option<String>,
+    pub used: u64,
+    /// Requests allowed per day, zero without a budget.
+    pub limit: u64,
+}
+
+impl RequestBudget {
+    pub fn is_exhausted(&self) -> bool {
+        self.limit > 0 && self.used >= self.limit
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackoffState {
+    pub consecutive_failures: u64,
+    /// Checks in a row which detected no public IP at all.
+    pub ip_check_attempts: u32,
+    pub streaks: Vec<FailureStreak>,
+}
+
+/// Failures in a row of a part of the check.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureStreak {
+    pub operation: String,
+    pub failures: u64,
+    pub escalated: bool,
+}
+
+/// Counters kept across restarts of the updater.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub checks: u64,
+    pub updates: u64,
+    pub failures: FailureCounts,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub started: Option<OffsetDateTime>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct FailureCounts {
+    pub network: u64,
+    pub auth: u64,
+    pub rate_limit: u64,
+    pub api: u64,
+    #[serde(default)]
+    pub mismatch: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderStats {
+    pub url: String,
+    pub successes: u64,
+    pub failures: u64,
+    pub latency_ms: Option<u64>,
+}
+
+impl ProviderStats {
+    /// Returns the share of requests which succeeded, if any was sent.
+    pub fn success_rate(&self) -> Option<f64> {
+        match self.successes + self.failures {
+            0 => None,
+            total => Some(self.successes as f64 / total as f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateError {
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpChange {
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    #[serde(default)]
+    pub record: Option<String>,
+    pub from: Option<String>,
+    pub to: String,
+}
+
+impl State {
+    /// Loads the state file, returning `None` if it does not exist or cannot be read.
+    pub fn load() -> Option<State> {
+        let filepath = State::get_state_file_path();
+
+        if !filepath.exists() {
+            debug!("No state file exists at {}.", filepath.display());
+            return None;
+        }
+
+        let contents = match fs::read_to_string(&filepath) {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Unable to read state file {}: {:?}", filepath.display(), e);
+                return None;
+            }
+        };
+
+        match serde_json::from_str::<State>(contents.as_str()) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                warn!("Unable to parse state file {}: {:?}", filepath.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Writes the state file, logging any issue instead of failing.
+    pub fn save(&self) {
+        let filepath = State::get_state_file_path();
+
+        let result = fs::create_dir_all(State::get_state_dir())
+            .and_then(|_| fs::write(&filepath, serde_json::to_string_pretty(self).unwrap()));
+
+        if let Err(e) = result {
+            warn!("Unable to write state file {}: {:?}", filepath.display(), e);
+        }
+    }
+
+    /// Records an error, only keeping the most recent ones.
+    pub fn push_error(&mut self, timestamp: OffsetDateTime, message: &str) {
+        self.recent_errors.push(StateError {
+            timestamp,
+            message: message.to_string(),
+        });
+
+        if self.recent_errors.len() > MAX_RECENT_ERRORS {
+            let excess = self.recent_errors.len() - MAX_RECENT_ERRORS;
+            self.recent_errors.drain(..excess);
+        }
+    }
+
+    /// Returns the state of the provided record, adding it if it is not tracked yet.
+    pub fn record_mut(&mut self, id: &str) -> &mut RecordState {
+        let index = match self.records.iter().position(|r| r.id == id) {
+            Some(i) => i,
+            None => {
+                self.records.push(RecordState { id: id.to_string(), ..Default::default() });
+                self.records.len() - 1
+            }
+        };
+
+        &mut self.records[index]
+    }
+
+    /// Records an IP change of the provided record, only keeping the most recent ones.
+    pub fn push_change(&mut self, timestamp: OffsetDateTime, record: &str, from: Option<String>, to: &str) {
+        self.history.push(IpChange {
+            timestamp,
+            record: Some(record.to_string()),
+            from,
+            to: to.to_string(),
+        });
+        self.last_changed = Some(timestamp);
+
+        if self.history.len() > MAX_HISTORY_ENTRIES {
+            let excess = self.history.len() - MAX_HISTORY_ENTRIES;
+            self.history.drain(..excess);
+        }
+    }
+
+    /// Returns the path of the state file.
+    pub fn get_state_file_path() -> PathBuf {
+        State::get_state_dir().join(STATE_FILE_NAME)
+    }
+
+    /// Returns the state directory.
+    pub fn get_state_dir() -> PathBuf {
+        match dirs::data_local_dir() {
+            Some(dir) => {
+                dir.join(Path::new(CONFIG_FOLDER_NAME))
+            },
+            None => {
+                dirs::home_dir()
+                    .expect("Cannot get data folder or home directory.")
+                    .join(format!(".{}", CONFIG_FOLDER_NAME))
+            }
+        }
+    }
+}
+
+impl Stats {
+    pub fn record_failure(&mut self, kind: FailureKind) {
+        match kind {
+            FailureKind::Network => self.failures.network += 1,
+            FailureKind::Auth => self.failures.auth += 1,
+            FailureKind::RateLimit => self.failures.rate_limit += 1,
+            FailureKind::Api => self.failures.api += 1,
+            FailureKind::Mismatch => self.failures.mismatch += 1,
+        }
+    }
+
+    /// Returns how long the updater has been running, if it is.
+    pub fn uptime(&self, now: OffsetDateTime) -> Option<Duration> {
+        self.started.map(|t| now - t)
+    }
+}
+
+/// Formats an optional timestamp for display.
+pub fn format_timestamp(timestamp: Option<OffsetDateTime>) -> String {
+    match timestamp {
+        Some(t) => t.format(&Rfc3339).unwrap_or_else(|_| t.to_string()),
+        None => "never".to_string(),
+    }
+}
@@ -0,0 +1,164 @@
+use crate::config::{Config, ConfigError};
+use crate::constants::*;
+use crate::failure::FailureKind;
+
+#[cfg(feature = "otlp")]
+use opentelemetry::metrics::{Counter, Histogram};
+#[cfg(feature = "otlp")]
+use opentelemetry::trace::{Span, Status, Tracer};
+#[cfg(feature = "otlp")]
+use opentelemetry::{global, KeyValue};
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::trace::SdkTracerProvider;
+#[cfg(feature = "otlp")]
+use std::sync::OnceLock;
+#[cfg(feature = "otlp")]
+use std::time::Instant;
+
+#[cfg(feature = "otlp")]
+static PROVIDERS: OnceLock<(SdkTracerProvider, SdkMeterProvider)> = OnceLock::new();
+
+#[cfg(feature = "otlp")]
+struct Instruments {
+    checks: Counter<u64>,
+    updates: Counter<u64>,
+    failures: Counter<u64>,
+    duration: Histogram<f64>,
+}
+
+/// Starts exporting traces and metrics over OTLP/HTTP if `telemetry.otlp_endpoint` is set.
+///
+/// Must be called before the async runtime is started, the exporters run on their own threads.
+#[cfg(feature = "otlp")]
+pub fn init(config: &Config) -> Result<(), ConfigError> {
+    use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::Resource;
+
+    let telemetry_config = config.telemetry.as_ref().unwrap();
+    let endpoint = telemetry_config.otlp_endpoint.as_ref().unwrap();
+    if endpoint == DEFAULT_NOT_SET {
+        return Ok(());
+    }
+
+    let invalid = |e: opentelemetry_otlp::ExporterBuildError| ConfigError::Invalid {
+        key: "telemetry.otlp_endpoint",
+        reason: e.to_string(),
+    };
+    let endpoint = endpoint.trim_end_matches('/');
+    let resource = Resource::builder()
+        .with_service_name(telemetry_config.service_name.clone().unwrap())
+        .build();
+
+    let span_exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint))
+        .build()
+        .map_err(invalid)?;
+    let metric_exporter = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/metrics", endpoint))
+        .build()
+        .map_err(invalid)?;
+
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(span_exporter)
+        .with_resource(resource.clone())
+        .build();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(metric_exporter)
+        .with_resource(resource)
+        .build();
+
+    global::set_tracer_provider(tracer_provider.clone());
+    global::set_meter_provider(meter_provider.clone());
+    let _ = PROVIDERS.set((tracer_provider, meter_provider));
+
+    log::debug!("Exporting telemetry to {}.", endpoint);
+    Ok(())
+}
+
+#[cfg(not(feature = "otlp"))]
+pub fn init(config: &Config) -> Result<(), ConfigError> {
+    if config.telemetry.as_ref().unwrap().otlp_endpoint.as_ref().unwrap() == DEFAULT_NOT_SET {
+        return Ok(());
+    }
+
+    Err(ConfigError::Invalid {
+        key: "telemetry.otlp_endpoint",
+        reason: "only supported when built with the otlp feature".to_string(),
+    })
+}
+
+/// Exports pending spans and metrics, before the process exits.
+pub fn flush() {
+    #[cfg(feature = "otlp")]
+    if let Some((tracer_provider, meter_provider)) = PROVIDERS.get() {
+        let _ = tracer_provider.force_flush();
+        let _ = meter_provider.force_flush();
+    }
+}
+
+#[cfg(feature = "otlp")]
+fn instruments() -> &'static Instruments {
+    static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+
+    INSTRUMENTS.get_or_init(|| {
+        let meter = global::meter(TELEMETRY_SCOPE);
+
+        Instruments {
+            checks: meter.u64_counter("cloudflare_ddns.checks").with_description("Public IP checks performed.").build(),
+            updates: meter.u64_counter("cloudflare_ddns.updates").with_description("DNS records updated.").build(),
+            failures: meter.u64_counter("cloudflare_ddns.failures").with_description("Failed checks by category.").build(),
+            duration: meter.f64_histogram("cloudflare_ddns.check.duration").with_unit("s").with_description("Duration of checks.").build(),
+        }
+    })
+}
+
+/// Span covering a single check, recording its outcome once finished.
+pub struct CheckSpan {
+    #[cfg(feature = "otlp")]
+    span: global::BoxedSpan,
+    #[cfg(feature = "otlp")]
+    started: Instant,
+}
+
+/// Starts the span of a check of the provided number of records.
+#[cfg(feature = "otlp")]
+pub fn start_check(records: usize) -> CheckSpan {
+    let mut span = global::tracer(TELEMETRY_SCOPE).start("check");
+    span.set_attribute(KeyValue::new("records", records as i64));
+
+    CheckSpan { span, started: Instant::now() }
+}
+
+#[cfg(not(feature = "otlp"))]
+pub fn start_check(_records: usize) -> CheckSpan {
+    CheckSpan {}
+}
+
+impl CheckSpan {
+    /// Ends the span with the result of the check and the number of records it updated.
+    #[cfg(feature = "otlp")]
+    pub fn finish(mut self, result: Result<(), FailureKind>, updates: u64) {
+        let instruments = instruments();
+        instruments.checks.add(1, &[]);
+        instruments.updates.add(updates, &[]);
+        instruments.duration.record(self.started.elapsed().as_secs_f64(), &[]);
+
+        self.span.set_attribute(KeyValue::new("updates", updates as i64));
+        match result {
+            Ok(()) => self.span.set_status(Status::Ok),
+            Err(kind) => {
+                instruments.failures.add(1, &[KeyValue::new("kind", kind.as_str())]);
+                self.span.set_status(Status::error(kind.as_str()));
+            }
+        }
+
+        self.span.end();
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    pub fn finish(self, _result: Result<(), FailureKind>, _updates: u64) {}
+}
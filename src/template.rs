@@ -0,0 +1,41 @@
+/// Replaces the `{hostname}` and `{env:NAME}` placeholders in the provided value with the
+/// short host name and the value of the environment variable.
+pub fn render(value: &str) -> Result<String, String> {
+    let mut rendered = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}')
+            .map(|e| start + e)
+            .ok_or_else(|| format!("unclosed placeholder in `{}`", value))?;
+
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&resolve(&rest[start + 1..end])?);
+        rest = &rest[end + 1..];
+    }
+
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+fn resolve(placeholder: &str) -> Result<String, String> {
+    if placeholder == "hostname" {
+        return hostname();
+    }
+
+    match placeholder.strip_prefix("env:") {
+        Some(name) => std::env::var(name)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| format!("the {} environment variable is not set", name)),
+        None => Err(format!("unknown placeholder `{{{}}}`", placeholder)),
+    }
+}
+
+/// Returns the host name up to its first dot, in lowercase.
+pub fn hostname() -> Result<String, String> {
+    let hostname = hostname::get().map_err(|e| format!("unable to get the host name: {}", e))?;
+    let hostname = hostname.to_string_lossy();
+
+    Ok(hostname.split('.').next().unwrap_or_default().to_lowercase())
+}
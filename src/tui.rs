@@ -0,0 +1,220 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+use crate::config::{Config, ConfigOverrides};
+use crate::constants::*;
+use crate::control::ControlHandle;
+use crate::state;
+
+pub struct LogEntry {
+    pub timestamp: OffsetDateTime,
+    pub level: Level,
+    pub message: String,
+}
+
+/// Logger buffering records for the event log, writing to stderr until the dashboard is shown.
+pub struct TuiLogger {
+    level: LevelFilter,
+    active: AtomicBool,
+    entries: Mutex<VecDeque<LogEntry>>,
+}
+
+impl TuiLogger {
+    /// Installs the logger, honoring `RUST_LOG` like the default logger does.
+    pub fn init() -> &'static TuiLogger {
+        let level = std::env::var("RUST_LOG").ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(LevelFilter::Info);
+
+        let logger: &'static TuiLogger = Box::leak(Box::new(TuiLogger {
+            level,
+            active: AtomicBool::new(false),
+            entries: Mutex::new(VecDeque::new()),
+        }));
+
+        log::set_logger(logger).unwrap();
+        log::set_max_level(level);
+
+        logger
+    }
+}
+
+impl Log for TuiLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        if !self.active.load(Ordering::SeqCst) {
+            eprintln!("{:<5} [{}] {}", record.level(), record.target(), record.args());
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(LogEntry {
+            timestamp: OffsetDateTime::now_utc(),
+            level: record.level(),
+            message: record.args().to_string(),
+        });
+
+        if entries.len() > MAX_TUI_LOG_ENTRIES {
+            entries.pop_front();
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Runs the updater while showing a live dashboard, until the user quits.
+pub async fn run(logger: &'static TuiLogger, config: Config, overrides: ConfigOverrides, handle: Arc<ControlHandle>, pid_file: Option<PathBuf>) {
+    let record_ids: Vec<String> = config.records().into_iter()
+        .map(|r| r.dns_record_id.unwrap())
+        .collect();
+
+    logger.active.store(true, Ordering::SeqCst);
+
+    let ui_handle = handle.clone();
+    let ui = tokio::task::spawn_blocking(move || {
+        let mut terminal = ratatui::init();
+        let result = run_ui(&mut terminal, logger, &ui_handle, &record_ids);
+        ratatui::restore();
+        result
+    });
+
+    tokio::select! {
+        _ = crate::updater::run_updater(config, overrides, handle, None, pid_file) => {},
+        result = ui => {
+            if let Ok(Err(e)) = result {
+                eprintln!("Dashboard stopped unexpectedly: {:?}", e);
+            }
+        },
+    }
+
+    logger.active.store(false, Ordering::SeqCst);
+}
+
+fn run_ui(terminal: &mut DefaultTerminal, logger: &TuiLogger, handle: &ControlHandle, record_ids: &[String]) -> Result<(), std::io::Error> {
+    loop {
+        terminal.draw(|frame| draw(frame, logger, handle, record_ids))?;
+
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') => {
+                    handle.execute("check-now");
+                },
+                KeyCode::Char('p') => {
+                    handle.execute(if handle.is_paused() { "resume" } else { "pause" });
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, logger: &TuiLogger, handle: &ControlHandle, record_ids: &[String]) {
+    let [status_area, records_area, events_area, help_area] = Layout::vertical([
+        Constraint::Length(6),
+        Constraint::Length(3 + record_ids.len() as u16),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+
+    let status = handle.status();
+    let next_check = match *handle.next_check.lock().unwrap() {
+        Some(t) => format!("{}s", t.saturating_duration_since(Instant::now()).as_secs()),
+        None => "now".to_string(),
+    };
+
+    let status_lines = vec![
+        Line::from(vec![Span::raw("Public IP:     "), Span::raw(status.state.public_ip.clone().unwrap_or("unknown".to_string()))]),
+        Line::from(vec![Span::raw("Updater:       "), if status.paused {
+            Span::styled("paused", Style::default().fg(Color::Yellow))
+        } else {
+            Span::styled("running", Style::default().fg(Color::Green))
+        }]),
+        Line::from(format!("Next check in: {}", next_check)),
+        Line::from(format!("Last changed:  {}", state::format_timestamp(status.state.last_changed))),
+    ];
+
+    frame.render_widget(
+        Paragraph::new(status_lines).block(Block::default().borders(Borders::ALL).title(" Status ")),
+        status_area);
+
+    let rows: Vec<Row> = record_ids.iter()
+        .map(|id| {
+            let record = status.state.records.iter().find(|r| &r.id == id);
+            let content = record.and_then(|r| r.content.clone());
+            let proxied = record.is_some_and(|r| r.proxied);
+
+            let (sync_label, sync_color) = match (&status.state.public_ip, &content) {
+                (Some(ip), Some(content)) if ip == content => ("in sync", Color::Green),
+                (Some(_), Some(_)) => ("out of sync", Color::Red),
+                _ => ("unknown", Color::DarkGray),
+            };
+
+            Row::new(vec![
+                record.and_then(|r| r.name.clone()).unwrap_or(id.to_string()),
+                content.unwrap_or("unknown".to_string()) + if proxied { " (proxied)" } else { "" },
+                sync_label.to_string(),
+            ]).style(Style::default().fg(sync_color))
+        })
+        .collect();
+
+    let records = Table::new(rows, [Constraint::Percentage(50), Constraint::Percentage(30), Constraint::Percentage(20)])
+        .header(Row::new(vec!["NAME", "CONTENT", "STATUS"]))
+        .block(Block::default().borders(Borders::ALL).title(" Records "));
+
+    frame.render_widget(records, records_area);
+
+    let entries = logger.entries.lock().unwrap();
+    let visible = events_area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = entries.iter()
+        .skip(entries.len().saturating_sub(visible))
+        .map(|e| {
+            let color = match e.level {
+                Level::Error => Color::Red,
+                Level::Warn => Color::Yellow,
+                Level::Info => Color::Reset,
+                _ => Color::DarkGray,
+            };
+
+            ListItem::new(Line::from(vec![
+                Span::styled(state::format_timestamp(Some(e.timestamp)), Style::default().fg(Color::DarkGray)),
+                Span::raw(" "),
+                Span::styled(format!("{:<5}", e.level), Style::default().fg(color)),
+                Span::raw(" "),
+                Span::raw(e.message.clone()),
+            ]))
+        })
+        .collect();
+
+    frame.render_widget(
+        List::new(items).block(Block::default().borders(Borders::ALL).title(" Events ")),
+        events_area);
+
+    frame.render_widget(Paragraph::new(" q: quit  c: check now  p: pause/resume"), help_area);
+}
@@ -0,0 +1,100 @@
+use chrono::{Local, NaiveTime, TimeZone, Timelike};
+use std::time::Duration;
+use time::OffsetDateTime;
+
+use crate::config::ConfigError;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Range of the day in local time, written as `02:00-02:30`, which may span midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    start: u32,
+    end: u32,
+}
+
+impl TimeWindow {
+    pub fn parse(window: &str) -> Result<TimeWindow, String> {
+        let (start, end) = window.split_once('-')
+            .ok_or_else(|| format!("`{}` is not a range such as `02:00-02:30`", window))?;
+
+        Ok(TimeWindow { start: parse_minute(start)?, end: parse_minute(end)? })
+    }
+
+    /// Returns whether the window contains the provided minute of the day. The window
+    /// includes its start but not its end.
+    fn contains(&self, minute: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+/// Parses `HH:MM` into minutes since midnight.
+fn parse_minute(time: &str) -> Result<u32, String> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M")
+        .map(|t| t.hour() * 60 + t.minute())
+        .map_err(|e| format!("`{}` is not a time such as `02:30`: {}", time.trim(), e))
+}
+
+/// Times of the day at which records may be updated: outside every blocked window, and
+/// within one of the allowed windows if any is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateWindows {
+    allowed: Vec<TimeWindow>,
+    blocked: Vec<TimeWindow>,
+}
+
+impl UpdateWindows {
+    pub fn new(allowed: &[String], blocked: &[String]) -> Result<UpdateWindows, ConfigError> {
+        let parse = |windows: &[String], key| windows.iter()
+            .map(|w| TimeWindow::parse(w).map_err(|reason| ConfigError::Invalid { key, reason }))
+            .collect::<Result<Vec<TimeWindow>, ConfigError>>();
+
+        let windows = UpdateWindows {
+            allowed: parse(allowed, "update_windows.allowed")?,
+            blocked: parse(blocked, "update_windows.blocked")?,
+        };
+
+        if !(0..MINUTES_PER_DAY).any(|m| windows.allows_minute(m)) {
+            return Err(ConfigError::Invalid {
+                key: "update_windows.blocked",
+                reason: "leaves no time of the day to update the records".to_string(),
+            });
+        }
+
+        Ok(windows)
+    }
+
+    fn allows_minute(&self, minute: u32) -> bool {
+        !self.blocked.iter().any(|w| w.contains(minute))
+            && (self.allowed.is_empty() || self.allowed.iter().any(|w| w.contains(minute)))
+    }
+
+    /// Returns whether records may be updated at the provided local time.
+    pub fn allows(&self, time: NaiveTime) -> bool {
+        self.allows_minute(time.hour() * 60 + time.minute())
+    }
+
+    /// Returns how long from the provided local time until records may be updated, zero if
+    /// they may be now.
+    pub fn wait_until_open(&self, time: NaiveTime) -> Duration {
+        let minute = time.hour() * 60 + time.minute();
+
+        if self.allows_minute(minute) {
+            return Duration::ZERO;
+        }
+
+        (1..MINUTES_PER_DAY)
+            .find(|offset| self.allows_minute((minute + offset) % MINUTES_PER_DAY))
+            .map(|offset| Duration::from_secs((offset * 60 - time.second()) as u64))
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+/// Returns the local time of the day at the provided time.
+pub fn local_time(now_utc: OffsetDateTime) -> NaiveTime {
+    Local.timestamp_nanos(now_utc.unix_timestamp_nanos() as i64).time()
+}
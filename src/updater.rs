@@ -0,0 +1,1897 @@
+use futures::stream::{self, StreamExt};
+use log::{debug, error, info, warn};
+use reqwest::{Client, Url};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use time::format_description::well_known::Rfc3339;
+
+use crate::backoff::Backoff;
+use crate::clock::{Clock, SystemClock};
+use crate::cloudflare_api::{CloudflareBatchPut, CloudflareBatchRequest, CloudflareDnsRecord, CloudflareDnsResult, CloudflareResponse};
+use crate::cloudflare_client::CloudflareClient;
+use crate::config::{Config, ConfigError, ConfigOverrides, GrpcConfig, NotificationsConfig, ProviderKind, RecordConfig};
+use crate::constants::*;
+#[cfg(unix)]
+use crate::control;
+use crate::control::ControlHandle;
+use crate::dns_precheck::DnsPrecheck;
+use crate::dns_provider::DnsProvider;
+use crate::events::{EventStream, LifecycleEvent};
+use crate::exec_provider::ExecProvider;
+use crate::failure::{FailureKind, FailureStreaks, Operation};
+use crate::geoip::{GeoIp, IpInfo};
+use crate::http_api;
+#[cfg(feature = "grpc")]
+use crate::grpc;
+use crate::http_client;
+use crate::interface::{self, Ipv6Selection};
+use crate::ip_file;
+use crate::ip_provider::{self, IpProvider};
+use crate::ipv6;
+use crate::notification::{Event, EventKind, Notifications, Notifier, Route};
+use crate::privileges;
+use crate::public_ip::{self, AddressPolicy, DetectionStrategy, IpSource, PublicIps, Transport};
+use crate::reporting::{self, FailureReport};
+use crate::schedule::Schedule;
+use crate::state::{BackoffState, FailureStreak, RequestBudget, State};
+use crate::telemetry;
+use crate::template;
+use crate::update_window::{self, UpdateWindows};
+
+/// DNS record kept in sync on its own schedule.
+struct TrackedRecord {
+    dns_record_id: String,
+    zone_id: String,
+    /// Index of the Cloudflare client holding the API token of the record.
+    client: usize,
+    /// Index of the selector that matched the record, if it was not configured by ID.
+    selector: Option<usize>,
+    schedule: Schedule,
+    refresh_interval: Duration,
+    /// Suffix and prefix length composing the published IPv6 address from the detected one.
+    ipv6_suffix: Option<(Ipv6Addr, u8)>,
+    /// Whether a companion TXT record is kept, and its ID once found or created.
+    txt_record: bool,
+    txt_record_id: Option<String>,
+    /// TTL and proxy status applied when the record is updated, keeping the current ones if unset.
+    ttl: Option<i64>,
+    proxied: Option<bool>,
+    /// Group the record is listed in, for routing its notifications.
+    group: Option<String>,
+    current: Option<CloudflareResponse<CloudflareDnsResult>>,
+    fetched_at: Option<Instant>,
+    next_check: Instant,
+    /// Failed attempts at updating the record since the last successful update.
+    update_attempts: u32,
+    /// Whether the user was told the DNS pre-check does not apply to the record as it is proxied.
+    proxied_warned: bool,
+}
+
+impl TrackedRecord {
+    fn new(config: &RecordConfig, dns_record_id: &str, client: usize, selector: Option<usize>, next_check: Instant) -> Result<TrackedRecord, ConfigError> {
+        let prefix_length = config.ipv6_prefix_length.unwrap();
+        if prefix_length > 128 {
+            return Err(ConfigError::Invalid { key: "records.ipv6_prefix_length", reason: "must be at most 128".to_string() });
+        }
+
+        let ipv6_suffix = config.ipv6_suffix.as_deref()
+            .map(|s| ipv6::parse_suffix(s).map(|s| (s, prefix_length)))
+            .transpose()
+            .map_err(|reason| ConfigError::Invalid { key: "records.ipv6_suffix", reason })?;
+
+        Ok(TrackedRecord {
+            dns_record_id: dns_record_id.to_string(),
+            zone_id: config.zone_id.clone().unwrap(),
+            client,
+            selector,
+            schedule: Schedule::new(config.wait_duration.unwrap(), config.schedule.as_ref().unwrap())?,
+            refresh_interval: Duration::from_secs(config.refresh_interval.unwrap()),
+            ipv6_suffix,
+            txt_record: config.txt_record.unwrap(),
+            txt_record_id: None,
+            ttl: config.ttl,
+            proxied: config.proxied,
+            group: config.group.clone(),
+            current: None,
+            fetched_at: None,
+            next_check,
+            update_attempts: 0,
+            proxied_warned: false,
+        })
+    }
+
+    /// Returns the content to publish for the detected public IPs, or `None` if the address
+    /// of the family of the record was not detected.
+    fn content_for(&self, public_ips: &PublicIps, dns_type: &str) -> Option<String> {
+        match self.ipv6_suffix {
+            Some((suffix, prefix_length)) => public_ips.v6.as_deref()
+                .and_then(|ip| ip.parse::<Ipv6Addr>().ok())
+                .map(|prefix| ipv6::combine(prefix, prefix_length, suffix).to_string()),
+            None => public_ips.for_record_type(dns_type).map(str::to_string),
+        }
+    }
+
+    /// Returns whether the cached record should be fetched again from Cloudflare.
+    fn needs_fetch(&self, now: Instant) -> bool {
+        match (&self.current, self.fetched_at) {
+            (Some(_), Some(t)) => !self.refresh_interval.is_zero() && now >= t + self.refresh_interval,
+            _ => true,
+        }
+    }
+}
+
+/// Returns whether the contents are the same, comparing addresses regardless of their notation.
+fn same_content(a: &str, b: &str) -> bool {
+    match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Returns whether the TXT record was written by the updater, carrying its comment or its
+/// metadata.
+fn is_companion_txt(record: &CloudflareDnsResult) -> bool {
+    record.comment.as_deref() == Some(MANAGED_RECORD_COMMENT)
+        || record.content.trim_matches('"').starts_with(COMPANION_TXT_PREFIX)
+}
+
+/// Records updated by a request, or why the request failed.
+type UpdateOutcome = Result<Vec<(usize, CloudflareResponse<CloudflareDnsResult>)>, FailureKind>;
+
+/// Change a dry run would have sent to Cloudflare.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlannedChange {
+    /// Whether the record would be created rather than updated.
+    pub create: bool,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub dns_type: String,
+    /// Current content of the record, if it exists.
+    pub before: Option<String>,
+    pub after: String,
+    pub ttl: i64,
+    pub proxied: bool,
+}
+
+impl fmt::Display for PlannedChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.before {
+            Some(before) => write!(f, "~ {} {} {} -> {}", self.name, self.dns_type, before, self.after)?,
+            None => write!(f, "+ {} {} {}", self.name, self.dns_type, self.after)?,
+        }
+
+        write!(f, " (ttl {}{})", self.ttl, if self.proxied { ", proxied" } else { "" })
+    }
+}
+
+/// Name pattern or marker selecting the records of a zone to keep in sync.
+struct RecordSelector {
+    config: RecordConfig,
+    client: usize,
+}
+
+/// Keeps the configured DNS records in sync with the public IP, one check at a time.
+pub struct Updater<C: Clock> {
+    clock: Arc<C>,
+    handle: Arc<ControlHandle>,
+    client: Client,
+    /// Where the records are kept, shared by the records with the same API token.
+    dns_providers: Vec<DnsProvider>,
+    ip_providers: Vec<IpProvider>,
+    ipv6_providers: Vec<IpProvider>,
+    /// File the public IP is read from instead of asking the providers.
+    ip_file: Option<PathBuf>,
+    /// Interface whose addresses are published instead of asking the providers.
+    interface: Option<(String, Ipv6Selection)>,
+    address_policy: AddressPolicy,
+    detection_strategy: DetectionStrategy,
+    provider_failure_threshold: u64,
+    provider_cooldown: Duration,
+    /// How long a detected public IP is reused instead of asking the providers again.
+    ip_cache_ttl: Duration,
+    cached_ips: Option<(PublicIps, Instant)>,
+    /// How often every provider is asked at once to rank them, with the latency strategy.
+    probe_interval: Duration,
+    next_probe: Instant,
+    records: Vec<TrackedRecord>,
+    selectors: Vec<RecordSelector>,
+    selection_refresh: Duration,
+    next_selection: Instant,
+    wait_jitter: Duration,
+    max_consecutive_failures: u64,
+    /// Maximum number of Cloudflare requests sent at once during a check.
+    update_concurrency: usize,
+    update_windows: UpdateWindows,
+    /// Whether drift between the records and the public IP is only reported, never fixed.
+    observe_only: bool,
+    /// Longest wait between checks in low-bandwidth mode, which doubles the wait after every
+    /// check changing nothing.
+    max_wait: Option<Duration>,
+    stable_checks: u32,
+    /// Requests allowed per day, zero without a budget.
+    daily_request_budget: u64,
+    /// Requests recorded in the metrics at the last check, to count the ones sent since.
+    requests_counted: u64,
+    /// Delays before checking again after failing to detect the public IP, or to update a record.
+    ip_check_backoff: Backoff,
+    cloudflare_backoff: Backoff,
+    /// Checks in a row which detected no public IP at all.
+    ip_check_attempts: u32,
+    heartbeat_interval: u64,
+    /// File touched after every successful check, for container health checks.
+    health_file: Option<PathBuf>,
+    /// Uptime Kuma push monitor told about every successful check.
+    push_url: Option<Url>,
+    report_failure_threshold: u64,
+    notifications: Notifications,
+    /// Failures of each part of a check, escalated and notified at the failure threshold.
+    failure_streaks: FailureStreaks,
+    geoip: Option<GeoIp>,
+    /// Resolver asked first, skipping the Cloudflare API while records resolve to the public IP.
+    dns_precheck: Option<DnsPrecheck>,
+    /// Locations of the addresses being published, looked up before updating the records.
+    locations: HashMap<String, IpInfo>,
+    last_failed_provider: Option<String>,
+    /// Stream lifecycle events are written to, if requested.
+    events: Option<EventStream>,
+    /// Changes collected instead of being sent, while planning.
+    planned: Option<Vec<PlannedChange>>,
+    state: State,
+    consecutive_failures: u64,
+    iterations: u64,
+}
+
+impl<C: Clock> Updater<C> {
+    pub fn new(config: &Config, handle: Arc<ControlHandle>, clock: Arc<C>) -> Result<Updater<C>, ConfigError> {
+        let general_config = config.general.as_ref().unwrap();
+        let cloudflare_config = config.cloudflare.as_ref().unwrap();
+        let ip_check_config = config.ip_check.as_ref().unwrap();
+        let notifications_config = config.notifications.as_ref().unwrap();
+        let retry_config = config.retry.as_ref().unwrap();
+        let update_windows_config = config.update_windows.as_ref().unwrap();
+        let low_bandwidth_config = config.low_bandwidth.as_ref().unwrap();
+        let low_bandwidth = low_bandwidth_config.enabled.unwrap();
+        let requests_counted = handle.requests.total();
+
+        debug!("Initializing reqwest client.");
+        let client = http_client::build_for_ip_check(config)?;
+        let cloudflare_http_client = http_client::build(config)?;
+        let mut dns_providers: Vec<DnsProvider> = Vec::new();
+        let mut api_tokens: Vec<String> = Vec::new();
+        let provider_config = config.provider.as_ref().unwrap();
+        let plugin = (provider_config.kind == Some(ProviderKind::Exec))
+            .then(|| ExecProvider::new(provider_config.command.as_ref().unwrap()));
+        let state = {
+            let mut state = handle.state.lock().unwrap();
+            state.stats.started = Some(clock.now_utc());
+            state.clone()
+        };
+
+        let now = clock.now();
+        let now_utc = clock.now_utc();
+        let wait_jitter = Duration::from_secs(general_config.wait_jitter.unwrap());
+        let mut records = Vec::new();
+        let mut selectors = Vec::new();
+
+        for r in config.records() {
+            // Records sharing an API token share its client and request quota.
+            let api_token = r.api_token.clone().unwrap();
+            let client = match api_tokens.iter().position(|t| *t == api_token) {
+                Some(i) => i,
+                None => {
+                    dns_providers.push(match plugin.as_ref() {
+                        Some(p) => DnsProvider::Exec(p.clone()),
+                        None => {
+                            let client = CloudflareClient::new(cloudflare_http_client.clone(), api_token.as_str())
+                                .with_metrics(handle.requests.clone())
+                                .with_rate_limit(cloudflare_config.rate_limit_requests.unwrap(),
+                                    Duration::from_secs(cloudflare_config.rate_limit_period.unwrap()));
+
+                            DnsProvider::Cloudflare(if low_bandwidth { client.with_conditional_requests() } else { client })
+                        },
+                    });
+                    api_tokens.push(api_token);
+                    api_tokens.len() - 1
+                }
+            };
+
+            if r.is_selector() {
+                TrackedRecord::new(&r, "", client, None, now)?;
+                selectors.push(RecordSelector { config: r, client });
+            } else {
+                let mut record = TrackedRecord::new(&r, r.dns_record_id.as_ref().unwrap(), client, None, now)?;
+                record.next_check = now + record.schedule.next_wait_jittered(now_utc, wait_jitter);
+                records.push(record);
+            }
+        }
+
+        let mut notifiers = notifications_config.urls.as_ref().unwrap().iter()
+            .map(|u| Notifier::parse(u).map(Route::new).map_err(|reason| ConfigError::Invalid { key: "notifications.urls", reason }))
+            .collect::<Result<Vec<Route>, ConfigError>>()?;
+
+        for route in notifications_config.routes.as_ref().unwrap() {
+            let notifier = Notifier::parse(&route.url)
+                .map_err(|reason| ConfigError::Invalid { key: "notifications.routes.url", reason })?;
+            notifiers.push(Route { events: Some(route.events.clone()), ..Route::new(notifier) });
+        }
+
+        for group in config.groups.as_ref().unwrap() {
+            for url in group.notification_urls.iter().flatten() {
+                let notifier = Notifier::parse(url)
+                    .map_err(|reason| ConfigError::Invalid { key: "groups.notification_urls", reason })?;
+                notifiers.push(Route {
+                    notifier,
+                    events: Some(vec![EventKind::IpChanged, EventKind::ProviderChanged, EventKind::Drift]),
+                    group: group.name.clone(),
+                });
+            }
+        }
+
+        let push_url = match config.uptime_kuma.as_ref().unwrap().push_url.as_ref().unwrap() {
+            u if u == DEFAULT_NOT_SET => None,
+            u => Some(Url::parse(u).map_err(|e| ConfigError::Invalid { key: "uptime_kuma.push_url", reason: e.to_string() })?),
+        };
+
+        let geoip_config = config.geoip.as_ref().unwrap();
+        let geoip = match (geoip_config.mmdb_file.as_ref().unwrap(), geoip_config.api_url.as_ref().unwrap()) {
+            (f, u) if f == DEFAULT_NOT_SET && u == DEFAULT_NOT_SET => None,
+            (f, u) if f != DEFAULT_NOT_SET && u != DEFAULT_NOT_SET => return Err(ConfigError::Invalid {
+                key: "geoip.api_url",
+                reason: "cannot be set together with `geoip.mmdb_file`".to_string(),
+            }),
+            (f, _) if f != DEFAULT_NOT_SET => Some(GeoIp::mmdb(Path::new(f))?),
+            (_, u) => Some(GeoIp::api(cloudflare_http_client.clone(), u)),
+        };
+
+        let dns_precheck_config = config.dns_precheck.as_ref().unwrap();
+        let dns_precheck = dns_precheck_config.enabled.unwrap()
+            .then(|| DnsPrecheck::new(cloudflare_http_client.clone(), dns_precheck_config.doh_url.as_ref().unwrap()));
+
+        let mut notifications = Notifications::routed(cloudflare_http_client, notifiers)
+            .with_rate_limit(notifications_config.rate_limit.unwrap());
+
+        if notifications_config.digest.unwrap() {
+            notifications = notifications.with_digest(Duration::from_secs(notifications_config.digest_interval.unwrap()));
+        }
+
+        let mut updater = Updater {
+            clock,
+            handle,
+            client,
+            dns_providers,
+            ip_providers: ip_providers(ip_check_config.urls.as_ref().unwrap(), &DEFAULT_DNS_IP_CHECK_URLS, low_bandwidth),
+            ipv6_providers: ip_providers(ip_check_config.ipv6_urls.as_ref().unwrap(), &DEFAULT_DNS_IPV6_CHECK_URLS, low_bandwidth),
+            ip_file: (ip_check_config.ip_source.unwrap() == IpSource::File)
+                .then(|| PathBuf::from(ip_check_config.ip_file.clone().unwrap())),
+            interface: (ip_check_config.ip_source.unwrap() == IpSource::Interface)
+                .then(|| (ip_check_config.interface.clone().unwrap(), ip_check_config.ipv6_selection.unwrap())),
+            address_policy: ip_check_config.address_policy.unwrap(),
+            detection_strategy: ip_check_config.strategy.unwrap(),
+            provider_failure_threshold: ip_check_config.failure_threshold.unwrap(),
+            provider_cooldown: Duration::from_secs(ip_check_config.cooldown.unwrap()),
+            ip_cache_ttl: Duration::from_secs(ip_check_config.cache_ttl.unwrap()),
+            cached_ips: None,
+            probe_interval: Duration::from_secs(ip_check_config.probe_interval.unwrap()),
+            next_probe: now,
+            records,
+            selectors,
+            selection_refresh: Duration::from_secs(general_config.selection_refresh.unwrap()),
+            next_selection: now,
+            wait_jitter,
+            max_consecutive_failures: general_config.max_consecutive_failures.unwrap(),
+            ip_check_backoff: retry_config.ip_check.as_ref().unwrap().backoff(),
+            cloudflare_backoff: retry_config.cloudflare.as_ref().unwrap().backoff(),
+            ip_check_attempts: 0,
+            update_concurrency: general_config.update_concurrency.unwrap().max(1),
+            observe_only: general_config.observe_only.unwrap(),
+            max_wait: low_bandwidth.then(|| Duration::from_secs(low_bandwidth_config.max_wait_duration.unwrap())),
+            stable_checks: 0,
+            daily_request_budget: if low_bandwidth { low_bandwidth_config.daily_request_budget.unwrap() } else { 0 },
+            requests_counted,
+            update_windows: UpdateWindows::new(update_windows_config.allowed.as_ref().unwrap(),
+                update_windows_config.blocked.as_ref().unwrap())?,
+            heartbeat_interval: general_config.heartbeat_interval.unwrap(),
+            health_file: Some(general_config.health_file.clone().unwrap())
+                .filter(|f| f != DEFAULT_NOT_SET)
+                .map(PathBuf::from),
+            push_url,
+            report_failure_threshold: config.sentry.as_ref().unwrap().failure_threshold.unwrap(),
+            notifications,
+            failure_streaks: failure_streaks(notifications_config),
+            geoip,
+            dns_precheck,
+            locations: HashMap::new(),
+            last_failed_provider: None,
+            events: None,
+            planned: None,
+            state,
+            consecutive_failures: 0,
+            iterations: 0,
+        };
+
+        updater.restore_backoff();
+        Ok(updater)
+    }
+
+    /// Continues the failures in a row and pending retries of the previous run, so that an
+    /// updater restarting while failing keeps backing off instead of starting over.
+    fn restore_backoff(&mut self) {
+        let now = self.clock.now();
+        let now_utc = self.clock.now_utc();
+        let backoff = &self.state.backoff;
+        self.consecutive_failures = backoff.consecutive_failures;
+        self.ip_check_attempts = backoff.ip_check_attempts;
+
+        for streak in &backoff.streaks {
+            if let Some(operation) = Operation::ALL.into_iter().find(|o| o.as_str() == streak.operation) {
+                self.failure_streaks.restore(operation, streak.failures, streak.escalated);
+            }
+        }
+
+        for record in self.records.iter_mut() {
+            let Some(record_state) = self.state.records.iter().find(|r| r.id == record.dns_record_id) else {
+                continue;
+            };
+
+            record.update_attempts = record_state.update_attempts;
+
+            if let Some(retry_at) = record_state.retry_at {
+                let wait = Duration::try_from(retry_at - now_utc).unwrap_or(Duration::ZERO);
+                record.next_check = record.next_check.min(now + wait);
+            }
+        }
+    }
+
+    /// Keeps the failures in a row and pending retries in the state, for `restore_backoff`.
+    fn save_backoff(&mut self) {
+        let now = self.clock.now();
+        let now_utc = self.clock.now_utc();
+
+        self.state.backoff = BackoffState {
+            consecutive_failures: self.consecutive_failures,
+            ip_check_attempts: self.ip_check_attempts,
+            streaks: Operation::ALL.into_iter()
+                .filter(|&o| self.failure_streaks.get(o) > 0)
+                .map(|o| FailureStreak {
+                    operation: o.as_str().to_string(),
+                    failures: self.failure_streaks.get(o),
+                    escalated: self.failure_streaks.is_escalated(o),
+                })
+                .collect(),
+        };
+
+        for record in &self.records {
+            let retrying = record.update_attempts > 0 || self.ip_check_attempts > 0;
+            let tracked = self.state.records.iter().any(|r| r.id == record.dns_record_id);
+
+            if !retrying && !tracked {
+                continue;
+            }
+
+            let record_state = self.state.record_mut(record.dns_record_id.as_str());
+            record_state.update_attempts = record.update_attempts;
+            record_state.retry_at = retrying.then(|| now_utc + record.next_check.saturating_duration_since(now));
+        }
+    }
+
+    /// Loads the configuration file again and continues with it. The current configuration is
+    /// kept if the new one is invalid. The listeners, logging and privileges keep the settings
+    /// they were started with.
+    pub fn reload(&mut self, overrides: &ConfigOverrides) {
+        let result = Config::load(overrides)
+            .and_then(|config| Updater::new(&config, self.handle.clone(), self.clock.clone()));
+
+        match result {
+            Ok(mut updater) => {
+                info!("Configuration reloaded.");
+                updater.state.stats.started = self.state.stats.started;
+                updater.events = self.events.take();
+                *self = updater;
+            },
+            Err(e) => {
+                let message = format!("Keeping the current configuration, the new one is invalid: {}", e);
+                warn!("{}", message);
+                self.state.push_error(self.clock.now_utc(), &message);
+            }
+        }
+
+        self.handle.update_state(&self.state);
+    }
+
+    /// Writes lifecycle events to the provided stream.
+    pub fn with_event_stream(mut self, events: EventStream) -> Updater<C> {
+        self.events = Some(events);
+        self
+    }
+
+    /// Sends the Cloudflare requests of every record through the provided client.
+    pub fn with_cloudflare_client(mut self, cloudflare_client: CloudflareClient) -> Updater<C> {
+        for provider in self.dns_providers.iter_mut() {
+            *provider = DnsProvider::Cloudflare(cloudflare_client.clone());
+        }
+        self
+    }
+
+    /// Sends Cloudflare requests to the provided base URL, keeping the API token of each record.
+    pub fn with_cloudflare_base_url(mut self, base_url: &str) -> Updater<C> {
+        self.dns_providers = self.dns_providers.into_iter()
+            .map(|p| match p {
+                DnsProvider::Cloudflare(c) => DnsProvider::Cloudflare(c.with_base_url(base_url)),
+                plugin => plugin,
+            })
+            .collect();
+        self
+    }
+
+    /// Detects the public IP using only the provided IP echo service.
+    pub fn with_ip_check_url(self, ip_check_url: &str) -> Updater<C> {
+        self.with_ip_check_urls(&[ip_check_url])
+    }
+
+    /// Detects the public IP using the provided IP echo services, in order.
+    pub fn with_ip_check_urls(mut self, ip_check_urls: &[&str]) -> Updater<C> {
+        self.ip_providers = ip_check_urls.iter().map(|u| IpProvider::new(u)).collect();
+        self
+    }
+
+    /// Detects the public IPv6 address using the provided IP echo services, in order.
+    pub fn with_ipv6_check_urls(mut self, ip_check_urls: &[&str]) -> Updater<C> {
+        self.ipv6_providers = ip_check_urls.iter().map(|u| IpProvider::new(u)).collect();
+        self
+    }
+
+    /// Waits until a record is due, then checks the due records. Returns an error once too
+    /// many consecutive checks have failed.
+    pub async fn tick(&mut self) -> Result<(), FailureKind> {
+        let woken = self.wait_for_next_check().await;
+        self.check_due(woken).await
+    }
+
+    /// Checks the records which are due, or every record when an immediate check was
+    /// requested.
+    async fn check_due(&mut self, woken: bool) -> Result<(), FailureKind> {
+        if !self.selectors.is_empty() && self.clock.now() >= self.next_selection {
+            self.refresh_selection().await;
+            self.next_selection = self.clock.now() + self.selection_refresh;
+        }
+
+        let now = self.clock.now();
+        let now_utc = self.clock.now_utc();
+        let due: Vec<usize> = (0..self.records.len())
+            .filter(|&i| woken || self.records[i].next_check <= now)
+            .collect();
+
+        for &i in &due {
+            let wait = self.records[i].schedule.next_wait_jittered(now_utc, self.wait_jitter);
+            self.records[i].next_check = now + self.stretch_wait(wait);
+        }
+
+        self.iterations += 1;
+        self.log_heartbeat();
+
+        debug!("Starting iteration.");
+        self.emit(LifecycleEvent::CheckStarted { records: due.len() });
+        let span = telemetry::start_check(due.len());
+        let updates = self.state.stats.updates;
+        let result = self.check_records(&due).await;
+        span.finish(result, self.state.stats.updates - updates);
+
+        let stable = result.is_ok() && self.state.stats.updates == updates;
+        self.stable_checks = if stable { self.stable_checks.saturating_add(1) } else { 0 };
+
+        match result {
+            Ok(()) => {
+                self.touch_health_file();
+                self.push_to_uptime_kuma().await;
+                Ok(())
+            },
+            Err(kind) => {
+                if self.consecutive_failures == self.report_failure_threshold {
+                    self.report_failure(kind, &due);
+                }
+
+                if self.max_consecutive_failures == 0 || self.consecutive_failures < self.max_consecutive_failures {
+                    return Ok(());
+                }
+
+                error!("Giving up after {} consecutive failures ({:?}).", self.consecutive_failures, kind);
+                Err(kind)
+            }
+        }
+    }
+
+    /// Lengthens the wait in low-bandwidth mode, doubling it after every check which changed
+    /// nothing, up to `low_bandwidth.max_wait_duration`. The longest wait is used once the
+    /// daily request budget is spent.
+    fn stretch_wait(&self, wait: Duration) -> Duration {
+        let Some(max_wait) = self.max_wait.map(|m| m.max(wait)) else {
+            return wait;
+        };
+
+        if self.state.requests_today.is_exhausted() {
+            return max_wait;
+        }
+
+        wait.saturating_mul(1 << self.stable_checks.min(16)).min(max_wait)
+    }
+
+    /// Adds the requests sent since the last check to the requests of the day.
+    fn count_requests(&mut self) {
+        let total = self.handle.requests.total();
+        let sent = total.saturating_sub(self.requests_counted);
+        self.requests_counted = total;
+
+        let today = self.clock.now_utc().date().to_string();
+        let budget = &mut self.state.requests_today;
+
+        if budget.date.as_deref() != Some(today.as_str()) {
+            *budget = RequestBudget { date: Some(today), ..Default::default() };
+        }
+
+        let exhausted = budget.is_exhausted();
+        budget.limit = self.daily_request_budget;
+        budget.used += sent;
+
+        if !exhausted && budget.is_exhausted() {
+            warn!("{} requests were sent today, reaching the daily budget of {}. Waiting the longest between checks until tomorrow.",
+                budget.used,
+                budget.limit);
+        }
+    }
+
+    /// Reports the failure of the provided records to the error reporting service.
+    fn report_failure(&self, kind: FailureKind, indices: &[usize]) {
+        let records = indices.iter()
+            .map(|&i| &self.records[i])
+            .filter(|r| r.current.is_none())
+            .map(|r| self.state.records.iter()
+                .find(|s| s.id == r.dns_record_id)
+                .and_then(|s| s.name.clone())
+                .unwrap_or_else(|| r.dns_record_id.clone()))
+            .collect();
+
+        reporting::report_failure(&FailureReport {
+            kind,
+            consecutive_failures: self.consecutive_failures,
+            records,
+            provider: self.last_failed_provider.as_deref(),
+            recent_errors: &self.state.recent_errors,
+        });
+    }
+
+    /// Writes the time of the successful check to the health file, updating its modification
+    /// time. Failures are only logged.
+    fn touch_health_file(&self) {
+        let Some(path) = self.health_file.as_ref() else {
+            return;
+        };
+
+        let now_utc = self.clock.now_utc().format(&Rfc3339).unwrap_or_default();
+        if let Err(e) = std::fs::write(path, format!("{}\n", now_utc)) {
+            warn!("Unable to write the health file {}: {}", path.display(), e);
+        }
+    }
+
+    /// Tells the Uptime Kuma push monitor about the successful check, with the public IP as
+    /// its message. Failures are only logged.
+    async fn push_to_uptime_kuma(&self) {
+        let Some(push_url) = self.push_url.as_ref() else {
+            return;
+        };
+
+        let mut url = push_url.clone();
+        let pairs: Vec<(String, String)> = push_url.query_pairs()
+            .filter(|(k, _)| k != "status" && k != "msg")
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+
+        url.query_pairs_mut()
+            .clear()
+            .extend_pairs(pairs)
+            .append_pair("status", "up")
+            .append_pair("msg", self.state.public_ip.as_deref().unwrap_or("OK"));
+
+        match self.notifications.client().get(url).send().await {
+            Ok(r) if r.status().is_success() => debug!("Pushed the check to Uptime Kuma."),
+            Ok(r) => warn!("Uptime Kuma returned {} to the push.", r.status()),
+            Err(e) => warn!("Unable to push the check to Uptime Kuma: {}", e),
+        }
+    }
+
+    /// Logs a summary every `heartbeat_interval` iterations, so that a quiet updater can be told
+    /// apart from a dead one.
+    fn log_heartbeat(&self) {
+        if self.heartbeat_interval == 0 || !self.iterations.is_multiple_of(self.heartbeat_interval) {
+            return;
+        }
+
+        let last_changed = match self.state.last_changed {
+            Some(t) => format!("last changed {}s ago", (self.clock.now_utc() - t).whole_seconds()),
+            None => "never changed".to_string(),
+        };
+
+        info!("Still running: {} checks performed, public IP {}, {}.",
+            self.state.stats.checks,
+            self.state.public_ip.as_deref().unwrap_or("unknown"),
+            last_changed);
+    }
+
+    /// Lists the records of the zone of every selector, tracking the records that started
+    /// matching and forgetting the ones that stopped. The selection is kept on errors.
+    /// Selectors allowed to create their record create it when nothing matches.
+    async fn refresh_selection(&mut self) {
+        let now = self.clock.now();
+        let mut listings: Vec<(&str, usize, Vec<CloudflareDnsResult>)> = Vec::new();
+        let mut missing = Vec::new();
+
+        for (s, selector) in self.selectors.iter().enumerate() {
+            let zone_id = selector.config.zone_id.as_ref().unwrap();
+            let pattern = selector.config.selection();
+
+            // Selectors sharing a zone and API token share its listing.
+            if !listings.iter().any(|(z, c, _)| z == zone_id && *c == selector.client) {
+                debug!("Listing the Cloudflare DNS records of zone {}.", zone_id);
+                match self.dns_providers[selector.client].list_dns_records(zone_id).await {
+                    Ok(r) => listings.push((zone_id, selector.client, r.result)),
+                    Err(_) => {
+                        warn!("Unable to list the DNS records matching {}, keeping the current selection.", pattern);
+                        continue;
+                    }
+                }
+            }
+
+            let (_, _, listed) = listings.iter().find(|(z, c, _)| z == zone_id && *c == selector.client).unwrap();
+            let matching: Vec<&CloudflareDnsResult> = listed.iter().filter(|r| selector.config.selects(r)).collect();
+
+            self.records.retain(|t| {
+                let keep = t.selector != Some(s) || matching.iter().any(|m| m.id == t.dns_record_id);
+                if !keep {
+                    info!("Record {} no longer matches {}, no longer updating it.", t.dns_record_id, pattern);
+                }
+                keep
+            });
+
+            if matching.is_empty() && selector.config.create.unwrap() {
+                missing.push((s, listed.first().map(|r| r.zone_name.clone()).unwrap_or_default()));
+            }
+
+            for m in matching {
+                if self.records.iter().any(|t| t.dns_record_id == m.id) {
+                    continue;
+                }
+
+                info!("Record {} matches {}, keeping it updated.", m.name, pattern);
+                if let Ok(record) = TrackedRecord::new(&selector.config, m.id.as_str(), selector.client, Some(s), now) {
+                    self.records.push(record);
+                }
+            }
+        }
+
+        for (s, zone_name) in missing {
+            if self.observe_only {
+                warn!("No record matches {}, not creating it while only observing.", self.selectors[s].config.selection());
+                continue;
+            }
+
+            if self.handle.is_paused() {
+                warn!("No record matches {}, not creating it while paused.", self.selectors[s].config.selection());
+                continue;
+            }
+
+            self.create_record(s, zone_name.as_str()).await;
+        }
+    }
+
+    /// Creates the record of the provided selector with the current public IP, then keeps it
+    /// updated. Nothing is created until an address of the family of the record is detected,
+    /// nor if the name is not within the zone, when its name is known.
+    async fn create_record(&mut self, selector: usize, zone_name: &str) {
+        let now = self.clock.now();
+        let config = self.selectors[selector].config.clone();
+        let client = self.selectors[selector].client;
+        let name = config.name.clone().unwrap();
+        let dns_type = config.record_type.clone().unwrap().to_uppercase();
+
+        let mut record = match TrackedRecord::new(&config, "", client, Some(selector), now) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        let (public_ips, _) = self.get_public_ips().await;
+        let content = match record.content_for(&public_ips, dns_type.as_str()) {
+            Some(c) => c,
+            None => {
+                warn!("Not creating the {} record of {} until its public IP is detected.", dns_type, name);
+                return;
+            }
+        };
+
+        let new_dns_record = CloudflareDnsRecord {
+            dns_type,
+            name: name.clone(),
+            content,
+            ttl: record.ttl.unwrap_or(CREATED_RECORD_TTL),
+            proxied: record.proxied.unwrap_or(false),
+            comment: Some(MANAGED_RECORD_COMMENT.to_string()),
+            tags: Vec::new(),
+        };
+
+        if let Err(e) = new_dns_record.validate(zone_name, MIN_RECORD_TTL) {
+            let message = format!("Not creating the {} record of {}, {}.", new_dns_record.dns_type, name, e);
+            error!("{}", message);
+            self.state.push_error(self.clock.now_utc(), &message);
+            return;
+        }
+
+        if let Some(planned) = self.planned.as_mut() {
+            planned.push(PlannedChange {
+                create: true,
+                name,
+                dns_type: new_dns_record.dns_type,
+                before: None,
+                after: new_dns_record.content,
+                ttl: new_dns_record.ttl,
+                proxied: new_dns_record.proxied,
+            });
+            return;
+        }
+
+        match self.dns_providers[client].create_dns_record(record.zone_id.as_str(), &new_dns_record).await {
+            Ok(r) => {
+                record.dns_record_id = r.result.id.clone();
+                record.current = Some(r);
+                record.fetched_at = Some(now);
+                self.records.push(record);
+            },
+            Err(_) => warn!("Unable to create the {} record of {}.", new_dns_record.dns_type, name),
+        }
+    }
+
+    /// Sleeps until the next record is due, or until an immediate check is requested.
+    /// Returns whether an immediate check was requested.
+    async fn wait_for_next_check(&self) -> bool {
+        let now = self.clock.now();
+        let next_selection = (!self.selectors.is_empty()).then_some(self.next_selection);
+        let next_check = self.records.iter().map(|r| r.next_check).chain(next_selection).min().unwrap_or(now);
+        let wait = next_check.saturating_duration_since(now);
+
+        debug!("Waiting {}s before next iteration.", wait.as_secs());
+        *self.handle.next_check.lock().unwrap() = Some(next_check);
+
+        let woken = tokio::select! {
+            _ = self.clock.sleep(wait) => false,
+            _ = self.handle.check_now.notified() => {
+                debug!("Woken up for an immediate check.");
+                true
+            },
+        };
+
+        *self.handle.next_check.lock().unwrap() = None;
+        woken
+    }
+
+    /// Checks every DNS record once without changing anything, returning the records which
+    /// would be created or updated.
+    pub async fn plan(&mut self) -> Result<Vec<PlannedChange>, FailureKind> {
+        self.planned = Some(Vec::new());
+
+        if !self.selectors.is_empty() {
+            self.refresh_selection().await;
+        }
+
+        let result = self.check().await;
+        let planned = self.planned.take().unwrap_or_default();
+        result.map(|()| planned)
+    }
+
+    /// Compares the public IP with every DNS record, updating the records that differ.
+    pub async fn check(&mut self) -> Result<(), FailureKind> {
+        let all: Vec<usize> = (0..self.records.len()).collect();
+        self.check_records(&all).await
+    }
+
+    /// Compares the public IP with the provided DNS records, updating the records that differ.
+    async fn check_records(&mut self, indices: &[usize]) -> Result<(), FailureKind> {
+        let mut failure = None;
+        let now = self.clock.now();
+        self.state.stats.checks += 1;
+
+        // Get the current public IP.
+        debug!("Getting the current public IP.");
+        let (public_ips, ip_failure) = self.get_public_ips().await;
+        let to_fetch = self.records_to_fetch(indices, &public_ips, now).await;
+
+        let (records, clients) = (&self.records, &self.dns_providers);
+        let results: Vec<(usize, Result<CloudflareResponse<CloudflareDnsResult>, FailureKind>)> = stream::iter(to_fetch)
+            .map(|i| async move {
+                let record = &records[i];
+                debug!("Getting the current Cloudflare DNS entry for {}.", record.dns_record_id);
+
+                let result = clients[record.client].get_dns_record(record.zone_id.as_str(),
+                    record.dns_record_id.as_str())
+                    .await;
+
+                (i, result)
+            })
+            .buffered(self.update_concurrency)
+            .collect()
+            .await;
+
+        let fetched = !results.is_empty();
+        let mut fetch_failure = None;
+
+        for (i, result) in results {
+            match result {
+                Ok(r) => {
+                    self.records[i].current = Some(r);
+                    self.records[i].fetched_at = Some(now);
+                },
+                Err(kind) => {
+                    self.invalidate(&[i], kind);
+                    fetch_failure = Some(kind);
+
+                    // Cloudflare may still be unreachable, keep retrying the pending update.
+                    if self.records[i].update_attempts > 0 {
+                        self.records[i].update_attempts += 1;
+                        self.schedule_retry(i);
+                    }
+                }
+            }
+        }
+
+        if fetched {
+            self.record_outcome(Operation::FetchRecords, fetch_failure);
+            failure = fetch_failure;
+        }
+
+        failure = ip_failure.or(failure);
+        self.record_outcome(Operation::DetectIp, ip_failure);
+
+        let now_utc = self.clock.now_utc();
+        self.state.last_checked = Some(now_utc);
+
+        if ip_failure.is_some() {
+            self.state.push_error(now_utc, "Unable to get the current public IP.");
+        }
+
+        if indices.iter().any(|&i| self.records[i].current.is_none()) {
+            self.state.push_error(now_utc, "Unable to get the current Cloudflare DNS record.");
+        }
+
+        if public_ips.is_empty() {
+            self.ip_check_attempts += 1;
+            self.schedule_ip_check_retry(indices);
+            return self.finish_check(Some(failure.unwrap_or(FailureKind::Network)));
+        }
+
+        self.ip_check_attempts = 0;
+
+        debug!("Current public IP: {:?}", public_ips);
+        self.emit(LifecycleEvent::IpDetected { ipv4: public_ips.v4.clone(), ipv6: public_ips.v6.clone() });
+        self.state.public_ip = public_ips.primary().map(str::to_string);
+
+        let mut pending: Vec<(usize, CloudflareDnsRecord)> = Vec::new();
+
+        for &i in indices {
+            match self.plan_update(i, &public_ips) {
+                Ok(Some(new_dns_record)) => pending.push((i, new_dns_record)),
+                Ok(None) => {},
+                Err(kind) => failure = Some(kind),
+            }
+        }
+
+        if self.planned.is_none() && !pending.is_empty() && self.defer_updates(&pending) {
+            pending.clear();
+        }
+
+        if self.planned.is_none() {
+            self.look_up_locations(&pending).await;
+        }
+
+        if !pending.is_empty() {
+            let update_failure = self.send_updates(pending).await.err();
+            self.record_outcome(Operation::UpdateRecords, update_failure);
+            failure = update_failure.or(failure);
+        }
+
+        self.finish_check(failure)
+    }
+
+    /// Returns the records to fetch from Cloudflare. With the DNS pre-check, cached records are
+    /// considered up to date while both their cached content and the resolver agree with the
+    /// public IP, and fetched otherwise. Without it, they are fetched when their cache expired.
+    /// Proxied records resolve to Cloudflare, so they always rely on the cache and the API.
+    async fn records_to_fetch(&mut self, indices: &[usize], public_ips: &PublicIps, now: Instant) -> Vec<usize> {
+        // Another mechanism updates the records being observed, so the cache cannot be trusted.
+        if self.observe_only {
+            return indices.to_vec();
+        }
+
+        let mut to_fetch = Vec::new();
+
+        for &i in indices {
+            let record = &self.records[i];
+            let (Some(dns_precheck), Some(current)) = (self.dns_precheck.as_ref(), record.current.as_ref()) else {
+                if record.needs_fetch(now) {
+                    to_fetch.push(i);
+                }
+                continue;
+            };
+
+            if current.result.proxied {
+                if !record.proxied_warned {
+                    warn!("{} is proxied and resolves to Cloudflare rather than {}, checking it through the Cloudflare API instead of `dns_precheck`.",
+                        current.result.name,
+                        current.result.content.trim());
+                    self.records[i].proxied_warned = true;
+                }
+
+                if self.records[i].needs_fetch(now) {
+                    to_fetch.push(i);
+                }
+                continue;
+            }
+
+            let Some(expected) = record.content_for(public_ips, current.result.dns_type.as_str()) else {
+                if record.needs_fetch(now) {
+                    to_fetch.push(i);
+                }
+                continue;
+            };
+
+            let name = current.result.name.as_str();
+            let cached = same_content(current.result.content.trim(), expected.as_str());
+
+            match dns_precheck.resolve(name, current.result.dns_type.as_str()).await {
+                Ok(resolved) if cached && resolved.iter().any(|r| same_content(r, expected.as_str())) => {
+                    debug!("{} already resolves to {}, not asking Cloudflare.", name, expected);
+                    self.records[i].fetched_at = Some(now);
+                },
+                Ok(resolved) => {
+                    debug!("{} resolves to {:?} rather than {}, asking Cloudflare.", name, resolved, expected);
+                    to_fetch.push(i);
+                },
+                Err(e) => {
+                    warn!("Unable to resolve {}: {}", name, e);
+                    if record.needs_fetch(now) {
+                        to_fetch.push(i);
+                    }
+                },
+            }
+        }
+
+        to_fetch
+    }
+
+    /// Looks up the locations of the addresses about to be published and of those they
+    /// replace, reusing those already known.
+    async fn look_up_locations(&mut self, pending: &[(usize, CloudflareDnsRecord)]) {
+        let Some(geoip) = self.geoip.as_ref() else {
+            return;
+        };
+
+        let mut locations = HashMap::new();
+        let addresses = pending.iter()
+            .flat_map(|(i, r)| [self.records[*i].current.as_ref().map(|c| c.result.content.trim()), Some(r.content.as_str())])
+            .flatten();
+
+        for address in addresses {
+            if locations.contains_key(address) {
+                continue;
+            }
+
+            let info = match (self.locations.remove(address), address.parse::<IpAddr>()) {
+                (Some(info), _) => Some(info),
+                (None, Ok(ip)) => geoip.lookup(ip).await,
+                (None, Err(_)) => None,
+            };
+
+            if let Some(info) = info {
+                locations.insert(address.to_string(), info);
+            }
+        }
+
+        self.locations = locations;
+    }
+
+    /// Holds the updates back while `update_windows` does not allow them, checking the records
+    /// again once it does. Returns whether the updates were held back.
+    fn defer_updates(&mut self, pending: &[(usize, CloudflareDnsRecord)]) -> bool {
+        let wait = self.update_windows.wait_until_open(update_window::local_time(self.clock.now_utc()));
+
+        if wait.is_zero() {
+            return false;
+        }
+
+        info!("Updates are not allowed now, updating {} records in {}s.", pending.len(), wait.as_secs());
+        let open_at = self.clock.now() + wait;
+
+        for &(i, _) in pending {
+            let record = &mut self.records[i];
+            record.next_check = record.next_check.min(open_at);
+        }
+
+        true
+    }
+
+    /// Checks the records again soon after failing to detect the public IP, following
+    /// `retry.ip_check`, rather than waiting for their next scheduled check.
+    fn schedule_ip_check_retry(&mut self, indices: &[usize]) {
+        let Some(delay) = self.ip_check_backoff.delay(self.ip_check_attempts) else {
+            return;
+        };
+
+        info!("Detecting the public IP again in {}s.", delay.as_secs());
+        let retry_at = self.clock.now() + delay;
+
+        for &i in indices {
+            let record = &mut self.records[i];
+            record.next_check = record.next_check.min(retry_at);
+        }
+    }
+
+    /// Counts the success or failure of a part of the check, notifying once it keeps failing.
+    fn record_outcome(&mut self, operation: Operation, failure: Option<FailureKind>) {
+        let Some(kind) = failure else {
+            self.failure_streaks.record_success(operation);
+            return;
+        };
+
+        let escalated = self.failure_streaks.record_failure(operation, kind);
+        self.emit(LifecycleEvent::Error {
+            operation: operation.as_str(),
+            kind: kind.as_str(),
+            message: format!("Unable to {}.", operation.describe()),
+            consecutive_failures: self.failure_streaks.get(operation),
+        });
+
+        if escalated {
+            self.notifications.notify(Event::Failure {
+                kind,
+                consecutive_failures: self.failure_streaks.get(operation),
+            });
+        }
+    }
+
+    fn emit(&self, event: LifecycleEvent) {
+        if let Some(events) = self.events.as_ref() {
+            events.emit(self.clock.now_utc(), &event);
+        }
+    }
+
+    /// Counts the failure of the check if any, then publishes the state.
+    fn finish_check(&mut self, failure: Option<FailureKind>) -> Result<(), FailureKind> {
+        match failure {
+            Some(kind) => {
+                self.state.stats.record_failure(kind);
+                self.consecutive_failures += 1;
+            },
+            None => self.consecutive_failures = 0,
+        }
+
+        self.count_requests();
+        self.save_backoff();
+        self.handle.update_state(&self.state);
+
+        match failure {
+            Some(kind) => Err(kind),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the new content of the provided DNS record if it differs from the public IP,
+    /// refusing to update it when only an address of the other family was detected.
+    fn plan_update(&mut self, index: usize, public_ips: &PublicIps) -> Result<Option<CloudflareDnsRecord>, FailureKind> {
+        let record = &self.records[index];
+        let Some(current_record) = record.current.as_ref() else {
+            return Ok(None);
+        };
+
+        debug!("Current Cloudflare DNS IP of {}: {}{}",
+            current_record.result.name,
+            current_record.result.content.trim(),
+            if current_record.result.proxied { " (proxied)" } else { "" });
+
+        let record_state = self.state.record_mut(record.dns_record_id.as_str());
+        record_state.name = Some(current_record.result.name.clone());
+        record_state.content = Some(current_record.result.content.trim().to_string());
+        record_state.proxied = current_record.result.proxied;
+
+        let current_public_ip = match record.content_for(public_ips, current_record.result.dns_type.as_str()) {
+            Some(ip) => ip,
+            // Both families were asked for, so the failure to detect one was already counted.
+            None if self.address_policy == AddressPolicy::Both => {
+                debug!("No public IP of the family of {} was detected, leaving it untouched.", current_record.result.name);
+                return Ok(None);
+            },
+            None => {
+                let message = format!("{} is an {} record but only an IPv{} address was detected, refusing to update it. Check `ip_check.address_policy`.",
+                    current_record.result.name,
+                    current_record.result.dns_type,
+                    if public_ips.v4.is_some() { 4 } else { 6 });
+
+                error!("{}", message);
+                self.state.push_error(self.clock.now_utc(), &message);
+                return Err(FailureKind::Mismatch);
+            }
+        };
+
+        // If the IPs match, then skip this record.
+        if same_content(&current_public_ip, current_record.result.content.trim()) {
+            debug!("IP addresses are the same.");
+
+            if self.state.record_mut(record.dns_record_id.as_str()).drift.take().is_some() {
+                info!("{} points to the public IP again.", current_record.result.name);
+            }
+
+            self.records[index].update_attempts = 0;
+            return Ok(None);
+        }
+
+        // While paused, keep monitoring without touching the record.
+        if self.handle.is_paused() {
+            info!("IP of {} changed from {} to {}. Not updating while paused.",
+                current_record.result.name,
+                current_record.result.content,
+                current_public_ip);
+
+            return Ok(None);
+        }
+
+        // While only observing, report the drift once instead of fixing it.
+        if self.observe_only {
+            let published = current_record.result.content.trim().to_string();
+            let previous = self.state.record_mut(record.dns_record_id.as_str()).drift.replace(current_public_ip.clone());
+
+            if previous.as_ref() != Some(&current_public_ip) {
+                let message = format!("{} points to {} but the public IP is {}.", current_record.result.name, published, current_public_ip);
+                warn!("{}", message);
+                self.state.push_error(self.clock.now_utc(), &message);
+                self.notifications.notify_group(Event::Drift {
+                    name: current_record.result.name.clone(),
+                    published,
+                    detected: current_public_ip,
+                }, record.group.as_deref());
+            }
+
+            return Ok(None);
+        }
+
+        // While planning, collect the change instead of sending it.
+        if let Some(planned) = self.planned.as_mut() {
+            planned.push(PlannedChange {
+                create: false,
+                name: current_record.result.name.clone(),
+                dns_type: current_record.result.dns_type.clone(),
+                before: Some(current_record.result.content.trim().to_string()),
+                after: current_public_ip,
+                ttl: record.ttl.unwrap_or(current_record.result.ttl),
+                proxied: record.proxied.unwrap_or(current_record.result.proxied),
+            });
+
+            return Ok(None);
+        }
+
+        // If the IPs do not match, then update the new IP with Cloudflare.
+        info!("IP of {} changed from {} to {}. Updating with Cloudflare.",
+            current_record.result.name,
+            current_record.result.content,
+            current_public_ip);
+
+        Ok(Some(CloudflareDnsRecord {
+            dns_type: current_record.result.dns_type.clone(),
+            name: current_record.result.name.clone(),
+            content: current_public_ip,
+            ttl: record.ttl.unwrap_or(current_record.result.ttl),
+            proxied: record.proxied.unwrap_or(current_record.result.proxied),
+            comment: current_record.result.comment.clone(),
+            tags: current_record.result.tags.clone(),
+        }))
+    }
+
+    /// Sends the pending updates, batching the records sharing a zone and API token into a
+    /// single request.
+    async fn send_updates(&mut self, pending: Vec<(usize, CloudflareDnsRecord)>) -> Result<(), FailureKind> {
+        let mut groups: Vec<Vec<(usize, CloudflareDnsRecord)>> = Vec::new();
+        let submitted: HashMap<usize, String> = pending.iter().map(|(i, r)| (*i, r.content.clone())).collect();
+
+        for (i, new_dns_record) in pending {
+            let record = &self.records[i];
+            let group = groups.iter_mut().find(|g| {
+                let first = &self.records[g[0].0];
+                first.zone_id == record.zone_id && first.client == record.client
+            });
+
+            match group {
+                Some(g) => g.push((i, new_dns_record)),
+                None => groups.push(vec![(i, new_dns_record)]),
+            }
+        }
+
+        let outcomes: Vec<(Vec<usize>, UpdateOutcome)> = stream::iter(groups)
+            .map(|g| self.send_group(g))
+            .buffered(self.update_concurrency)
+            .collect()
+            .await;
+
+        let mut failure = None;
+
+        for (indices, outcome) in outcomes {
+            match outcome {
+                Ok(updated) => {
+                    let mut unconfirmed: Vec<usize> = indices.iter()
+                        .filter(|i| !updated.iter().any(|(u, _)| u == *i))
+                        .copied()
+                        .collect();
+
+                    for (i, r) in updated {
+                        let content = r.result.content.trim();
+                        if !same_content(content, submitted[&i].as_str()) {
+                            error!("Cloudflare answered the update of {} with {} rather than {}.", r.result.name, content, submitted[&i]);
+                            unconfirmed.push(i);
+                            continue;
+                        }
+
+                        self.record_updated(i, r);
+
+                        if self.records[i].txt_record {
+                            self.update_companion_txt(i).await;
+                        }
+                    }
+
+                    // The records are fetched again to find out what was actually applied.
+                    if !unconfirmed.is_empty() {
+                        self.update_failed(&unconfirmed, FailureKind::Api);
+                        failure = Some(FailureKind::Api);
+                    }
+                },
+                Err(kind) => {
+                    self.update_failed(&indices, kind);
+                    failure = Some(kind);
+                },
+            }
+        }
+
+        match failure {
+            Some(kind) => Err(kind),
+            None => Ok(()),
+        }
+    }
+
+    /// Updates the records of a group, through the batch endpoint if there are several.
+    /// Returns the indices of the records along with the updated records.
+    async fn send_group(&self, mut group: Vec<(usize, CloudflareDnsRecord)>) -> (Vec<usize>, UpdateOutcome) {
+        let indices: Vec<usize> = group.iter().map(|(i, _)| *i).collect();
+        let record = &self.records[indices[0]];
+        let client = &self.dns_providers[record.client];
+
+        if group.len() == 1 {
+            let (i, new_dns_record) = group.remove(0);
+            let outcome = client.update_dns_record(record.zone_id.as_str(), record.dns_record_id.as_str(), &new_dns_record)
+                .await
+                .map(|r| vec![(i, r)]);
+
+            return (indices, outcome);
+        }
+
+        let batch = CloudflareBatchRequest {
+            puts: group.into_iter()
+                .map(|(i, r)| CloudflareBatchPut { id: self.records[i].dns_record_id.clone(), record: r })
+                .collect(),
+        };
+
+        debug!("Updating {} Cloudflare DNS records in a single batch.", batch.puts.len());
+        let outcome = client.batch_update_dns_records(record.zone_id.as_str(), &batch)
+            .await
+            .map(|response| response.result.puts.into_iter()
+                .filter_map(|put| {
+                    let i = *indices.iter().find(|&&i| self.records[i].dns_record_id == put.id)?;
+                    Some((i, CloudflareResponse { result: put, success: response.success, errors: Vec::new(), messages: Vec::new(), result_info: None }))
+                })
+                .collect());
+
+        (indices, outcome)
+    }
+
+    fn record_updated(&mut self, index: usize, response: CloudflareResponse<CloudflareDnsResult>) {
+        let now = self.clock.now();
+        let now_utc = self.clock.now_utc();
+        let record = &mut self.records[index];
+        let content = response.result.content.trim().to_string();
+
+        let location = self.locations.get(&content)
+            .map(IpInfo::to_string)
+            .filter(|l| !l.is_empty());
+
+        if let Some(previous) = record.current.as_ref() {
+            let changes = response.result.changes_from(&previous.result);
+            match location.as_ref() {
+                Some(location) if !changes.is_empty() =>
+                    info!("Updated {}: {} ({}).", response.result.name, changes.join(", "), location),
+                _ if !changes.is_empty() => info!("Updated {}: {}.", response.result.name, changes.join(", ")),
+                _ => {},
+            }
+        }
+
+        let previous_content = self.state.record_mut(record.dns_record_id.as_str()).content.replace(content.clone());
+        self.state.push_change(now_utc, response.result.name.as_str(), previous_content.clone(), content.as_str());
+        let provider_change = self.provider_change(response.result.name.as_str(), previous_content.as_deref(), content.as_str());
+        self.emit(LifecycleEvent::UpdateApplied {
+            name: response.result.name.clone(),
+            record_type: response.result.dns_type.clone(),
+            previous: previous_content.clone(),
+            current: content.clone(),
+        });
+        let group = self.records[index].group.as_deref();
+        self.notifications.notify_group(Event::IpChanged {
+            name: response.result.name.clone(),
+            previous: previous_content,
+            current: content.clone(),
+            location,
+        }, group);
+
+        if let Some(event) = provider_change {
+            self.notifications.notify_group(event, group);
+        }
+
+        self.state.stats.updates += 1;
+        let record = &mut self.records[index];
+        record.update_attempts = 0;
+        record.current = Some(response);
+        record.fetched_at = Some(now);
+    }
+
+    /// Returns an event if the previous and new address belong to different autonomous systems.
+    fn provider_change(&self, name: &str, previous: Option<&str>, current: &str) -> Option<Event> {
+        let previous = self.locations.get(previous?)?;
+        let current = self.locations.get(current)?;
+
+        if previous.asn.is_none() || current.asn.is_none() || previous.asn == current.asn {
+            return None;
+        }
+
+        let (previous, current) = (previous.network()?, current.network()?);
+        warn!("{} moved from network {} to {}.", name, previous, current);
+
+        Some(Event::ProviderChanged { name: name.to_string(), previous, current })
+    }
+
+    /// Writes the update time, version and host into the TXT record of the same name as the
+    /// provided record, finding or creating it first. Failures are only logged.
+    async fn update_companion_txt(&mut self, index: usize) {
+        let record = &self.records[index];
+        let name = match record.current.as_ref() {
+            Some(r) => r.result.name.clone(),
+            None => return,
+        };
+
+        let now_utc = self.clock.now_utc().format(&Rfc3339).unwrap_or_default();
+        let hostname = template::hostname().unwrap_or_else(|_| "unknown".to_string());
+        let txt_record = CloudflareDnsRecord {
+            dns_type: "TXT".to_string(),
+            name: name.clone(),
+            content: format!("\"{}{} version={} host={}\"", COMPANION_TXT_PREFIX, now_utc, env!("CARGO_PKG_VERSION"), hostname),
+            ttl: COMPANION_TXT_TTL,
+            proxied: false,
+            comment: Some(MANAGED_RECORD_COMMENT.to_string()),
+            tags: Vec::new(),
+        };
+
+        let client = &self.dns_providers[record.client];
+        let zone_id = record.zone_id.as_str();
+
+        // Other TXT records of the name, such as SPF or site verification ones, are never
+        // touched, only the one written by the updater is reused.
+        let txt_record_id = match record.txt_record_id.clone() {
+            Some(id) => Some(id),
+            None => match client.list_dns_records(zone_id).await {
+                Ok(r) => r.result.into_iter()
+                    .find(|r| r.dns_type == "TXT" && r.name == name && is_companion_txt(r))
+                    .map(|r| r.id),
+                Err(_) => {
+                    warn!("Unable to find the TXT record of {}, not updating it.", name);
+                    return;
+                },
+            },
+        };
+
+        let result = match txt_record_id.as_deref() {
+            Some(id) => client.update_dns_record(zone_id, id, &txt_record).await,
+            None => client.create_dns_record(zone_id, &txt_record).await,
+        };
+
+        match result {
+            Ok(r) => self.records[index].txt_record_id = Some(r.result.id),
+            Err(_) => {
+                warn!("Unable to update the TXT record of {}.", name);
+                self.records[index].txt_record_id = None;
+            },
+        }
+    }
+
+    fn update_failed(&mut self, indices: &[usize], kind: FailureKind) {
+        let now_utc = self.clock.now_utc();
+        self.state.push_error(now_utc, "Unable to update the Cloudflare DNS record.");
+        self.invalidate(indices, kind);
+
+        for &i in indices {
+            self.records[i].update_attempts += 1;
+            self.schedule_retry(i);
+        }
+    }
+
+    /// Checks the record again soon after a failed update, following `retry.cloudflare`,
+    /// rather than waiting for its next scheduled check.
+    fn schedule_retry(&mut self, index: usize) {
+        let record = &mut self.records[index];
+        let Some(delay) = self.cloudflare_backoff.delay(record.update_attempts) else {
+            return;
+        };
+
+        let retry_at = self.clock.now() + delay;
+
+        if retry_at < record.next_check {
+            info!("Retrying the update of {} in {}s.", record.dns_record_id, delay.as_secs());
+            record.next_check = retry_at;
+        }
+    }
+
+    /// Forgets the cached content of the records so that they are fetched again. Cloudflare
+    /// rejecting a request may mean the zone changed, so every record of the zone is
+    /// revalidated as well.
+    fn invalidate(&mut self, indices: &[usize], kind: FailureKind) {
+        let rejected = matches!(kind, FailureKind::Auth | FailureKind::Api);
+        let zones: Vec<(String, usize)> = indices.iter()
+            .map(|&i| (self.records[i].zone_id.clone(), self.records[i].client))
+            .collect();
+
+        for (i, record) in self.records.iter_mut().enumerate() {
+            let same_zone = zones.iter().any(|(z, c)| *z == record.zone_id && *c == record.client);
+
+            if indices.contains(&i) || (rejected && same_zone) {
+                record.current = None;
+            }
+        }
+    }
+
+    /// Gets the public IPs of the families of the address policy, along with the failure
+    /// if an address required by the policy could not be detected. The IPs detected within
+    /// `ip_check.cache_ttl` are reused.
+    async fn get_public_ips(&mut self) -> (PublicIps, Option<FailureKind>) {
+        let now = self.clock.now();
+
+        // A pushed IP replaces the detected address of its family.
+        if let Some(pushed) = self.handle.pushed_ip.lock().unwrap().take() {
+            debug!("Using the pushed public IP {}.", pushed);
+            let mut ips = self.cached_ips.take()
+                .filter(|(_, t)| now < *t + self.ip_cache_ttl)
+                .map(|(ips, _)| ips)
+                .unwrap_or_default();
+
+            match pushed {
+                IpAddr::V4(_) => ips.v4 = Some(pushed.to_string()),
+                IpAddr::V6(_) => ips.v6 = Some(pushed.to_string()),
+            }
+
+            self.cached_ips = Some((ips.clone(), now));
+            return (ips, None);
+        }
+
+        if let Some(path) = self.ip_file.as_ref() {
+            return match ip_file::read(path) {
+                Ok(ips) => (ips, None),
+                Err(kind) => (PublicIps::default(), Some(kind)),
+            };
+        }
+
+        if let Some((name, selection)) = self.interface.as_ref() {
+            return match interface::read(name, *selection) {
+                Ok(ips) => (ips, None),
+                Err(kind) => (PublicIps::default(), Some(kind)),
+            };
+        }
+
+        if let Some((ips, detected_at)) = self.cached_ips.as_ref().filter(|(_, t)| now < *t + self.ip_cache_ttl) {
+            debug!("Reusing the public IP detected {}s ago.", (now - *detected_at).as_secs());
+            return (ips.clone(), None);
+        }
+
+        let mut ips = PublicIps::default();
+        let mut failure = None;
+        let probing = self.detection_strategy == DetectionStrategy::Latency && now >= self.next_probe;
+
+        if probing {
+            self.next_probe = now + self.probe_interval;
+        }
+
+        for &ipv6 in self.address_policy.families() {
+            match self.get_current_public_ip(ipv6, probing).await {
+                Ok(ip) if ipv6 => ips.v6 = Some(ip),
+                Ok(ip) => ips.v4 = Some(ip),
+                Err(kind) => {
+                    failure = Some(kind);
+                    continue;
+                },
+            }
+
+            // Preferring a family only falls back to the other one if it was not detected.
+            if self.address_policy != AddressPolicy::Both {
+                failure = None;
+                break;
+            }
+        }
+
+        if failure.is_none() {
+            self.cached_ips = Some((ips.clone(), now));
+        }
+
+        self.state.providers = self.ip_providers.iter()
+            .chain(self.ipv6_providers.iter())
+            .filter(|p| p.successes + p.failures > 0)
+            .map(IpProvider::stats)
+            .collect();
+
+        (ips, failure)
+    }
+
+    /// Gets the public IP of the provided family from the first working provider, skipping
+    /// providers that keep failing. Every provider is tried if all of them are being skipped.
+    /// With the fastest strategy, every provider is asked at once and the first valid answer wins.
+    /// When probing, every provider is asked at once too, then they are ranked by their results.
+    async fn get_current_public_ip(&mut self, ipv6: bool, probing: bool) -> Result<String, FailureKind> {
+        let now = self.clock.now();
+        let providers = if ipv6 { &mut self.ipv6_providers } else { &mut self.ip_providers };
+        let all_open = providers.iter().all(|p| p.is_open(now));
+        let mut failure = FailureKind::Network;
+
+        let candidates: Vec<(usize, String)> = providers.iter()
+            .enumerate()
+            .filter(|(_, p)| {
+                let skipped = !all_open && p.is_open(now);
+                if skipped {
+                    debug!("Skipping IP provider {}.", p.url);
+                }
+
+                !skipped
+            })
+            .map(|(i, p)| (i, p.url.clone()))
+            .collect();
+
+        let parallelism = match self.detection_strategy {
+            _ if probing => candidates.len().max(1),
+            DetectionStrategy::Sequential | DetectionStrategy::Latency => 1,
+            DetectionStrategy::Fastest => candidates.len().max(1),
+        };
+
+        let client = &self.client;
+        let requests = &self.handle.requests;
+        let mut results = stream::iter(candidates)
+            .map(|(i, url)| async move {
+                let started = std::time::Instant::now();
+                let result = public_ip::get_public_ip_of_family(client, url.as_str(), ipv6).await;
+                requests.observe("ip_check", url.as_str(), started.elapsed(), result.as_ref().err().copied());
+                (i, result, started.elapsed())
+            })
+            .buffer_unordered(parallelism);
+
+        let mut answer = None;
+
+        while let Some((i, result, latency)) = results.next().await {
+            let provider = &mut providers[i];
+
+            match result {
+                Ok(ip) => {
+                    provider.record_success(latency);
+                    self.last_failed_provider = None;
+
+                    if !probing {
+                        return Ok(ip);
+                    }
+
+                    answer.get_or_insert(ip);
+                },
+                Err(kind) => {
+                    provider.record_failure(self.clock.now(), self.provider_failure_threshold, self.provider_cooldown);
+                    self.last_failed_provider = Some(provider.url.clone());
+                    failure = kind;
+                }
+            }
+        }
+
+        if probing {
+            ip_provider::rank(providers);
+            debug!("IP providers ranked: {}", providers.iter().map(|p| p.url.as_str()).collect::<Vec<_>>().join(", "));
+        }
+
+        match answer {
+            Some(ip) => {
+                self.last_failed_provider = None;
+                Ok(ip)
+            },
+            None => Err(failure),
+        }
+    }
+}
+
+/// Returns the providers of the provided URLs. In low-bandwidth mode only the DNS providers
+/// are kept, falling back to the provided defaults if none is configured.
+fn ip_providers(urls: &[String], dns_defaults: &[&str], dns_only: bool) -> Vec<IpProvider> {
+    if !dns_only {
+        return urls.iter().map(|u| IpProvider::new(u)).collect();
+    }
+
+    let providers: Vec<IpProvider> = urls.iter()
+        .filter(|u| Transport::of(u) == Ok(Transport::Dns))
+        .map(|u| IpProvider::new(u))
+        .collect();
+
+    match providers.is_empty() {
+        true => dns_defaults.iter().map(|u| IpProvider::new(u)).collect(),
+        false => providers,
+    }
+}
+
+/// Counts failures against the default threshold, or the one configured for their operation
+/// or kind.
+fn failure_streaks(notifications_config: &NotificationsConfig) -> FailureStreaks {
+    let thresholds = notifications_config.thresholds.as_ref().unwrap();
+    let mut streaks = FailureStreaks::new(notifications_config.failure_threshold.unwrap());
+
+    let operations = [
+        (Operation::DetectIp, thresholds.detect_ip),
+        (Operation::FetchRecords, thresholds.fetch_records),
+        (Operation::UpdateRecords, thresholds.update_records),
+    ];
+    for (operation, threshold) in operations {
+        if let Some(threshold) = threshold {
+            streaks = streaks.with_operation_threshold(operation, threshold);
+        }
+    }
+
+    let kinds = [
+        (FailureKind::Network, thresholds.network),
+        (FailureKind::Auth, thresholds.auth),
+        (FailureKind::RateLimit, thresholds.rate_limit),
+        (FailureKind::Api, thresholds.api),
+        (FailureKind::Mismatch, thresholds.mismatch),
+    ];
+    for (kind, threshold) in kinds {
+        if let Some(threshold) = threshold {
+            streaks = streaks.with_kind_threshold(kind, threshold);
+        }
+    }
+
+    streaks
+}
+
+#[cfg(feature = "grpc")]
+async fn start_grpc(grpc_config: GrpcConfig, handle: Arc<ControlHandle>) {
+    let auth_token = grpc_config.auth_token.unwrap();
+    let listen_address = grpc_config.listen_address.unwrap();
+
+    match listen_address.parse() {
+        Ok(_) if auth_token == DEFAULT_NOT_SET => {
+            warn!("The gRPC control API is enabled but no auth_token is set, not starting it.");
+        },
+        Ok(address) => if let Some(listener) = grpc::bind(address).await {
+            tokio::spawn(async move {
+                grpc::serve(listener, auth_token, handle).await;
+            });
+        },
+        Err(e) => error!("Invalid gRPC listen address {}: {:?}", listen_address, e),
+    }
+}
+
+#[cfg(not(feature = "grpc"))]
+async fn start_grpc(_: GrpcConfig, _: Arc<ControlHandle>) {
+    warn!("The gRPC control API is enabled but this build does not include the grpc feature, not starting it.");
+}
+
+#[cfg(unix)]
+fn start_control_socket(path: PathBuf, handle: Arc<ControlHandle>) {
+    tokio::spawn(async move {
+        control::serve(&path, handle).await;
+    });
+}
+
+#[cfg(not(unix))]
+fn start_control_socket(_: PathBuf, _: Arc<ControlHandle>) {
+    debug!("The control socket is unsupported on this platform, use the HTTP API instead.");
+}
+
+/// Runs the updater loop, keeping the configured DNS record in sync with the public IP.
+pub async fn run_updater(config: Config, overrides: ConfigOverrides, handle: Arc<ControlHandle>, events: Option<EventStream>, pid_file: Option<PathBuf>) {
+    let general_config = config.general.clone().unwrap();
+
+    let http_api_config = config.http_api.clone().unwrap();
+    if http_api_config.enabled.unwrap() {
+        let auth_token = http_api_config.auth_token.unwrap();
+        let webhook_secret = http_api_config.webhook_secret.unwrap();
+        let listen_address = http_api_config.listen_address.unwrap();
+
+        match listen_address.parse() {
+            Ok(_) if auth_token == DEFAULT_NOT_SET => {
+                warn!("The HTTP API is enabled but no auth_token is set, not starting it.");
+            },
+            Ok(address) => if let Some(builder) = http_api::bind(address) {
+                let http_api_handle = handle.clone();
+                tokio::spawn(async move {
+                    http_api::serve(builder, auth_token, webhook_secret, http_api_handle).await;
+                });
+            },
+            Err(e) => error!("Invalid HTTP API listen address {}: {:?}", listen_address, e),
+        }
+    }
+
+    let grpc_config = config.grpc.clone().unwrap();
+    if grpc_config.enabled.unwrap() {
+        start_grpc(grpc_config, handle.clone()).await;
+    }
+
+    let ip_check_config = config.ip_check.clone().unwrap();
+    if ip_check_config.ip_source.unwrap() == IpSource::File {
+        let path = PathBuf::from(ip_check_config.ip_file.unwrap());
+        let ip_file_handle = handle.clone();
+        tokio::spawn(async move {
+            ip_file::watch(path, ip_file_handle).await;
+        });
+    }
+
+    #[cfg(unix)]
+    {
+        let signal_handle = handle.clone();
+        tokio::spawn(async move {
+            control::handle_signals(signal_handle).await;
+        });
+    }
+
+    let mut updater = match Updater::new(&config, handle.clone(), Arc::new(SystemClock)) {
+        Ok(u) => u,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        }
+    };
+    debug!("Configuration loaded.");
+
+    if let Some(events) = events {
+        updater = updater.with_event_stream(events);
+    }
+
+    let owned: Vec<PathBuf> = [State::get_state_dir(), State::get_state_file_path(), State::get_state_dir().join(LOCK_FILE_NAME)]
+        .into_iter()
+        .chain(pid_file)
+        .collect();
+
+    if let Err(e) = privileges::drop_privileges(general_config.user.as_ref().unwrap(), general_config.group.as_ref().unwrap(), &owned) {
+        error!("{}", e);
+        std::process::exit(EXIT_CONFIG_ERROR);
+    }
+
+    start_control_socket(PathBuf::from(general_config.control_socket.unwrap()), handle.clone());
+
+    debug!("Starting main loop.");
+    loop {
+        // A reload only interrupts the wait between checks. One requested during a check is
+        // applied once the check is done, so that an update sent to Cloudflare is always
+        // recorded.
+        let woken = tokio::select! {
+            woken = updater.wait_for_next_check() => woken,
+            _ = handle.reload.notified() => {
+                updater.reload(&overrides);
+                continue;
+            },
+        };
+
+        if let Err(kind) = updater.check_due(woken).await {
+            reporting::flush();
+            telemetry::flush();
+            std::process::exit(kind.exit_code());
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use cloudflare_dynamic_ip_updater::backoff::{Backoff, BackoffStrategy};
+use std::time::Duration;
+
+fn delays(strategy: BackoffStrategy, max_attempts: u32) -> Vec<Option<u64>> {
+    let backoff = Backoff { strategy, base: Duration::from_secs(10), cap: Duration::from_secs(60), max_attempts };
+    (1..=5).map(|attempt| backoff.delay(attempt).map(|d| d.as_secs())).collect()
+}
+
+#[test]
+fn grows_the_delay_according_to_the_strategy() {
+    assert_eq!(delays(BackoffStrategy::Fixed, 0), vec![Some(10); 5]);
+    assert_eq!(delays(BackoffStrategy::Linear, 0), vec![Some(10), Some(20), Some(30), Some(40), Some(50)]);
+    assert_eq!(delays(BackoffStrategy::Exponential, 0), vec![Some(10), Some(20), Some(40), Some(60), Some(60)]);
+}
+
+#[test]
+fn stops_retrying_after_the_maximum_attempts() {
+    assert_eq!(delays(BackoffStrategy::Fixed, 2), vec![Some(10), Some(10), None, None, None]);
+
+    let disabled = Backoff { strategy: BackoffStrategy::Fixed, base: Duration::ZERO, cap: Duration::from_secs(60), max_attempts: 0 };
+    assert_eq!(disabled.delay(1), None);
+}
@@ -0,0 +1,17 @@
+use clap::Parser;
+use cloudflare_dynamic_ip_updater::cli::Cli;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+#[test]
+fn stops_at_the_earliest_runtime_limit() {
+    let now = OffsetDateTime::from_unix_timestamp(1738342800).unwrap();
+    let limit = |args: &[&str]| Cli::parse_from([&["cloudflare-dynamic-ip-updater"], args].concat()).runtime_limit(now);
+
+    assert_eq!(limit(&[]), None);
+    assert_eq!(limit(&["--max-runtime", "600"]), Some(Duration::from_secs(600)));
+    assert_eq!(limit(&["--until", "2025-01-31T18:00:00Z"]), Some(Duration::from_secs(3600)));
+    assert_eq!(limit(&["--until", "2025-01-31T18:00:00+02:00"]), Some(Duration::ZERO));
+    assert_eq!(limit(&["--max-runtime", "600", "--until", "2025-01-31T18:00:00Z"]), Some(Duration::from_secs(600)));
+    assert!(Cli::try_parse_from(["cloudflare-dynamic-ip-updater", "--until", "tomorrow"]).is_err());
+}
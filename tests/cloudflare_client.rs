@@ -0,0 +1,352 @@
+use cloudflare_dynamic_ip_updater::cloudflare_api::{CloudflareDnsRecord, CloudflareDnsResult};
+use cloudflare_dynamic_ip_updater::cloudflare_client::CloudflareClient;
+use cloudflare_dynamic_ip_updater::failure::FailureKind;
+use serde_json::json;
+use wiremock::matchers::{bearer_token, body_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const ZONE_ID: &str = "zone";
+const RECORD_ID: &str = "record";
+const API_TOKEN: &str = "token";
+
+fn dns_record(content: &str) -> serde_json::Value {
+    json!({
+        "id": RECORD_ID,
+        "zone_id": ZONE_ID,
+        "zone_name": "example.com",
+        "name": "home.example.com",
+        "type": "A",
+        "content": content,
+        "proxiable": true,
+        "proxied": false,
+        "ttl": 300,
+        "locked": false,
+        "meta": {
+            "auto_added": false,
+            "managed_by_apps": false,
+            "managed_by_argo_tunnel": false,
+            "source": "primary"
+        },
+        "created_on": "2022-01-01T00:00:00Z",
+        "modified_on": "2022-01-01T00:00:00Z"
+    })
+}
+
+fn success(result: serde_json::Value) -> serde_json::Value {
+    json!({ "result": result, "success": true, "errors": [], "messages": [] })
+}
+
+fn failure(code: i64, message: &str) -> serde_json::Value {
+    json!({ "result": null, "success": false, "errors": [{ "code": code, "message": message }], "messages": [] })
+}
+
+fn client(server: &MockServer) -> CloudflareClient {
+    CloudflareClient::new(reqwest::Client::new(), API_TOKEN).with_base_url(&server.uri())
+}
+
+#[tokio::test]
+async fn gets_the_dns_record() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/zones/{}/dns_records/{}", ZONE_ID, RECORD_ID)))
+        .and(bearer_token(API_TOKEN))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success(dns_record("1.2.3.4"))))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let response = client(&server).get_dns_record(ZONE_ID, RECORD_ID).await.unwrap();
+
+    assert_eq!(response.result.content, "1.2.3.4");
+    assert_eq!(response.result.name, "home.example.com");
+}
+
+#[tokio::test]
+async fn reuses_the_dns_record_when_it_is_not_modified() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/zones/{}/dns_records/{}", ZONE_ID, RECORD_ID)))
+        .and(header("If-None-Match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/zones/{}/dns_records/{}", ZONE_ID, RECORD_ID)))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("ETag", "\"v1\"")
+            .set_body_json(success(dns_record("1.2.3.4"))))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server).with_conditional_requests();
+    client.get_dns_record(ZONE_ID, RECORD_ID).await.unwrap();
+    let response = client.get_dns_record(ZONE_ID, RECORD_ID).await.unwrap();
+
+    assert_eq!(response.result.content, "1.2.3.4");
+}
+
+#[tokio::test]
+async fn tolerates_missing_optional_fields() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/zones/{}/dns_records/{}", ZONE_ID, RECORD_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success(json!({
+            "id": RECORD_ID,
+            "name": "home.example.com",
+            "type": "A",
+            "content": "1.2.3.4",
+            "ttl": 1
+        }))))
+        .mount(&server)
+        .await;
+
+    let response = client(&server).get_dns_record(ZONE_ID, RECORD_ID).await.unwrap();
+
+    assert_eq!(response.result.content, "1.2.3.4");
+    assert!(!response.result.proxied);
+}
+
+#[tokio::test]
+async fn updates_the_dns_record() {
+    let server = MockServer::start().await;
+    let record = CloudflareDnsRecord {
+        dns_type: "A".to_string(),
+        name: "home.example.com".to_string(),
+        content: "5.6.7.8".to_string(),
+        ttl: 300,
+        proxied: false,
+        comment: None,
+        tags: Vec::new(),
+    };
+
+    Mock::given(method("POST"))
+        .and(path(format!("/zones/{}/dns_records/{}", ZONE_ID, RECORD_ID)))
+        .and(bearer_token(API_TOKEN))
+        .and(body_json(&record))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success(dns_record("5.6.7.8"))))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let response = client(&server).update_dns_record(ZONE_ID, RECORD_ID, &record).await.unwrap();
+
+    assert_eq!(response.result.content, "5.6.7.8");
+}
+
+#[tokio::test]
+async fn patches_only_the_comment_of_the_dns_record() {
+    let server = MockServer::start().await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("/zones/{}/dns_records/{}", ZONE_ID, RECORD_ID)))
+        .and(bearer_token(API_TOKEN))
+        .and(body_json(json!({ "comment": "home router" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success(dns_record("1.2.3.4"))))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let response = client(&server).patch_dns_record_comment(ZONE_ID, RECORD_ID, Some("home router")).await.unwrap();
+
+    assert_eq!(response.result.content, "1.2.3.4");
+}
+
+#[tokio::test]
+async fn lists_dns_records_and_zones() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/zones/{}/dns_records", ZONE_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success(json!([dns_record("1.2.3.4")]))))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success(json!([{
+            "id": ZONE_ID,
+            "name": "example.com",
+            "status": "active",
+            "plan": { "id": "free", "name": "Free Website" }
+        }]))))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let records = client.list_dns_records(ZONE_ID).await.unwrap();
+    let zones = client.list_zones().await.unwrap();
+
+    assert_eq!(records.result.len(), 1);
+    assert_eq!(zones.result[0].plan.name, "Free Website");
+}
+
+#[tokio::test]
+async fn lists_every_page_of_dns_records() {
+    let server = MockServer::start().await;
+    for page in 1..=3 {
+        let mut record = dns_record("1.2.3.4");
+        record["id"] = json!(format!("record-{}", page));
+        Mock::given(method("GET"))
+            .and(path(format!("/zones/{}/dns_records", ZONE_ID)))
+            .and(query_param("page", page.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": [record],
+                "success": true,
+                "errors": [],
+                "messages": [],
+                "result_info": { "page": page, "per_page": 1, "total_pages": 3, "total_count": 3 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let records = client(&server).list_dns_records(ZONE_ID).await.unwrap();
+
+    let ids: Vec<&str> = records.result.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids, ["record-1", "record-2", "record-3"]);
+}
+
+#[tokio::test]
+async fn verifies_the_api_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/user/tokens/verify"))
+        .and(bearer_token(API_TOKEN))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success(json!({ "id": "token", "status": "active" }))))
+        .mount(&server)
+        .await;
+
+    let status = client(&server).verify_token().await.unwrap();
+
+    assert_eq!(status.result.status, "active");
+}
+
+#[tokio::test]
+async fn creates_and_deletes_dns_records() {
+    let server = MockServer::start().await;
+    let record = CloudflareDnsRecord {
+        dns_type: "A".to_string(),
+        name: "home.example.com".to_string(),
+        content: "1.2.3.4".to_string(),
+        ttl: 1,
+        proxied: false,
+        comment: Some("managed".to_string()),
+        tags: Vec::new(),
+    };
+
+    Mock::given(method("POST"))
+        .and(path(format!("/zones/{}/dns_records", ZONE_ID)))
+        .and(body_json(&record))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success(dns_record("1.2.3.4"))))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path(format!("/zones/{}/dns_records/{}", ZONE_ID, RECORD_ID)))
+        .and(bearer_token(API_TOKEN))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success(json!({ "id": RECORD_ID }))))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let created = client.create_dns_record(ZONE_ID, &record).await.unwrap();
+    let deleted = client.delete_dns_record(ZONE_ID, RECORD_ID).await.unwrap();
+
+    assert_eq!(created.result.id, RECORD_ID);
+    assert_eq!(deleted.result.id, RECORD_ID);
+}
+
+#[tokio::test]
+async fn classifies_invalid_tokens_as_auth_failures() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(failure(9109, "Invalid access token")))
+        .mount(&server)
+        .await;
+
+    let result = client(&server).get_dns_record(ZONE_ID, RECORD_ID).await;
+
+    assert_eq!(result.unwrap_err(), FailureKind::Auth);
+}
+
+#[tokio::test]
+async fn classifies_unsuccessful_responses_as_api_failures() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(failure(81044, "Record does not exist.")))
+        .mount(&server)
+        .await;
+
+    let result = client(&server).get_dns_record(ZONE_ID, RECORD_ID).await;
+
+    assert_eq!(result.unwrap_err(), FailureKind::Api);
+}
+
+#[tokio::test]
+async fn classifies_rate_limits() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(429).set_body_json(failure(971, "Please wait and consider throttling your request speed")))
+        .mount(&server)
+        .await;
+
+    let result = client(&server).get_dns_record(ZONE_ID, RECORD_ID).await;
+
+    assert_eq!(result.unwrap_err(), FailureKind::RateLimit);
+}
+
+#[tokio::test]
+async fn classifies_unreachable_servers_as_network_failures() {
+    let client = CloudflareClient::new(reqwest::Client::new(), API_TOKEN).with_base_url("http://127.0.0.1:1");
+    let result = client.get_dns_record(ZONE_ID, RECORD_ID).await;
+
+    assert_eq!(result.unwrap_err(), FailureKind::Network);
+}
+
+#[test]
+fn describes_the_changes_to_a_record() {
+    let previous = CloudflareDnsResult {
+        content: "1.2.3.4".to_string(),
+        ttl: 300,
+        comment: None,
+        ..CloudflareDnsResult::default()
+    };
+    let updated = CloudflareDnsResult {
+        content: "5.6.7.8".to_string(),
+        ttl: 300,
+        proxied: true,
+        comment: Some("home".to_string()),
+        ..CloudflareDnsResult::default()
+    };
+
+    assert_eq!(updated.changes_from(&previous), [
+        "content 1.2.3.4 -> 5.6.7.8",
+        "proxied false -> true",
+        "comment \"\" -> \"home\"",
+    ]);
+    assert!(updated.changes_from(&updated).is_empty());
+}
+
+#[test]
+fn validates_records_before_sending_them() {
+    let record = CloudflareDnsRecord {
+        dns_type: "A".to_string(),
+        name: "home.example.com".to_string(),
+        content: "5.6.7.8".to_string(),
+        ttl: 1,
+        proxied: true,
+        comment: None,
+        tags: Vec::new(),
+    };
+
+    assert_eq!(record.validate("example.com", 60), Ok(()));
+    assert_eq!(record.validate("", 60), Ok(()));
+    assert_eq!(CloudflareDnsRecord { ttl: 30, ..record.clone() }.validate("example.com", 30), Ok(()));
+    assert!(CloudflareDnsRecord { ttl: 30, ..record.clone() }.validate("example.com", 60).unwrap_err().contains("TTL"));
+    assert!(CloudflareDnsRecord { ttl: 90000, ..record.clone() }.validate("example.com", 60).is_err());
+    assert!(CloudflareDnsRecord { dns_type: "TXT".to_string(), ..record.clone() }.validate("example.com", 60).unwrap_err().contains("proxied"));
+    assert_eq!(record.validate("example.org", 60), Err("home.example.com is not within the zone example.org".to_string()));
+    assert!(CloudflareDnsRecord { name: "notexample.com".to_string(), ..record }.validate("example.com", 60).is_err());
+}
@@ -0,0 +1,12 @@
+use cloudflare_dynamic_ip_updater::daemon::PidFile;
+
+#[test]
+fn pid_file_is_removed_when_dropped() {
+    let path = std::env::temp_dir().join(format!("cloudflare-ddns-{}.pid", std::process::id()));
+
+    let pid_file = PidFile::create(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), format!("{}\n", std::process::id()));
+
+    drop(pid_file);
+    assert!(!path.exists());
+}
@@ -0,0 +1,40 @@
+use cloudflare_dynamic_ip_updater::dns_precheck::DnsPrecheck;
+use serde_json::json;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn resolves_the_addresses_of_a_record() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/dns-query"))
+        .and(query_param("name", "home.example.com"))
+        .and(query_param("type", "AAAA"))
+        .and(header("Accept", "application/dns-json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "Status": 0,
+            "Answer": [
+                { "name": "home.example.com", "type": 5, "TTL": 300, "data": "edge.example.com." },
+                { "name": "edge.example.com", "type": 28, "TTL": 300, "data": "2606:4700::1111" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+
+    let precheck = DnsPrecheck::new(reqwest::Client::new(), &format!("{}/dns-query", server.uri()));
+
+    assert_eq!(precheck.resolve("home.example.com", "AAAA").await.unwrap(), vec!["2606:4700::1111"]);
+}
+
+#[tokio::test]
+async fn resolves_missing_names_to_nothing() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "Status": 3 })))
+        .mount(&server)
+        .await;
+
+    let precheck = DnsPrecheck::new(reqwest::Client::new(), &server.uri());
+
+    assert!(precheck.resolve("missing.example.com", "A").await.unwrap().is_empty());
+}
@@ -0,0 +1,14 @@
+#![cfg(windows)]
+
+use cloudflare_dynamic_ip_updater::constants::EVENT_LOG_SOURCE;
+use cloudflare_dynamic_ip_updater::event_log::EventLog;
+use log::Level;
+
+#[test]
+fn only_reports_warnings_and_errors() {
+    let event_log = EventLog::register(EVENT_LOG_SOURCE).unwrap();
+
+    assert!(event_log.report(Level::Error, "Unable to update home.example.com."));
+    assert!(event_log.report(Level::Warn, "IP provider https://ip.example.com failed."));
+    assert!(!event_log.report(Level::Info, "Checking the public IP."));
+}
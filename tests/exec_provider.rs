@@ -0,0 +1,44 @@
+#![cfg(unix)]
+
+use cloudflare_dynamic_ip_updater::cloudflare_api::CloudflareDnsRecord;
+use cloudflare_dynamic_ip_updater::exec_provider::ExecProvider;
+use cloudflare_dynamic_ip_updater::failure::FailureKind;
+
+const RECORD: &str = r#"{"id":"record","name":"home.example.com","type":"A","content":"1.2.3.4","ttl":300,"proxied":false}"#;
+
+#[tokio::test]
+async fn gets_the_record_from_the_plugin() {
+    let plugin = ExecProvider::new(&format!("cat > /dev/null; echo '{{\"success\":true,\"result\":{}}}'", RECORD));
+
+    let record = plugin.get_dns_record("zone", "record").await.unwrap();
+
+    assert_eq!(record.result.content, "1.2.3.4");
+}
+
+#[tokio::test]
+async fn sends_the_request_on_stdin() {
+    let command = format!(r#"read -r request; case "$request" in *'"action":"update"'*'"record_id":"record"'*'"content":"5.6.7.8"'*) echo '{{"success":true,"result":{}}}';; *) exit 1;; esac"#, RECORD);
+    let plugin = ExecProvider::new(&command);
+    let record = CloudflareDnsRecord {
+        dns_type: "A".to_string(),
+        name: "home.example.com".to_string(),
+        content: "5.6.7.8".to_string(),
+        ttl: 300,
+        proxied: false,
+        comment: None,
+        tags: Vec::new(),
+    };
+
+    assert!(plugin.update_dns_record("zone", "record", &record).await.is_ok());
+}
+
+#[tokio::test]
+async fn classifies_plugin_failures() {
+    let reported = ExecProvider::new(r#"cat > /dev/null; echo '{"success":false,"error":"no such record"}'"#);
+    let crashed = ExecProvider::new("cat > /dev/null; exit 2");
+    let garbled = ExecProvider::new("cat > /dev/null; echo nope");
+
+    assert_eq!(reported.get_dns_record("zone", "record").await.unwrap_err(), FailureKind::Api);
+    assert_eq!(crashed.get_dns_record("zone", "record").await.unwrap_err(), FailureKind::Api);
+    assert_eq!(garbled.get_dns_record("zone", "record").await.unwrap_err(), FailureKind::Api);
+}
@@ -0,0 +1,47 @@
+use cloudflare_dynamic_ip_updater::failure::{FailureKind, FailureStreaks, Operation};
+
+#[test]
+fn escalates_once_an_operation_keeps_failing() {
+    let mut streaks = FailureStreaks::new(3);
+
+    assert!(!streaks.record_failure(Operation::DetectIp, FailureKind::Network));
+    assert!(!streaks.record_failure(Operation::DetectIp, FailureKind::Network));
+    assert!(!streaks.record_failure(Operation::UpdateRecords, FailureKind::Api));
+    assert!(streaks.record_failure(Operation::DetectIp, FailureKind::Network));
+    assert!(!streaks.record_failure(Operation::DetectIp, FailureKind::Network));
+    assert_eq!(streaks.get(Operation::DetectIp), 4);
+    assert_eq!(streaks.get(Operation::UpdateRecords), 1);
+
+    streaks.record_success(Operation::DetectIp);
+    assert_eq!(streaks.get(Operation::DetectIp), 0);
+    assert!(!streaks.record_failure(Operation::DetectIp, FailureKind::Network));
+}
+
+#[test]
+fn escalates_by_the_threshold_of_the_kind_then_of_the_operation() {
+    let mut streaks = FailureStreaks::new(3)
+        .with_kind_threshold(FailureKind::Auth, 1)
+        .with_kind_threshold(FailureKind::RateLimit, 0)
+        .with_operation_threshold(Operation::DetectIp, 5);
+
+    assert!(streaks.record_failure(Operation::FetchRecords, FailureKind::Auth));
+    assert!(!streaks.record_failure(Operation::FetchRecords, FailureKind::Auth));
+
+    for _ in 0..10 {
+        assert!(!streaks.record_failure(Operation::UpdateRecords, FailureKind::RateLimit));
+    }
+
+    for _ in 0..4 {
+        assert!(!streaks.record_failure(Operation::DetectIp, FailureKind::Network));
+    }
+    assert!(streaks.record_failure(Operation::DetectIp, FailureKind::Network));
+}
+
+#[test]
+fn never_escalates_without_a_threshold() {
+    let mut streaks = FailureStreaks::new(0);
+
+    for _ in 0..10 {
+        assert!(!streaks.record_failure(Operation::FetchRecords, FailureKind::Network));
+    }
+}
@@ -0,0 +1,60 @@
+use cloudflare_dynamic_ip_updater::geoip::{GeoIp, IpInfo};
+use reqwest::Client;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn describes_what_is_known_about_an_address() {
+    let info = IpInfo {
+        country: Some("DE".to_string()),
+        city: Some("Berlin".to_string()),
+        asn: Some(3320),
+        organization: Some("Deutsche Telekom AG".to_string()),
+    };
+    assert_eq!(info.to_string(), "Berlin, DE, AS3320 Deutsche Telekom AG");
+
+    let info = IpInfo { country: Some("DE".to_string()), ..IpInfo::default() };
+    assert_eq!(info.to_string(), "DE");
+}
+
+#[tokio::test]
+async fn looks_up_addresses_through_ipinfo_and_ip_api_style_apis() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/ipinfo/5.6.7.8"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_json(json!({ "ip": "5.6.7.8", "city": "Berlin", "country": "DE", "org": "AS3320 Deutsche Telekom AG" })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/ip-api/5.6.7.8"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_json(json!({ "city": "Berlin", "country": "Germany", "countryCode": "DE", "as": "AS3320 Deutsche Telekom AG" })))
+        .mount(&server)
+        .await;
+
+    let expected = IpInfo {
+        country: Some("DE".to_string()),
+        city: Some("Berlin".to_string()),
+        asn: Some(3320),
+        organization: Some("Deutsche Telekom AG".to_string()),
+    };
+
+    for api in ["ipinfo", "ip-api"] {
+        let geoip = GeoIp::api(Client::new(), &format!("{}/{}/{{ip}}", server.uri(), api));
+        assert_eq!(geoip.lookup("5.6.7.8".parse().unwrap()).await, Some(expected.clone()), "{}", api);
+    }
+}
+
+#[tokio::test]
+async fn returns_nothing_when_the_lookup_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+
+    let geoip = GeoIp::api(Client::new(), &format!("{}/{{ip}}", server.uri()));
+    assert_eq!(geoip.lookup("5.6.7.8".parse().unwrap()).await, None);
+}
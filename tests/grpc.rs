@@ -0,0 +1,76 @@
+#![cfg(feature = "grpc")]
+
+use cloudflare_dynamic_ip_updater::control::ControlHandle;
+use cloudflare_dynamic_ip_updater::grpc::{self, proto};
+use cloudflare_dynamic_ip_updater::state::{IpChange, State};
+use futures::StreamExt;
+use proto::control_client::ControlClient;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tonic::metadata::MetadataValue;
+use tonic::transport::Channel;
+use tonic::{Code, Request};
+
+async fn start(handle: Arc<ControlHandle>) -> String {
+    let listener = grpc::bind("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(grpc::serve(listener, "secret".to_string(), handle));
+    address
+}
+
+fn authorized<T>(message: T) -> Request<T> {
+    let mut request = Request::new(message);
+    request.metadata_mut().insert("authorization", MetadataValue::from_static("Bearer secret"));
+    request
+}
+
+async fn client(address: String) -> ControlClient<Channel> {
+    ControlClient::connect(address).await.unwrap()
+}
+
+#[tokio::test]
+async fn requests_without_the_auth_token_are_rejected() {
+    let address = start(Arc::new(ControlHandle::in_memory(State::default()))).await;
+
+    let error = client(address).await.get_status(proto::GetStatusRequest {}).await.unwrap_err();
+    assert_eq!(error.code(), Code::Unauthenticated);
+}
+
+#[tokio::test]
+async fn pauses_and_reports_the_status() {
+    let state = State { public_ip: Some("203.0.113.1".to_string()), ..Default::default() };
+    let handle = Arc::new(ControlHandle::in_memory(state));
+    let mut client = client(start(handle.clone()).await).await;
+
+    let response = client.pause(authorized(proto::PauseRequest {})).await.unwrap().into_inner();
+    assert!(response.success);
+    assert!(handle.is_paused());
+
+    let status = client.get_status(authorized(proto::GetStatusRequest {})).await.unwrap().into_inner();
+    assert!(status.paused);
+    assert_eq!(status.public_ip.as_deref(), Some("203.0.113.1"));
+}
+
+#[tokio::test]
+async fn streams_the_history_then_new_changes() {
+    let change = |to: &str, timestamp| IpChange {
+        timestamp: OffsetDateTime::from_unix_timestamp(timestamp).unwrap(),
+        record: None,
+        from: None,
+        to: to.to_string(),
+    };
+
+    let state = State { history: vec![change("203.0.113.1", 1704067200)], ..Default::default() };
+    let handle = Arc::new(ControlHandle::in_memory(state.clone()));
+    let mut client = client(start(handle.clone()).await).await;
+
+    let mut history = client.stream_history(authorized(proto::StreamHistoryRequest {})).await.unwrap().into_inner();
+    assert_eq!(history.next().await.unwrap().unwrap().to, "203.0.113.1");
+
+    let mut updated = state;
+    updated.history.push(change("203.0.113.2", 1704153600));
+    handle.update_state(&updated);
+
+    assert_eq!(history.next().await.unwrap().unwrap().to, "203.0.113.2");
+}
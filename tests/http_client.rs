@@ -0,0 +1,129 @@
+use cloudflare_dynamic_ip_updater::config::{Config, ConfigError};
+use cloudflare_dynamic_ip_updater::failure::FailureKind;
+use cloudflare_dynamic_ip_updater::http_client;
+use cloudflare_dynamic_ip_updater::public_ip::get_current_public_ip;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn requests_go_through_the_configured_proxy() {
+    let proxy = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("1.2.3.4"))
+        .expect(1)
+        .mount(&proxy)
+        .await;
+
+    let config = Config::parse(&format!("[general]\nproxy = \"{}\"\n", proxy.uri())).unwrap();
+    let client = http_client::build(&config).unwrap();
+    let body = client.get("http://ip-check.invalid/").send().await.unwrap().text().await.unwrap();
+
+    assert_eq!(body, "1.2.3.4");
+}
+
+#[test]
+fn invalid_proxy_is_rejected() {
+    let config = Config::parse("[general]\nproxy = \"not a url\"\n").unwrap();
+
+    assert!(matches!(http_client::build(&config), Err(ConfigError::Invalid { key: "general.proxy", .. })));
+}
+
+#[test]
+fn ca_bundle_is_loaded() {
+    let config = Config::parse("[general]\nca_file = \"tests/fixtures/ca-bundle.pem\"\n").unwrap();
+
+    assert!(http_client::build(&config).is_ok());
+}
+
+#[test]
+fn missing_or_empty_ca_file_is_rejected() {
+    let config = Config::parse("[general]\nca_file = \"tests/fixtures/missing.pem\"\n").unwrap();
+    assert!(matches!(http_client::build(&config), Err(ConfigError::Io { .. })));
+
+    let config = Config::parse("[general]\nca_file = \"Cargo.toml\"\n").unwrap();
+    assert!(matches!(http_client::build(&config), Err(ConfigError::Invalid { key: "general.ca_file", .. })));
+}
+
+#[tokio::test]
+async fn hung_requests_time_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("1.2.3.4").set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let config = Config::parse("[general]\nrequest_timeout = 1\n").unwrap();
+    let client = http_client::build(&config).unwrap();
+    let started = Instant::now();
+
+    assert_eq!(get_current_public_ip(&client, &server.uri()).await, Err(FailureKind::Network));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn bind_address_and_interface_are_validated() {
+    let config = Config::parse("[general]\nbind_address = \"127.0.0.1\"\n").unwrap();
+    assert!(http_client::build(&config).is_ok());
+
+    if cfg!(target_os = "linux") {
+        let config = Config::parse("[general]\ninterface = \"lo\"\n").unwrap();
+        assert!(http_client::build(&config).is_ok());
+    }
+
+    let config = Config::parse("[general]\nbind_address = \"not an address\"\n").unwrap();
+    assert!(matches!(http_client::build(&config), Err(ConfigError::Invalid { key: "general.bind_address", .. })));
+
+    let config = Config::parse("[general]\ninterface = \"does-not-exist0\"\n").unwrap();
+    assert!(matches!(http_client::build(&config), Err(ConfigError::Invalid { key: "general.interface", .. })));
+
+    let config = Config::parse("[general]\nbind_address = \"127.0.0.1\"\ninterface = \"lo\"\n").unwrap();
+    assert!(matches!(http_client::build(&config), Err(ConfigError::Invalid { key: "general.interface", .. })));
+}
+
+#[tokio::test]
+async fn requests_are_sent_from_the_bind_address() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("1.2.3.4"))
+        .mount(&server)
+        .await;
+
+    let config = Config::parse("[general]\nbind_address = \"127.0.0.1\"\n").unwrap();
+    let client = http_client::build(&config).unwrap();
+    assert_eq!(get_current_public_ip(&client, &server.uri()).await, Ok("1.2.3.4".to_string()));
+
+    let config = Config::parse("[general]\nbind_address = \"::1\"\n").unwrap();
+    let client = http_client::build(&config).unwrap();
+    assert_eq!(get_current_public_ip(&client, &server.uri()).await, Err(FailureKind::Network));
+}
+
+#[tokio::test]
+async fn ip_check_requests_send_the_configured_headers() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("user-agent", "my-updater/1.0"))
+        .and(header("authorization", "Bearer secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("1.2.3.4"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config = Config::parse(r#"
+        [ip_check]
+        user_agent = "my-updater/1.0"
+
+        [ip_check.headers]
+        Authorization = "Bearer secret"
+    "#).unwrap();
+    let client = http_client::build_for_ip_check(&config).unwrap();
+
+    assert_eq!(get_current_public_ip(&client, &server.uri()).await, Ok("1.2.3.4".to_string()));
+}
+
+#[test]
+fn invalid_ip_check_header_is_rejected() {
+    let config = Config::parse("[ip_check.headers]\n\"bad header\" = \"value\"\n").unwrap();
+
+    assert!(matches!(http_client::build_for_ip_check(&config), Err(ConfigError::Invalid { key: "ip_check.headers", .. })));
+}
@@ -0,0 +1,40 @@
+use cloudflare_dynamic_ip_updater::interface::{parse_if_inet6, select_ipv6, Ipv6Selection};
+
+const IF_INET6: &str = "\
+2001067c12340000a1b2c3d4e5f60789 02 40 00 01 eth0
+2001067c12340000021122fffe334455 02 40 00 00 eth0
+fe80000000000000021122fffe334455 02 40 20 80 eth0
+2001067c123400000000000000000099 03 40 00 80 wlan0
+";
+
+#[test]
+fn reads_the_flags_of_the_addresses() {
+    let addresses = parse_if_inet6(IF_INET6, "eth0");
+
+    assert_eq!(addresses.len(), 3);
+    assert!(addresses[0].temporary);
+    assert!(!addresses[1].temporary);
+    assert!(addresses[2].permanent);
+}
+
+#[test]
+fn skips_temporary_addresses() {
+    let addresses = parse_if_inet6(IF_INET6, "eth0");
+    let stable = "2001:67c:1234:0:211:22ff:fe33:4455".parse().ok();
+
+    assert_eq!(select_ipv6(&addresses, Ipv6Selection::Stable), stable);
+    assert_eq!(select_ipv6(&addresses, Ipv6Selection::Eui64), stable);
+    assert_eq!(select_ipv6(&addresses, Ipv6Selection::Any), "2001:67c:1234:0:a1b2:c3d4:e5f6:789".parse().ok());
+    assert_eq!(select_ipv6(&addresses[..1], Ipv6Selection::Stable), None);
+}
+
+#[test]
+fn prefers_statically_configured_addresses() {
+    let addresses = parse_if_inet6("\
+2001067c12340000021122fffe334455 02 40 00 00 eth0
+2001067c123400000000000000000010 02 40 00 80 eth0
+", "eth0");
+
+    assert_eq!(select_ipv6(&addresses, Ipv6Selection::Stable), "2001:67c:1234::10".parse().ok());
+    assert_eq!(select_ipv6(&addresses, Ipv6Selection::Eui64), "2001:67c:1234:0:211:22ff:fe33:4455".parse().ok());
+}
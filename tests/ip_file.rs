@@ -0,0 +1,28 @@
+use cloudflare_dynamic_ip_updater::failure::FailureKind;
+use cloudflare_dynamic_ip_updater::ip_file;
+use std::path::PathBuf;
+
+fn ip_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cloudflare-ddns-{}-{}", name, std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn reads_an_address_of_each_family() {
+    let path = ip_file("both", "5.6.7.8\n2606:4700::1111\n");
+    let ips = ip_file::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(ips.v4.as_deref(), Some("5.6.7.8"));
+    assert_eq!(ips.v6.as_deref(), Some("2606:4700::1111"));
+}
+
+#[test]
+fn rejects_files_without_a_public_address() {
+    for (name, contents) in [("empty", "\n"), ("private", "192.168.1.1\n"), ("garbage", "not an ip\n")] {
+        let path = ip_file(name, contents);
+        assert_eq!(ip_file::read(&path), Err(FailureKind::Network), "{}", name);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
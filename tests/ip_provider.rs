@@ -0,0 +1,31 @@
+use cloudflare_dynamic_ip_updater::ip_provider::{self, IpProvider};
+use std::time::{Duration, Instant};
+
+#[test]
+fn ranks_providers_by_success_rate_then_latency() {
+    let mut fast = IpProvider::new("fast");
+    fast.record_success(Duration::from_millis(20));
+
+    let mut slow = IpProvider::new("slow");
+    slow.record_success(Duration::from_millis(300));
+
+    let mut failing = IpProvider::new("failing");
+    failing.record_success(Duration::from_millis(10));
+    failing.record_failure(Instant::now(), 3, Duration::from_secs(300));
+
+    let mut providers = vec![IpProvider::new("unmeasured"), failing, slow, fast];
+    ip_provider::rank(&mut providers);
+
+    let order: Vec<&str> = providers.iter().map(|p| p.url.as_str()).collect();
+    assert_eq!(order, vec!["fast", "slow", "unmeasured", "failing"]);
+}
+
+#[test]
+fn averages_the_latency_weighting_recent_requests() {
+    let mut provider = IpProvider::new("provider");
+    provider.record_success(Duration::from_millis(100));
+    provider.record_success(Duration::from_millis(200));
+
+    assert_eq!(provider.latency, Some(Duration::from_millis(130)));
+    assert_eq!(provider.success_rate(), 1.0);
+}
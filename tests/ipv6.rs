@@ -0,0 +1,20 @@
+use cloudflare_dynamic_ip_updater::ipv6::{combine, parse_suffix};
+use std::net::Ipv6Addr;
+
+#[test]
+fn combines_the_delegated_prefix_with_the_suffix() {
+    let prefix: Ipv6Addr = "2001:db8:1234:5600::1".parse().unwrap();
+    let suffix = parse_suffix("::1234:5678:9abc:def0").unwrap();
+
+    assert_eq!(combine(prefix, 64, suffix), "2001:db8:1234:5600:1234:5678:9abc:def0".parse::<Ipv6Addr>().unwrap());
+    assert_eq!(combine(prefix, 56, "0:0:0:ff::1".parse().unwrap()), "2001:db8:1234:56ff::1".parse::<Ipv6Addr>().unwrap());
+    assert_eq!(combine(prefix, 128, suffix), prefix);
+}
+
+#[test]
+fn derives_the_eui64_suffix_from_a_mac_address() {
+    assert_eq!(parse_suffix("00:11:22:33:44:55").unwrap(), "::211:22ff:fe33:4455".parse::<Ipv6Addr>().unwrap());
+    assert_eq!(parse_suffix("02-11-22-33-44-55").unwrap(), "::11:22ff:fe33:4455".parse::<Ipv6Addr>().unwrap());
+    assert!(parse_suffix("00:11:22:33:44").is_err());
+    assert!(parse_suffix("host").is_err());
+}
@@ -0,0 +1,18 @@
+use cloudflare_dynamic_ip_updater::lock::{InstanceLock, LockError};
+
+#[test]
+fn refuses_a_second_instance_until_the_first_exits() {
+    let dir = std::env::temp_dir().join(format!("cloudflare-ddns-lock-{}", std::process::id()));
+    let path = dir.join("updater.lock");
+
+    let first = InstanceLock::acquire(&path).unwrap();
+    match InstanceLock::acquire(&path) {
+        Err(LockError::Held { pid, .. }) => assert_eq!(pid, std::process::id().to_string()),
+        _ => panic!("the second lock should be refused"),
+    }
+
+    drop(first);
+    assert!(InstanceLock::acquire(&path).is_ok());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,48 @@
+use cloudflare_dynamic_ip_updater::logging::{LogFormat, LogTimestamps, Logger};
+use log::{Level, Record};
+use time::{Duration, OffsetDateTime, UtcOffset};
+
+fn format(format: LogFormat) -> String {
+    let logger = Logger::new(format, LogTimestamps::Utc, false, true);
+    let timestamp = OffsetDateTime::UNIX_EPOCH + Duration::milliseconds(1_234);
+
+    logger.format(&Record::builder()
+        .args(format_args!("IP changed."))
+        .level(Level::Info)
+        .target("updater")
+        .build(), timestamp)
+}
+
+#[test]
+fn formats_full_lines() {
+    assert_eq!(format(LogFormat::Full), "1970-01-01 00:00:01,234 INFO  [updater] IP changed.");
+}
+
+#[test]
+fn formats_compact_lines() {
+    assert_eq!(format(LogFormat::Compact), "INFO  IP changed.");
+}
+
+#[test]
+fn formats_json_lines() {
+    let line: serde_json::Value = serde_json::from_str(format(LogFormat::Json).as_str()).unwrap();
+
+    assert_eq!(line["timestamp"], "1970-01-01T00:00:01.234Z");
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["target"], "updater");
+    assert_eq!(line["message"], "IP changed.");
+}
+
+#[test]
+fn formats_timestamps_in_their_offset() {
+    let logger = Logger::new(LogFormat::Full, LogTimestamps::Local, false, true);
+    let timestamp = OffsetDateTime::UNIX_EPOCH.to_offset(UtcOffset::from_hms(2, 0, 0).unwrap());
+
+    let line = logger.format(&Record::builder()
+        .args(format_args!("IP changed."))
+        .level(Level::Info)
+        .target("updater")
+        .build(), timestamp);
+
+    assert_eq!(line, "1970-01-01 02:00:00,000 INFO  [updater] IP changed.");
+}
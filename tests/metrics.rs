@@ -0,0 +1,65 @@
+use cloudflare_dynamic_ip_updater::failure::FailureKind;
+use cloudflare_dynamic_ip_updater::metrics::{self, RequestMetrics};
+use cloudflare_dynamic_ip_updater::state::{ProviderStats, State};
+use time::{Duration, OffsetDateTime};
+
+#[test]
+fn renders_counters_and_gauges() {
+    let now = OffsetDateTime::UNIX_EPOCH + Duration::hours(1);
+    let mut state = State::default();
+    state.stats.checks = 12;
+    state.stats.updates = 2;
+    state.stats.failures.auth = 1;
+    state.stats.started = Some(OffsetDateTime::UNIX_EPOCH);
+    state.last_changed = Some(now - Duration::minutes(5));
+
+    let output = metrics::render(&state, &RequestMetrics::default(), true, now);
+
+    assert!(output.contains("# TYPE cloudflare_ddns_checks_total counter\ncloudflare_ddns_checks_total 12\n"));
+    assert!(output.contains("cloudflare_ddns_updates_total 2\n"));
+    assert!(output.contains("cloudflare_ddns_failures_total{kind=\"auth\"} 1\n"));
+    assert!(output.contains("cloudflare_ddns_failures_total{kind=\"network\"} 0\n"));
+    assert!(output.contains("cloudflare_ddns_paused 1\n"));
+    assert!(output.contains("cloudflare_ddns_uptime_seconds 3600\n"));
+    assert!(output.contains("cloudflare_ddns_seconds_since_last_change 300\n"));
+    assert!(!output.contains("cloudflare_ddns_last_check_timestamp_seconds"));
+    assert!(!output.contains("cloudflare_ddns_request_duration_seconds"));
+}
+
+#[test]
+fn renders_the_results_of_each_ip_provider() {
+    let state = State {
+        providers: vec![
+            ProviderStats { url: "https://api.ipify.org".to_string(), successes: 9, failures: 1, latency_ms: Some(250) },
+            ProviderStats { url: "https://icanhazip.com".to_string(), successes: 0, failures: 3, latency_ms: None },
+        ],
+        ..Default::default()
+    };
+
+    let output = metrics::render(&state, &RequestMetrics::default(), false, OffsetDateTime::UNIX_EPOCH);
+
+    assert!(output.contains("cloudflare_ddns_ip_provider_successes_total{url=\"https://api.ipify.org\"} 9\n"));
+    assert!(output.contains("cloudflare_ddns_ip_provider_failures_total{url=\"https://icanhazip.com\"} 3\n"));
+    assert!(output.contains("cloudflare_ddns_ip_provider_latency_seconds{url=\"https://api.ipify.org\"} 0.25\n"));
+    assert!(!output.contains("cloudflare_ddns_ip_provider_latency_seconds{url=\"https://icanhazip.com\"}"));
+}
+
+#[test]
+fn renders_request_durations_and_failures() {
+    let requests = RequestMetrics::default();
+    requests.observe("cloudflare", "get_dns_record", std::time::Duration::from_millis(80), None);
+    requests.observe("cloudflare", "get_dns_record", std::time::Duration::from_secs(3), Some(FailureKind::RateLimit));
+    requests.observe("ip_check", "https://ip.example.com", std::time::Duration::from_millis(20), Some(FailureKind::Network));
+
+    let output = metrics::render(&State::default(), &requests, false, OffsetDateTime::UNIX_EPOCH);
+
+    let labels = "service=\"cloudflare\",endpoint=\"get_dns_record\"";
+    assert!(output.contains("# TYPE cloudflare_ddns_request_duration_seconds histogram\n"));
+    assert!(output.contains(&format!("cloudflare_ddns_request_duration_seconds_bucket{{{},le=\"0.05\"}} 0\n", labels)));
+    assert!(output.contains(&format!("cloudflare_ddns_request_duration_seconds_bucket{{{},le=\"0.1\"}} 1\n", labels)));
+    assert!(output.contains(&format!("cloudflare_ddns_request_duration_seconds_bucket{{{},le=\"5\"}} 2\n", labels)));
+    assert!(output.contains(&format!("cloudflare_ddns_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 2\n", labels)));
+    assert!(output.contains(&format!("cloudflare_ddns_request_duration_seconds_count{{{}}} 2\n", labels)));
+    assert!(output.contains(&format!("cloudflare_ddns_request_failures_total{{{},kind=\"rate_limit\"}} 1\n", labels)));
+    assert!(output.contains("cloudflare_ddns_request_failures_total{service=\"ip_check\",endpoint=\"https://ip.example.com\",kind=\"network\"} 1\n"));
+}
@@ -0,0 +1,71 @@
+use cloudflare_dynamic_ip_updater::migration;
+use std::collections::HashMap;
+use toml::Value;
+
+fn parse(contents: &str) -> toml::value::Table {
+    match contents.parse::<Value>().unwrap() {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn replaces_record_ids_by_names() {
+    let names = HashMap::from([
+        ("home".to_string(), ("home.example.com".to_string(), "A".to_string())),
+        ("nas".to_string(), ("nas.example.com".to_string(), "AAAA".to_string())),
+    ]);
+
+    let mut config = parse(r#"
+        [[records]]
+        dns_record_id = "home"
+        zone_id = "zone"
+
+        [[records]]
+        dns_record_id = "unknown"
+
+        [[groups]]
+        name = "lan"
+
+        [[groups.records]]
+        dns_record_id = "nas"
+    "#);
+
+    assert_eq!(migration::replace_record_ids(&mut config, &names), 2);
+    assert_eq!(config, parse(r#"
+        [[records]]
+        name = "home.example.com"
+        type = "A"
+        zone_id = "zone"
+
+        [[records]]
+        dns_record_id = "unknown"
+
+        [[groups]]
+        name = "lan"
+
+        [[groups.records]]
+        name = "nas.example.com"
+        type = "AAAA"
+    "#));
+}
+
+#[test]
+fn lists_the_legacy_record_by_name() {
+    let names = HashMap::from([("home".to_string(), ("home.example.com".to_string(), "A".to_string()))]);
+    let mut config = parse(r#"
+        [cloudflare]
+        zone_id = "zone"
+        dns_record_id = "home"
+    "#);
+
+    assert_eq!(migration::replace_record_ids(&mut config, &names), 1);
+    assert_eq!(config, parse(r#"
+        [cloudflare]
+        zone_id = "zone"
+
+        [[records]]
+        name = "home.example.com"
+        type = "A"
+    "#));
+}
@@ -0,0 +1,16 @@
+use cloudflare_dynamic_ip_updater::pattern::matches;
+
+#[test]
+fn matches_wildcards() {
+    assert!(matches("*.home.example.com", "nas.home.example.com"));
+    assert!(matches("*.home.example.com", "a.b.home.example.com"));
+    assert!(matches("host-?.example.com", "HOST-1.example.com"));
+    assert!(matches("*", "example.com"));
+}
+
+#[test]
+fn rejects_other_names() {
+    assert!(!matches("*.home.example.com", "home.example.com"));
+    assert!(!matches("host-?.example.com", "host-10.example.com"));
+    assert!(!matches("home.example.com", "home.example.org"));
+}
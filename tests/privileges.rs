@@ -0,0 +1,15 @@
+use cloudflare_dynamic_ip_updater::config::ConfigError;
+use cloudflare_dynamic_ip_updater::privileges::drop_privileges;
+
+#[test]
+fn rejects_unknown_users_and_groups() {
+    assert!(matches!(drop_privileges("no-such-user-here", "NOT SET", &[]),
+        Err(ConfigError::Invalid { key: "general.user", .. })));
+    assert!(matches!(drop_privileges("NOT SET", "no-such-group-here", &[]),
+        Err(ConfigError::Invalid { key: "general.group", .. })));
+}
+
+#[test]
+fn does_nothing_when_unset() {
+    assert!(drop_privileges("NOT SET", "NOT SET", &[]).is_ok());
+}
@@ -0,0 +1,124 @@
+use cloudflare_dynamic_ip_updater::failure::FailureKind;
+use cloudflare_dynamic_ip_updater::public_ip::{get_current_public_ip, get_public_ip_of_family, is_public, Transport};
+use tokio::net::UdpSocket;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn server_responding_with(response: ResponseTemplate) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(response)
+        .mount(&server)
+        .await;
+
+    server
+}
+
+#[tokio::test]
+async fn detects_the_public_ip() {
+    let server = server_responding_with(ResponseTemplate::new(200).set_body_string("1.2.3.4\n")).await;
+
+    let ip = get_current_public_ip(&reqwest::Client::new(), &server.uri()).await;
+
+    assert_eq!(ip, Ok("1.2.3.4".to_string()));
+}
+
+#[tokio::test]
+async fn detects_ipv6_addresses() {
+    let server = server_responding_with(ResponseTemplate::new(200).set_body_string("2001:db8::1")).await;
+
+    let ip = get_current_public_ip(&reqwest::Client::new(), &server.uri()).await;
+
+    assert_eq!(ip, Ok("2001:db8::1".to_string()));
+}
+
+#[tokio::test]
+async fn rejects_responses_that_are_not_ip_addresses() {
+    let server = server_responding_with(ResponseTemplate::new(200).set_body_string("<html>captive portal</html>")).await;
+
+    let ip = get_current_public_ip(&reqwest::Client::new(), &server.uri()).await;
+
+    assert_eq!(ip, Err(FailureKind::Network));
+}
+
+#[tokio::test]
+async fn rejects_error_responses() {
+    let server = server_responding_with(ResponseTemplate::new(503).set_body_string("1.2.3.4")).await;
+
+    let ip = get_current_public_ip(&reqwest::Client::new(), &server.uri()).await;
+
+    assert_eq!(ip, Err(FailureKind::Network));
+}
+
+#[tokio::test]
+async fn classifies_rate_limits() {
+    let server = server_responding_with(ResponseTemplate::new(429)).await;
+
+    let ip = get_current_public_ip(&reqwest::Client::new(), &server.uri()).await;
+
+    assert_eq!(ip, Err(FailureKind::RateLimit));
+}
+
+/// Starts a resolver answering the first question with the provided record, returning its port.
+async fn resolver_answering(record_type: u16, data: &'static [u8]) -> u16 {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = socket.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let mut query = [0; 512];
+        let (length, peer) = socket.recv_from(&mut query).await.unwrap();
+
+        let mut response = query[..length].to_vec();
+        response[2] |= 0x80;
+        response[7] = 1;
+        response.extend_from_slice(&[0xc0, 12]);
+        response.extend_from_slice(&record_type.to_be_bytes());
+        response.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+        response.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        response.extend_from_slice(data);
+        socket.send_to(&response, peer).await.unwrap();
+    });
+
+    port
+}
+
+#[tokio::test]
+async fn detects_the_public_ip_over_dns() {
+    let port = resolver_answering(1, &[203, 0, 113, 7]).await;
+    let url = format!("dns://127.0.0.1:{}/myip.example.com", port);
+
+    let ip = get_public_ip_of_family(&reqwest::Client::new(), &url, false).await;
+
+    assert_eq!(ip, Ok("203.0.113.7".to_string()));
+}
+
+#[tokio::test]
+async fn reads_the_public_ip_from_txt_answers() {
+    let port = resolver_answering(16, b"\x0b203.0.113.7").await;
+    let url = format!("dns://127.0.0.1:{}/whoami.example.com?type=TXT&class=CH", port);
+
+    let ip = get_public_ip_of_family(&reqwest::Client::new(), &url, false).await;
+
+    assert_eq!(ip, Ok("203.0.113.7".to_string()));
+}
+
+#[test]
+fn requires_an_explicit_transport() {
+    assert_eq!(Transport::of("https://api.ipify.org"), Ok(Transport::Https));
+    assert_eq!(Transport::of("http://ipv4.icanhazip.com"), Ok(Transport::Http));
+    assert_eq!(Transport::of("dns://208.67.222.222/myip.opendns.com"), Ok(Transport::Dns));
+    assert!(Transport::of("api.ipify.org").is_err());
+    assert!(Transport::of("dns://208.67.222.222").is_err());
+    assert!(Transport::of("dns://1.1.1.1/whoami.cloudflare?type=MX").is_err());
+}
+
+#[test]
+fn only_accepts_public_addresses() {
+    for ip in ["5.6.7.8", "2606:4700::1111"] {
+        assert!(is_public(&ip.parse().unwrap()), "{}", ip);
+    }
+
+    for ip in ["127.0.0.1", "192.168.1.1", "10.0.0.1", "100.64.0.1", "169.254.1.1", "0.0.0.0", "::1", "fd00::1", "fe80::1"] {
+        assert!(!is_public(&ip.parse().unwrap()), "{}", ip);
+    }
+}
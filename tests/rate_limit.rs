@@ -0,0 +1,43 @@
+use cloudflare_dynamic_ip_updater::rate_limit::RateLimiter;
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[tokio::test(start_paused = true)]
+async fn burst_is_served_immediately() {
+    let limiter = RateLimiter::new(100, Duration::from_secs(90));
+    let started = Instant::now();
+
+    for _ in 0..10 {
+        limiter.acquire().await;
+    }
+
+    assert_eq!(started.elapsed(), Duration::ZERO);
+}
+
+#[tokio::test(start_paused = true)]
+async fn requests_past_the_burst_wait_for_a_refill() {
+    // A burst of 10, then 90 requests refilled over 90 seconds.
+    let limiter = RateLimiter::new(100, Duration::from_secs(90));
+    let started = Instant::now();
+
+    for _ in 0..12 {
+        limiter.acquire().await;
+    }
+
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(1999) && elapsed <= Duration::from_millis(2001), "{:?}", elapsed);
+}
+
+#[tokio::test(start_paused = true)]
+async fn quota_is_never_exceeded_within_a_period() {
+    let limiter = RateLimiter::new(100, Duration::from_secs(90));
+    let started = Instant::now();
+    let mut sent = 0;
+
+    while started.elapsed() < Duration::from_secs(90) {
+        limiter.acquire().await;
+        sent += 1;
+    }
+
+    assert!(sent <= 101, "{} requests sent", sent);
+}
@@ -0,0 +1,25 @@
+use cloudflare_dynamic_ip_updater::config::{Config, ConfigError};
+use cloudflare_dynamic_ip_updater::reporting;
+
+#[test]
+fn is_disabled_without_a_dsn() {
+    let config = Config::parse("").unwrap();
+
+    assert!(reporting::init(&config).is_ok());
+}
+
+#[cfg(not(feature = "sentry"))]
+#[test]
+fn rejects_a_dsn_without_the_sentry_feature() {
+    let config = Config::parse("[sentry]\ndsn = \"https://key@sentry.example.com/1\"").unwrap();
+
+    assert!(matches!(reporting::init(&config), Err(ConfigError::Invalid { key: "sentry.dsn", .. })));
+}
+
+#[cfg(feature = "sentry")]
+#[test]
+fn rejects_an_invalid_dsn() {
+    let config = Config::parse("[sentry]\ndsn = \"not a dsn\"").unwrap();
+
+    assert!(matches!(reporting::init(&config), Err(ConfigError::Invalid { key: "sentry.dsn", .. })));
+}
@@ -0,0 +1,45 @@
+use cloudflare_dynamic_ip_updater::secret::{self, SecretError};
+
+#[test]
+fn encrypted_token_round_trips() {
+    let encrypted = secret::encrypt_token("my-api-token", b"passphrase");
+
+    assert!(secret::is_encrypted(&encrypted));
+    assert!(!encrypted.contains("my-api-token"));
+    assert_eq!(secret::decrypt_token(&encrypted, b"passphrase").unwrap(), "my-api-token");
+}
+
+#[test]
+fn encrypting_twice_gives_different_values() {
+    assert_ne!(secret::encrypt_token("my-api-token", b"passphrase"),
+        secret::encrypt_token("my-api-token", b"passphrase"));
+}
+
+#[test]
+fn wrong_key_is_rejected() {
+    let encrypted = secret::encrypt_token("my-api-token", b"passphrase");
+
+    assert!(matches!(secret::decrypt_token(&encrypted, b"other"), Err(SecretError::WrongKey)));
+}
+
+#[test]
+fn malformed_value_is_rejected() {
+    assert!(matches!(secret::decrypt_token("encrypted:not base64!", b"passphrase"), Err(SecretError::Malformed)));
+    assert!(matches!(secret::decrypt_token("encrypted:AAAA", b"passphrase"), Err(SecretError::Malformed)));
+}
+
+#[cfg(unix)]
+#[test]
+fn token_command_output_is_used() {
+    assert_eq!(secret::run_token_command("printf 'my-api-token\\nsecond line\\n'").unwrap(), "my-api-token");
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_token_command_is_reported() {
+    let error = secret::run_token_command("echo nope >&2; exit 3").unwrap_err();
+
+    assert!(matches!(error, SecretError::Command { .. }));
+    assert!(error.to_string().contains("nope"));
+    assert!(matches!(secret::run_token_command("true"), Err(SecretError::Command { .. })));
+}
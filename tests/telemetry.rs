@@ -0,0 +1,17 @@
+use cloudflare_dynamic_ip_updater::config::Config;
+use cloudflare_dynamic_ip_updater::telemetry;
+
+#[test]
+fn is_disabled_without_an_endpoint() {
+    let config = Config::parse("").unwrap();
+
+    assert!(telemetry::init(&config).is_ok());
+}
+
+#[cfg(not(feature = "otlp"))]
+#[test]
+fn rejects_an_endpoint_without_the_otlp_feature() {
+    let config = Config::parse("[telemetry]\notlp_endpoint = \"http://localhost:4318\"").unwrap();
+
+    assert!(matches!(telemetry::init(&config), Err(cloudflare_dynamic_ip_updater::config::ConfigError::Invalid { key: "telemetry.otlp_endpoint", .. })));
+}
@@ -0,0 +1,20 @@
+use cloudflare_dynamic_ip_updater::template::render;
+
+#[test]
+fn renders_the_hostname_and_environment_variables() {
+    std::env::set_var("CLOUDFLARE_DDNS_TEST_SITE", "berlin");
+
+    let rendered = render("{hostname}.{env:CLOUDFLARE_DDNS_TEST_SITE}.fleet.example.com").unwrap();
+    let (hostname, rest) = rendered.split_once('.').unwrap();
+
+    assert!(!hostname.is_empty());
+    assert_eq!(rest, "berlin.fleet.example.com");
+    assert_eq!(render("home.example.com").unwrap(), "home.example.com");
+}
+
+#[test]
+fn rejects_unknown_or_unset_placeholders() {
+    assert!(render("{user}.example.com").is_err());
+    assert!(render("{env:CLOUDFLARE_DDNS_TEST_UNSET}.example.com").is_err());
+    assert!(render("{hostname.example.com").is_err());
+}
@@ -0,0 +1,42 @@
+use chrono::NaiveTime;
+use cloudflare_dynamic_ip_updater::update_window::{TimeWindow, UpdateWindows};
+use std::time::Duration;
+
+fn at(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn windows(allowed: &[&str], blocked: &[&str]) -> UpdateWindows {
+    let strings = |w: &[&str]| w.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    UpdateWindows::new(&strings(allowed), &strings(blocked)).unwrap()
+}
+
+#[test]
+fn blocks_updates_within_the_blocked_windows() {
+    let windows = windows(&[], &["02:00-02:30"]);
+
+    assert!(windows.allows(at(1, 59)));
+    assert!(!windows.allows(at(2, 0)));
+    assert!(!windows.allows(at(2, 29)));
+    assert!(windows.allows(at(2, 30)));
+    assert_eq!(windows.wait_until_open(at(2, 10)), Duration::from_secs(20 * 60));
+    assert_eq!(windows.wait_until_open(at(3, 0)), Duration::ZERO);
+}
+
+#[test]
+fn only_allows_updates_within_the_allowed_windows() {
+    let windows = windows(&["22:00-06:00"], &["02:00-02:30"]);
+
+    assert!(windows.allows(at(23, 0)));
+    assert!(windows.allows(at(5, 59)));
+    assert!(!windows.allows(at(2, 15)));
+    assert!(!windows.allows(at(12, 0)));
+    assert_eq!(windows.wait_until_open(at(12, 0)), Duration::from_secs(10 * 3600));
+}
+
+#[test]
+fn rejects_invalid_windows() {
+    assert!(TimeWindow::parse("02:00").is_err());
+    assert!(TimeWindow::parse("25:00-02:00").is_err());
+    assert!(UpdateWindows::new(&[], &["00:00-12:00".to_string(), "12:00-00:00".to_string()]).is_err());
+}
@@ -0,0 +1,1897 @@
+use cloudflare_dynamic_ip_updater::clock::{Clock, SimulatedClock};
+use cloudflare_dynamic_ip_updater::cloudflare_client::CloudflareClient;
+use cloudflare_dynamic_ip_updater::config::{Config, ConfigOverrides};
+use cloudflare_dynamic_ip_updater::control::ControlHandle;
+use cloudflare_dynamic_ip_updater::events::EventStream;
+use cloudflare_dynamic_ip_updater::failure::FailureKind;
+use cloudflare_dynamic_ip_updater::state::State;
+use chrono::TimeZone;
+use cloudflare_dynamic_ip_updater::updater::Updater;
+use serde_json::json;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CONFIG: &str = r#"
+[general]
+wait_duration = 60
+max_consecutive_failures = 3
+
+[cloudflare]
+zone_id = "zone"
+api_token = "token"
+dns_record_id = "record"
+"#;
+
+fn record_response(content: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "result": {
+            "id": "record",
+            "name": "home.example.com",
+            "type": if content.contains(':') { "AAAA" } else { "A" },
+            "content": content,
+            "ttl": 300,
+            "proxied": false
+        },
+        "success": true,
+        "errors": [],
+        "messages": []
+    }))
+}
+
+struct Harness {
+    cloudflare: MockServer,
+    ip: MockServer,
+    clock: Arc<SimulatedClock>,
+    handle: Arc<ControlHandle>,
+}
+
+impl Harness {
+    async fn start() -> Harness {
+        Harness {
+            cloudflare: MockServer::start().await,
+            ip: MockServer::start().await,
+            clock: Arc::new(SimulatedClock::default()),
+            handle: Arc::new(ControlHandle::in_memory(State::default())),
+        }
+    }
+
+    async fn public_ip(&self, response: ResponseTemplate) {
+        Mock::given(method("GET"))
+            .respond_with(response)
+            .mount(&self.ip)
+            .await;
+    }
+
+    fn updater(&self) -> Updater<SimulatedClock> {
+        self.updater_with(CONFIG)
+    }
+
+    fn updater_with(&self, config: &str) -> Updater<SimulatedClock> {
+        let config = Config::parse(config).unwrap();
+        let cloudflare_client = CloudflareClient::new(reqwest::Client::new(), "token")
+            .with_base_url(&self.cloudflare.uri());
+
+        Updater::new(&config, self.handle.clone(), self.clock.clone())
+            .unwrap()
+            .with_cloudflare_client(cloudflare_client)
+            .with_ip_check_url(&self.ip.uri())
+            .with_ipv6_check_urls(&[&self.ip.uri()])
+    }
+}
+
+#[tokio::test]
+async fn waits_the_configured_interval_between_checks() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater();
+    for _ in 0..3 {
+        updater.tick().await.unwrap();
+    }
+
+    assert_eq!(harness.clock.sleeps(), vec![Duration::from_secs(60); 3]);
+    assert_eq!(harness.ip.received_requests().await.unwrap().len(), 3);
+    assert_eq!(harness.handle.status().state.last_checked, Some(harness.clock.now_utc()));
+}
+
+#[tokio::test]
+async fn updates_the_record_when_the_ip_changes() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater();
+    updater.tick().await.unwrap();
+    updater.tick().await.unwrap();
+
+    let state = harness.handle.status().state;
+    assert_eq!(state.records[0].content.as_deref(), Some("5.6.7.8"));
+    assert_eq!(state.history.len(), 1);
+    assert_eq!(state.history[0].from.as_deref(), Some("1.2.3.4"));
+    assert_eq!(state.last_changed, Some(harness.clock.now_utc() - Duration::from_secs(60)));
+}
+
+/// Buffer shared with the event stream writing to it.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn writes_a_json_event_for_each_lifecycle_occurrence() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("5.6.7.8"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let buffer = SharedBuffer::default();
+    let mut updater = harness.updater().with_event_stream(EventStream::new(buffer.clone()));
+    let _ = updater.tick().await;
+    updater.tick().await.unwrap();
+
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let events: Vec<serde_json::Value> = output.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    let names: Vec<&str> = events.iter().map(|e| e["event"].as_str().unwrap()).collect();
+
+    assert_eq!(names, vec!["check_started", "ip_detected", "error", "check_started", "ip_detected", "update_applied"]);
+    assert_eq!(events[1]["ipv4"], "5.6.7.8");
+    assert_eq!(events[2]["operation"], "update_records");
+    assert_eq!(events[5]["name"], "home.example.com");
+    assert_eq!(events[5]["current"], "5.6.7.8");
+    assert!(events.iter().all(|e| e["timestamp"].is_string()));
+}
+
+#[tokio::test]
+async fn gives_up_after_max_consecutive_failures() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(503)).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater();
+
+    assert_eq!(updater.tick().await, Ok(()));
+    assert_eq!(updater.tick().await, Ok(()));
+    assert_eq!(updater.tick().await, Err(FailureKind::Network));
+
+    // The public IP is detected again after 5s, then 10s, rather than a full interval.
+    assert_eq!(harness.clock.elapsed(), Duration::from_secs(75));
+}
+
+#[tokio::test]
+async fn keeps_monitoring_without_updating_while_paused() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(0)
+        .mount(&harness.cloudflare)
+        .await;
+    harness.handle.execute("pause");
+
+    let mut updater = harness.updater();
+    updater.tick().await.unwrap();
+    updater.tick().await.unwrap();
+
+    let state = harness.handle.status().state;
+    assert_eq!(harness.ip.received_requests().await.unwrap().len(), 2);
+    assert_eq!(state.public_ip.as_deref(), Some("5.6.7.8"));
+    assert_eq!(state.records[0].content.as_deref(), Some("1.2.3.4"));
+    assert!(state.history.is_empty());
+}
+
+#[tokio::test]
+async fn skips_a_failing_ip_provider_until_its_cooldown_ends() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(503)).await;
+    let backup = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("1.2.3.4"))
+        .mount(&backup)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater().with_ip_check_urls(&[&harness.ip.uri(), &backup.uri()]);
+    for _ in 0..5 {
+        updater.tick().await.unwrap();
+    }
+
+    // The failing provider is skipped once it failed three times in a row.
+    assert_eq!(harness.ip.received_requests().await.unwrap().len(), 3);
+    assert_eq!(backup.received_requests().await.unwrap().len(), 5);
+    assert_eq!(harness.handle.status().state.public_ip.as_deref(), Some("1.2.3.4"));
+
+    harness.clock.advance(Duration::from_secs(300));
+    updater.tick().await.unwrap();
+
+    assert_eq!(harness.ip.received_requests().await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn asks_the_fastest_provider_first_with_the_latency_strategy() {
+    let harness = Harness::start().await;
+    let slow = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("1.2.3.4").set_delay(Duration::from_millis(200)))
+        .mount(&slow)
+        .await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(&format!("{}\n[ip_check]\nstrategy = \"latency\"\n", CONFIG))
+        .with_ip_check_urls(&[&slow.uri(), &harness.ip.uri()]);
+    for _ in 0..3 {
+        updater.tick().await.unwrap();
+    }
+
+    // Both are asked by the first probe, then only the faster one.
+    assert_eq!(slow.received_requests().await.unwrap().len(), 1);
+    assert_eq!(harness.ip.received_requests().await.unwrap().len(), 3);
+
+    let providers = harness.handle.status().state.providers;
+    assert_eq!(providers.iter().map(|p| p.successes).collect::<Vec<_>>(), vec![3, 1]);
+    assert!(providers[1].latency_ms.unwrap() >= 200);
+}
+
+#[tokio::test]
+async fn checks_each_record_on_its_own_interval() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/fast"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(2)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/slow"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [general]
+        wait_duration = 60
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [[records]]
+        dns_record_id = "fast"
+        refresh_interval = 120
+
+        [[records]]
+        dns_record_id = "slow"
+        wait_duration = 180
+    "#);
+
+    for _ in 0..3 {
+        updater.tick().await.unwrap();
+    }
+
+    // The fast record is fetched at 60s and refreshed at 180s, the slow one only checked at 180s.
+    assert_eq!(harness.clock.sleeps(), vec![Duration::from_secs(60); 3]);
+    assert_eq!(harness.handle.status().state.records.len(), 2);
+}
+
+#[tokio::test]
+async fn creates_no_record_while_paused() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": [],
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&harness.cloudflare)
+        .await;
+    harness.handle.execute("pause");
+
+    let mut updater = harness.updater_with(r#"
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [hostnames]
+        zone = "example.com"
+        names = ["home"]
+    "#);
+
+    updater.tick().await.unwrap();
+
+    assert!(harness.handle.status().state.records.is_empty());
+}
+
+#[tokio::test]
+async fn randomizes_each_wait_within_the_jitter() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [general]
+        wait_duration = 60
+        wait_jitter = 10
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#);
+
+    for _ in 0..20 {
+        updater.tick().await.unwrap();
+    }
+
+    let sleeps = harness.clock.sleeps();
+    assert!(sleeps.iter().all(|s| (Duration::from_secs(50)..=Duration::from_secs(70)).contains(s)));
+    assert!(sleeps.iter().any(|s| *s != sleeps[0]));
+}
+
+#[tokio::test]
+async fn retries_failed_updates_with_a_backoff() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(2)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater();
+    for _ in 0..4 {
+        updater.tick().await.unwrap();
+    }
+
+    let sleeps: Vec<u64> = harness.clock.sleeps().iter().map(Duration::as_secs).collect();
+    assert_eq!(sleeps, vec![60, 5, 10, 60]);
+}
+
+#[tokio::test]
+async fn keeps_backing_off_after_a_restart() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let config = r#"
+        [general]
+        wait_duration = 60
+        max_consecutive_failures = 4
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#;
+
+    let mut updater = harness.updater_with(config);
+    for _ in 0..2 {
+        updater.tick().await.unwrap();
+    }
+
+    let backoff = harness.handle.status().state.backoff;
+    assert_eq!(backoff.consecutive_failures, 2);
+    assert_eq!(backoff.streaks[0].operation, "update_records");
+    assert_eq!(backoff.streaks[0].failures, 2);
+
+    // The restarted updater waits for the pending retry, then keeps growing the delay.
+    let mut updater = harness.updater_with(config);
+    updater.tick().await.unwrap();
+    assert_eq!(updater.tick().await, Err(FailureKind::Api));
+
+    let sleeps: Vec<u64> = harness.clock.sleeps().iter().map(Duration::as_secs).collect();
+    assert_eq!(sleeps, vec![60, 5, 10, 20]);
+}
+
+#[tokio::test]
+async fn holds_updates_back_until_the_update_window_opens() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    // Block updates during the second hour of the simulated clock, in local time.
+    let start = chrono::Local.timestamp_opt(3600, 0).unwrap().time();
+    let window = format!("{}-{}", start.format("%H:%M"), (start + chrono::Duration::minutes(30)).format("%H:%M"));
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [general]
+        wait_duration = 3600
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+
+        [update_windows]
+        blocked = ["{}"]
+    "#, window));
+    for _ in 0..2 {
+        updater.tick().await.unwrap();
+    }
+
+    let sleeps: Vec<u64> = harness.clock.sleeps().iter().map(Duration::as_secs).collect();
+    assert_eq!(sleeps, vec![3600, 1800]);
+    assert_eq!(harness.handle.status().state.stats.updates, 1);
+}
+
+#[tokio::test]
+async fn retries_updates_not_confirmed_by_the_response() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(2)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("1.2.3.4"))
+        .up_to_n_times(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater();
+    for _ in 0..2 {
+        updater.tick().await.unwrap();
+    }
+
+    let sleeps: Vec<u64> = harness.clock.sleeps().iter().map(Duration::as_secs).collect();
+    assert_eq!(sleeps, vec![60, 5]);
+    assert_eq!(harness.handle.status().state.stats.updates, 1);
+}
+
+#[tokio::test]
+async fn only_asks_cloudflare_when_the_record_resolves_to_another_ip() {
+    let harness = Harness::start().await;
+    let resolver = MockServer::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(2)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(query_param("name", "home.example.com"))
+        .and(query_param("type", "A"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "Status": 0,
+            "Answer": [{ "name": "home.example.com", "type": 1, "TTL": 300, "data": "1.2.3.4" }],
+        })))
+        .up_to_n_times(1)
+        .mount(&resolver)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "Status": 0,
+            "Answer": [{ "name": "home.example.com", "type": 1, "TTL": 300, "data": "9.9.9.9" }],
+        })))
+        .mount(&resolver)
+        .await;
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [general]
+        refresh_interval = 1
+
+        [dns_precheck]
+        enabled = true
+        doh_url = "{}/dns-query"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#, resolver.uri()));
+
+    // Fetched once to learn the name, skipped while it resolves to the public IP, then fetched
+    // again once it resolves to another one.
+    for _ in 0..3 {
+        updater.tick().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn checks_proxied_records_through_the_api_only() {
+    let harness = Harness::start().await;
+    let resolver = MockServer::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "id": "record", "name": "home.example.com", "type": "A", "content": "1.2.3.4", "ttl": 1, "proxied": true },
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(2)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "Status": 0 })))
+        .expect(0)
+        .mount(&resolver)
+        .await;
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [general]
+        refresh_interval = 1
+
+        [dns_precheck]
+        enabled = true
+        doh_url = "{}/dns-query"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#, resolver.uri()));
+
+    for _ in 0..2 {
+        updater.tick().await.unwrap();
+    }
+
+    assert!(harness.handle.status().state.records[0].proxied);
+}
+
+#[tokio::test]
+async fn checks_on_the_configured_schedule() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    harness.clock.advance(Duration::from_secs(90));
+    let mut updater = harness.updater_with(r#"
+        [general]
+        schedule = "*/5 * * * *"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#);
+
+    for _ in 0..2 {
+        updater.tick().await.unwrap();
+    }
+
+    // The first check waits for the next multiple of five minutes rather than a full interval.
+    assert_eq!(harness.clock.sleeps(), vec![Duration::from_secs(210), Duration::from_secs(300)]);
+}
+
+#[test]
+fn rejects_an_invalid_schedule() {
+    let config = Config::parse(r#"
+        [general]
+        schedule = "every five minutes"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#).unwrap();
+
+    let handle = Arc::new(ControlHandle::in_memory(State::default()));
+    let result = Updater::new(&config, handle, Arc::new(SimulatedClock::default()));
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn counts_checks_updates_and_failures() {
+    let harness = Harness::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("5.6.7.8"))
+        .up_to_n_times(1)
+        .mount(&harness.ip)
+        .await;
+    harness.public_ip(ResponseTemplate::new(503)).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater();
+    updater.tick().await.unwrap();
+    updater.tick().await.unwrap();
+
+    let stats = harness.handle.status().state.stats;
+    assert_eq!(stats.checks, 2);
+    assert_eq!(stats.updates, 1);
+    assert_eq!(stats.failures.network, 1);
+    assert_eq!(stats.uptime(harness.clock.now_utc()), Some(time::Duration::seconds(120)));
+}
+
+#[tokio::test]
+async fn uses_the_zone_and_token_of_each_record() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/personal-zone/dns_records/home"))
+        .and(header("Authorization", "Bearer personal-token"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/work-zone/dns_records/office"))
+        .and(header("Authorization", "Bearer work-token"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let config = Config::parse(r#"
+        [cloudflare]
+        zone_id = "personal-zone"
+        api_token = "personal-token"
+
+        [[records]]
+        dns_record_id = "home"
+
+        [[records]]
+        dns_record_id = "office"
+        zone_id = "work-zone"
+        api_token = "work-token"
+    "#).unwrap();
+
+    let mut updater = Updater::new(&config, harness.handle.clone(), harness.clock.clone())
+        .unwrap()
+        .with_cloudflare_base_url(&harness.cloudflare.uri())
+        .with_ip_check_url(&harness.ip.uri());
+
+    updater.tick().await.unwrap();
+}
+
+#[tokio::test]
+async fn selects_the_records_matching_a_name_pattern() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": [
+                { "id": "home", "name": "home.example.com", "type": "A", "content": "1.2.3.4", "ttl": 300 },
+                { "id": "home6", "name": "home.example.com", "type": "AAAA", "content": "::1", "ttl": 300 },
+                { "id": "mail", "name": "mail.example.org", "type": "A", "content": "1.2.3.4", "ttl": 300 }
+            ],
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/home"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [[records]]
+        name = "*.example.com"
+        type = "a"
+    "#);
+
+    updater.tick().await.unwrap();
+
+    assert_eq!(harness.handle.status().state.records.len(), 1);
+}
+
+#[tokio::test]
+async fn discovers_the_address_records_carrying_the_marker() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": [
+                { "id": "home", "name": "home.example.com", "type": "A", "content": "1.2.3.4", "ttl": 300, "comment": "ddns: home router" },
+                { "id": "nas", "name": "nas.example.com", "type": "AAAA", "content": "::1", "ttl": 300, "tags": ["ddns:auto"] },
+                { "id": "mail", "name": "mail.example.com", "type": "A", "content": "1.2.3.4", "ttl": 300 },
+                { "id": "txt", "name": "home.example.com", "type": "TXT", "content": "ddns", "ttl": 300, "tags": ["ddns"] }
+            ],
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/home"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/nas"))
+        .respond_with(record_response("::1"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [discovery]
+        marker = "ddns"
+    "#);
+
+    updater.tick().await.unwrap();
+
+    let mut ids: Vec<String> = harness.handle.status().state.records.into_iter().map(|r| r.id).collect();
+    ids.sort();
+    assert_eq!(ids, ["home", "nas"]);
+}
+
+#[tokio::test]
+async fn keeps_the_marker_tags_of_discovered_records_when_updating_them() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    let listing = |content: &str| ResponseTemplate::new(200).set_body_json(json!({
+        "result": [
+            { "id": "home", "name": "home.example.com", "type": "A", "content": content, "ttl": 300, "tags": ["ddns:auto"] },
+            { "id": "vpn", "name": "vpn.example.com", "type": "A", "content": content, "ttl": 300, "tags": ["ddns", "site:home"] }
+        ],
+        "success": true,
+        "errors": [],
+        "messages": []
+    }));
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records"))
+        .respond_with(listing("1.2.3.4"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records"))
+        .respond_with(listing("5.6.7.8"))
+        .mount(&harness.cloudflare)
+        .await;
+    for (id, tags) in [("home", json!(["ddns:auto"])), ("vpn", json!(["ddns", "site:home"]))] {
+        Mock::given(method("GET"))
+            .and(path(format!("/zones/zone/dns_records/{}", id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": { "id": id, "name": format!("{}.example.com", id), "type": "A", "content": "1.2.3.4", "ttl": 300, "tags": tags },
+                "success": true,
+                "errors": [],
+                "messages": []
+            })))
+            .mount(&harness.cloudflare)
+            .await;
+    }
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/batch"))
+        .and(body_partial_json(json!({
+            "puts": [
+                { "id": "home", "content": "5.6.7.8", "tags": ["ddns:auto"] },
+                { "id": "vpn", "content": "5.6.7.8", "tags": ["ddns", "site:home"] }
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "puts": [
+                { "id": "home", "name": "home.example.com", "type": "A", "content": "5.6.7.8", "ttl": 300, "tags": ["ddns:auto"] },
+                { "id": "vpn", "name": "vpn.example.com", "type": "A", "content": "5.6.7.8", "ttl": 300, "tags": ["ddns", "site:home"] }
+            ] },
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [general]
+        wait_duration = 60
+        selection_refresh = 60
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [discovery]
+        marker = "ddns"
+    "#);
+
+    // The second check lists the records again, finding the marker they were updated with.
+    updater.tick().await.unwrap();
+    updater.tick().await.unwrap();
+
+    let mut ids: Vec<String> = harness.handle.status().state.records.into_iter().map(|r| r.id).collect();
+    ids.sort();
+    assert_eq!(ids, ["home", "vpn"]);
+
+    let requests = harness.cloudflare.received_requests().await.unwrap();
+    assert_eq!(requests.iter().filter(|r| r.url.path() == "/zones/zone/dns_records").count(), 2);
+}
+
+#[tokio::test]
+async fn updates_the_records_of_a_zone_in_a_single_batch() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/batch"))
+        .and(body_partial_json(json!({
+            "puts": [
+                { "id": "home", "content": "5.6.7.8" },
+                { "id": "nas", "content": "5.6.7.8" }
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": {
+                "puts": [
+                    { "id": "home", "name": "home.example.com", "type": "A", "content": "5.6.7.8", "ttl": 300 },
+                    { "id": "nas", "name": "nas.example.com", "type": "A", "content": "5.6.7.8", "ttl": 300 }
+                ]
+            },
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [[records]]
+        dns_record_id = "home"
+
+        [[records]]
+        dns_record_id = "nas"
+    "#);
+
+    updater.tick().await.unwrap();
+
+    let state = harness.handle.status().state;
+    assert_eq!(state.stats.updates, 2);
+    assert_eq!(state.history.len(), 2);
+}
+
+#[tokio::test]
+async fn fetches_records_concurrently() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4").set_delay(Duration::from_millis(400)))
+        .expect(4)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [general]
+        update_concurrency = 4
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [[records]]
+        dns_record_id = "a"
+
+        [[records]]
+        dns_record_id = "b"
+
+        [[records]]
+        dns_record_id = "c"
+
+        [[records]]
+        dns_record_id = "d"
+    "#);
+
+    let started = std::time::Instant::now();
+    updater.tick().await.unwrap();
+
+    // Sequential requests would take at least 1.6s.
+    assert!(started.elapsed() < Duration::from_millis(1200));
+}
+
+#[tokio::test]
+async fn reuses_the_public_ip_within_the_cache_ttl() {
+    let harness = Harness::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("1.2.3.4"))
+        .expect(2)
+        .mount(&harness.ip)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [general]
+        wait_duration = 60
+
+        [ip_check]
+        cache_ttl = 90
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#);
+
+    // Detected at 60s, reused at 120s, detected again at 180s.
+    for _ in 0..3 {
+        updater.tick().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn revalidates_the_records_of_a_zone_after_an_api_error() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/stale"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/stale"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "result": null,
+            "success": false,
+            "errors": [{ "code": 81044, "message": "Record does not exist." }],
+            "messages": []
+        })))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/current"))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(2)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [retry.cloudflare]
+        base = 0
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [[records]]
+        dns_record_id = "stale"
+
+        [[records]]
+        dns_record_id = "current"
+    "#);
+
+    // The up to date record is fetched again after the update of the other one is rejected.
+    updater.tick().await.unwrap();
+    updater.tick().await.unwrap();
+}
+
+#[tokio::test]
+async fn publishes_the_delegated_prefix_with_the_suffix_of_the_host() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("2001:db8:1:2::1")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/nas"))
+        .respond_with(record_response("2001:db8:9:9:211:22ff:fe33:4455"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/nas"))
+        .and(body_partial_json(json!({ "content": "2001:db8:1:2:211:22ff:fe33:4455" })))
+        .respond_with(record_response("2001:db8:1:2:211:22ff:fe33:4455"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [ip_check]
+        address_policy = "v6-only"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [[records]]
+        dns_record_id = "nas"
+        ipv6_suffix = "00:11:22:33:44:55"
+    "#);
+
+    updater.tick().await.unwrap();
+}
+
+#[tokio::test]
+async fn leaves_a_record_written_in_another_notation_of_the_ip_alone() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("2001:db8::1")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("2001:0DB8:0000::0001"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("2001:db8::1"))
+        .expect(0)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [ip_check]
+        address_policy = "v6-only"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#);
+
+    updater.tick().await.unwrap();
+
+    assert_eq!(harness.handle.status().state.stats.updates, 0);
+}
+
+#[tokio::test]
+async fn publishes_each_address_family_in_its_own_records() {
+    let harness = Harness::start().await;
+    let ipv6 = MockServer::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("2001:db8::2"))
+        .mount(&ipv6)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/v4"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/v6"))
+        .respond_with(record_response("2001:db8::1"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/batch"))
+        .and(body_partial_json(json!({
+            "puts": [
+                { "id": "v4", "type": "A", "content": "5.6.7.8" },
+                { "id": "v6", "type": "AAAA", "content": "2001:db8::2" }
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "puts": [] },
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [ip_check]
+        address_policy = "both"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [[records]]
+        dns_record_id = "v4"
+
+        [[records]]
+        dns_record_id = "v6"
+    "#).with_ipv6_check_urls(&[&ipv6.uri()]);
+
+    updater.tick().await.unwrap();
+}
+
+#[tokio::test]
+async fn rejects_an_address_of_the_wrong_family() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("2001:db8::2")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater();
+    updater.tick().await.unwrap();
+
+    let stats = harness.handle.status().state.stats;
+    assert_eq!(stats.failures.network, 1);
+    assert_eq!(stats.updates, 0);
+}
+
+#[tokio::test]
+async fn keeps_the_current_configuration_when_the_new_one_is_invalid() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let path = std::env::temp_dir().join(format!("cloudflare-ddns-reload-{}.toml", std::process::id()));
+    std::fs::write(&path, "[general]\nwait_duration = \"soon\"\n").unwrap();
+    let overrides = ConfigOverrides { path: Some(path.clone()), ..ConfigOverrides::default() };
+
+    let mut updater = harness.updater();
+    updater.reload(&overrides);
+    std::fs::remove_file(&path).unwrap();
+    updater.tick().await.unwrap();
+
+    let state = harness.handle.status().state;
+    assert_eq!(state.stats.updates, 1);
+    assert!(state.recent_errors[0].message.starts_with("Keeping the current configuration"));
+}
+
+#[tokio::test]
+async fn records_of_a_group_share_its_settings_and_notifiers() {
+    let harness = Harness::start().await;
+    let notifications = MockServer::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/record"))
+        .and(body_partial_json(json!({ "content": "5.6.7.8", "ttl": 120, "proxied": true })))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "title": "IP of home.example.com changed" })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&notifications)
+        .await;
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [[groups]]
+        name = "home"
+        zone_id = "zone"
+        api_token = "token"
+        ttl = 120
+        proxied = true
+        notification_urls = ["{}"]
+
+        [[groups.records]]
+        dns_record_id = "record"
+    "#, notifications.uri().replace("http://", "json://")));
+
+    updater.tick().await.unwrap();
+
+    // Notifications are sent in the background.
+    for _ in 0..50 {
+        if !notifications.received_requests().await.unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn publishes_a_pushed_ip_immediately() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "content": "5.6.7.8" })))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater();
+    updater.tick().await.unwrap();
+    harness.handle.push_ip("5.6.7.8".parse().unwrap());
+    updater.tick().await.unwrap();
+
+    assert_eq!(harness.ip.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn reads_the_public_ip_from_a_file() {
+    let harness = Harness::start().await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "content": "5.6.7.8" })))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let path = std::env::temp_dir().join(format!("cloudflare-ddns-wan-ip-{}", std::process::id()));
+    std::fs::write(&path, "5.6.7.8\n").unwrap();
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [ip_check]
+        ip_source = "file"
+        ip_file = "{}"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#, path.display()));
+
+    updater.tick().await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(harness.ip.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn touches_the_health_file_after_a_successful_check() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let path = std::env::temp_dir().join(format!("cloudflare-ddns-health-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [general]
+        health_file = "{}"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#, path.display()));
+
+    updater.tick().await.unwrap();
+
+    let checked_at = harness.clock.now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), checked_at);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn notifies_of_ip_changes() {
+    let harness = Harness::start().await;
+    let notifications = MockServer::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "title": "IP of home.example.com changed" })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&notifications)
+        .await;
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [notifications]
+        urls = ["{}"]
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#, notifications.uri().replace("http://", "json://")));
+
+    updater.tick().await.unwrap();
+
+    // Notifications are sent in the background.
+    for _ in 0..50 {
+        if !notifications.received_requests().await.unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn includes_the_location_of_the_new_ip_in_notifications() {
+    let harness = Harness::start().await;
+    let notifications = MockServer::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/5.6.7.8/json"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_json(json!({ "city": "Berlin", "country": "DE", "org": "AS3320 Deutsche Telekom AG" })))
+        .expect(1)
+        .mount(&notifications)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({
+            "message": "home.example.com now points to 5.6.7.8 (Berlin, DE, AS3320 Deutsche Telekom AG), previously 1.2.3.4.",
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&notifications)
+        .await;
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [notifications]
+        urls = ["{}"]
+
+        [geoip]
+        api_url = "{}/{{ip}}/json"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#, notifications.uri().replace("http://", "json://"), notifications.uri()));
+
+    updater.tick().await.unwrap();
+
+    for _ in 0..50 {
+        if notifications.received_requests().await.unwrap().len() > 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn notifies_when_the_network_provider_changes() {
+    let harness = Harness::start().await;
+    let notifications = MockServer::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.2.3.4/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "country": "DE", "org": "AS3320 Deutsche Telekom AG" })))
+        .mount(&notifications)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/5.6.7.8/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "country": "DE", "org": "AS3209 Vodafone GmbH" })))
+        .mount(&notifications)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({
+            "title": "Network provider of home.example.com changed",
+            "message": "home.example.com is now served by AS3209 Vodafone GmbH, previously AS3320 Deutsche Telekom AG.",
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&notifications)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&notifications)
+        .await;
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [notifications]
+        urls = ["{}"]
+
+        [geoip]
+        api_url = "{}/{{ip}}/json"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#, notifications.uri().replace("http://", "json://"), notifications.uri()));
+
+    updater.tick().await.unwrap();
+
+    for _ in 0..50 {
+        if notifications.received_requests().await.unwrap().len() > 3 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn pushes_successful_checks_to_uptime_kuma() {
+    let harness = Harness::start().await;
+    let kuma = MockServer::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/push/abc"))
+        .and(query_param("status", "up"))
+        .and(query_param("msg", "1.2.3.4"))
+        .and(query_param("ping", ""))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&kuma)
+        .await;
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [uptime_kuma]
+        push_url = "{}/api/push/abc?status=up&msg=OK&ping="
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#, kuma.uri()));
+
+    updater.tick().await.unwrap();
+}
+
+#[tokio::test]
+async fn plans_the_changes_without_sending_them() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(0)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let planned = harness.updater().plan().await.unwrap();
+
+    let lines: Vec<String> = planned.iter().map(|c| c.to_string()).collect();
+    assert_eq!(lines, ["~ home.example.com A 1.2.3.4 -> 5.6.7.8 (ttl 300)"]);
+    assert_eq!(harness.handle.status().state.stats.updates, 0);
+}
+
+#[tokio::test]
+async fn refuses_to_update_a_record_of_the_other_address_family() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("2001:db8::2")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("2001:db8::2"))
+        .expect(0)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [ip_check]
+        address_policy = "v6-only"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#);
+
+    updater.tick().await.unwrap();
+
+    let state = harness.handle.status().state;
+    assert_eq!(state.stats.failures.mismatch, 1);
+    assert!(state.recent_errors[0].message.contains("only an IPv6 address was detected"));
+}
+
+#[tokio::test]
+async fn takes_the_first_answer_when_racing_the_ip_providers() {
+    let harness = Harness::start().await;
+    let slow = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("1.1.1.1").set_delay(Duration::from_secs(2)))
+        .mount(&slow)
+        .await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("5.6.7.8"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [ip_check]
+        strategy = "fastest"
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#).with_ip_check_urls(&[&slow.uri(), &harness.ip.uri()]);
+
+    let started = std::time::Instant::now();
+    updater.tick().await.unwrap();
+
+    assert!(started.elapsed() < Duration::from_millis(1500));
+    assert_eq!(harness.handle.status().state.public_ip.as_deref(), Some("5.6.7.8"));
+}
+
+#[tokio::test]
+async fn creates_the_companion_txt_record_after_an_update() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("5.6.7.8"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": [],
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records"))
+        .and(body_partial_json(json!({ "type": "TXT", "name": "home.example.com" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "id": "txt", "name": "home.example.com", "type": "TXT", "content": "", "ttl": 300 },
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [[records]]
+        dns_record_id = "record"
+        txt_record = true
+    "#);
+
+    updater.tick().await.unwrap();
+
+    let requests = harness.cloudflare.received_requests().await.unwrap();
+    let txt = requests.iter().find(|r| r.url.path() == "/zones/zone/dns_records" && r.method == wiremock::http::Method::Post).unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&txt.body).unwrap();
+    assert!(body["content"].as_str().unwrap().contains(concat!("version=", env!("CARGO_PKG_VERSION"))));
+}
+
+#[tokio::test]
+async fn leaves_other_txt_records_of_the_name_alone() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("5.6.7.8"))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": [
+                { "id": "spf", "name": "home.example.com", "type": "TXT", "content": "\"v=spf1 -all\"", "ttl": 1 }
+            ],
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records/spf"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records"))
+        .and(body_partial_json(json!({ "type": "TXT", "name": "home.example.com" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "id": "txt", "name": "home.example.com", "type": "TXT", "content": "", "ttl": 300 },
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [[records]]
+        dns_record_id = "record"
+        txt_record = true
+    "#);
+
+    updater.tick().await.unwrap();
+}
+
+#[tokio::test]
+async fn finds_or_creates_the_records_of_each_hostname() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": [
+                { "id": "apex", "name": "example.com", "type": "A", "content": "1.2.3.4", "ttl": 1 }
+            ],
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/apex"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/zones/zone/dns_records"))
+        .and(body_partial_json(json!({ "type": "A", "name": "home.example.com", "content": "1.2.3.4" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "id": "home", "name": "home.example.com", "type": "A", "content": "1.2.3.4", "ttl": 1 },
+            "success": true,
+            "errors": [],
+            "messages": []
+        })))
+        .expect(1)
+        .mount(&harness.cloudflare)
+        .await;
+
+    let mut updater = harness.updater_with(r#"
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+
+        [hostnames]
+        zone = "example.com"
+        names = ["@", "home"]
+    "#);
+
+    updater.tick().await.unwrap();
+
+    assert_eq!(harness.handle.status().state.records.len(), 2);
+}
+
+#[tokio::test]
+async fn only_reports_drift_when_observing() {
+    let harness = Harness::start().await;
+    let notifications = MockServer::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("5.6.7.8")).await;
+    Mock::given(method("GET"))
+        .respond_with(record_response("1.2.3.4"))
+        .expect(2)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(record_response("5.6.7.8"))
+        .expect(0)
+        .mount(&harness.cloudflare)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "title": "home.example.com does not point to the public IP" })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&notifications)
+        .await;
+
+    let mut updater = harness.updater_with(&format!(r#"
+        [general]
+        observe_only = true
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+
+        [notifications]
+        urls = ["{}"]
+    "#, notifications.uri().replace("http://", "json://")));
+
+    // The record is fetched at every check, and the drift only notified once.
+    updater.tick().await.unwrap();
+    updater.tick().await.unwrap();
+
+    let state = harness.handle.status().state;
+    assert_eq!(state.records[0].drift.as_deref(), Some("5.6.7.8"));
+    assert_eq!(state.stats.updates, 0);
+
+    for _ in 0..50 {
+        if !notifications.received_requests().await.unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn waits_longer_between_checks_on_a_metered_link() {
+    let harness = Harness::start().await;
+    harness.public_ip(ResponseTemplate::new(200).set_body_string("1.2.3.4")).await;
+    Mock::given(method("GET"))
+        .and(path("/zones/zone/dns_records/record"))
+        .respond_with(record_response("1.2.3.4"))
+        .mount(&harness.cloudflare)
+        .await;
+
+    let config = r#"
+        [general]
+        wait_duration = 60
+
+        [low_bandwidth]
+        enabled = true
+        max_wait_duration = 300
+
+        [cloudflare]
+        zone_id = "zone"
+        api_token = "token"
+        dns_record_id = "record"
+    "#;
+
+    let mut updater = harness.updater_with(config);
+    for _ in 0..5 {
+        updater.tick().await.unwrap();
+    }
+
+    let sleeps: Vec<u64> = harness.clock.sleeps().iter().map(Duration::as_secs).collect();
+    assert_eq!(sleeps, vec![60, 60, 120, 240, 300]);
+
+    let budget = harness.handle.status().state.requests_today;
+    assert_eq!(budget.date, Some(harness.clock.now_utc().date().to_string()));
+    assert_eq!(budget.used, harness.ip.received_requests().await.unwrap().len() as u64);
+    assert_eq!(budget.limit, 500);
+}